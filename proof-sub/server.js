@@ -11,7 +11,7 @@ const port = 3001;
 
 // Middleware
 app.use(cors());
-app.use(express.json());
+app.use(express.json({ limit: '10mb' })); // proofs are larger than the 100kb default
 
 const API_URL = 'https://relayer-api.horizenlabs.io/api/v1';
 const API_KEY = "141fdb518133e2ea7256b77337956ced62e295bc";
@@ -19,14 +19,12 @@ const API_KEY = "141fdb518133e2ea7256b77337956ced62e295bc";
 // Endpoint to verify and aggregate a proof
 app.post('/verify-proof', async (req, res) => {
     try {
-        // Read proof.json from the script directory
-        const proofPath = '../script/proof.json';
-        if (!fs.existsSync(proofPath)) {
-            return res.status(404).json({ error: 'proof.json not found' });
+        // The CLI posts the proof artifact it just generated
+        const proof = req.body;
+        if (!proof || !proof.proof || !proof.pub_inputs || !proof.image_id) {
+            return res.status(400).json({ error: 'Request body is not a proof artifact' });
         }
 
-        const proof = JSON.parse(fs.readFileSync(proofPath, 'utf8'));
-
         const params = {
             "proofType": "sp1",
             "vkRegistered": false,
@@ -0,0 +1,177 @@
+//! Exact ETH amounts
+//!
+//! Amounts are kept in wei as `U256` and parsed from decimal strings with an
+//! optional unit: `0.1eth`, `250 gwei`, `1000wei`. A plain number is in ETH.
+//! Parsing never goes through floating point, and amounts finer than one wei
+//! are rejected instead of being rounded.
+
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Decimals of one ETH
+const ETH_DECIMALS: usize = 18;
+
+/// Units accepted after an amount, with their number of decimals
+const UNITS: &[(&str, usize)] = &[
+    ("eth", ETH_DECIMALS),
+    ("ether", ETH_DECIMALS),
+    ("gwei", 9),
+    ("wei", 0),
+];
+
+/// Amount of ETH, exact to the wei
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Amount(U256);
+
+impl Amount {
+    /// Zero wei
+    pub const ZERO: Amount = Amount(U256::ZERO);
+
+    /// Create an amount from wei
+    pub const fn from_wei(wei: U256) -> Self {
+        Amount(wei)
+    }
+
+    /// Get the amount in wei
+    pub const fn wei(self) -> U256 {
+        self.0
+    }
+
+    /// Check whether the amount is zero
+    pub fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl From<U256> for Amount {
+    fn from(wei: U256) -> Self {
+        Amount(wei)
+    }
+}
+
+impl FromStr for Amount {
+    type Err = String;
+
+    /// Parse a decimal amount with an optional `eth`, `gwei` or `wei` unit
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let lower = input.to_ascii_lowercase();
+        let split = lower
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(lower.len());
+        let (number, unit) = (lower[..split].trim(), lower[split..].trim());
+
+        let decimals = if unit.is_empty() {
+            ETH_DECIMALS
+        } else {
+            UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .map(|&(_, decimals)| decimals)
+                .ok_or_else(|| format!("Unknown unit '{}' (use eth, gwei or wei)", unit))?
+        };
+
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) {
+            return Err(format!("Invalid amount '{}'", input));
+        }
+
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > decimals {
+            return Err(format!("Amount '{}' is more precise than one wei", input));
+        }
+
+        let overflow = || format!("Amount '{}' is too large", input);
+        let padded = format!("{}{:0<width$}", whole, fraction, width = decimals);
+        let wei = if padded.trim_start_matches('0').is_empty() {
+            U256::ZERO
+        } else {
+            U256::from_str_radix(&padded, 10).map_err(|_| overflow())?
+        };
+        Ok(Amount(wei))
+    }
+}
+
+impl fmt::Display for Amount {
+    /// Show the amount in ETH with every significant decimal, e.g. `0.1 ETH`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = U256::from(10u64).pow(U256::from(ETH_DECIMALS));
+        let whole = self.0 / unit;
+        let fraction = self.0 % unit;
+        if fraction.is_zero() {
+            return write!(f, "{} ETH", whole);
+        }
+        let fraction = format!("{:0>width$}", fraction.to_string(), width = ETH_DECIMALS);
+        write!(f, "{}.{} ETH", whole, fraction.trim_end_matches('0'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wei(amount: &str) -> U256 {
+        amount.parse::<Amount>().unwrap().wei()
+    }
+
+    #[test]
+    fn test_parse_units_exactly() {
+        let one_eth = U256::from(10u64).pow(U256::from(18u64));
+        assert_eq!(wei("0.1eth"), one_eth / U256::from(10u64));
+        assert_eq!(wei("0.1"), one_eth / U256::from(10u64));
+        assert_eq!(
+            wei("1.5 ETH"),
+            one_eth * U256::from(3u64) / U256::from(2u64)
+        );
+        assert_eq!(wei("2ether"), one_eth * U256::from(2u64));
+        assert_eq!(wei("250gwei"), U256::from(250_000_000_000u64));
+        assert_eq!(wei("0.5gwei"), U256::from(500_000_000u64));
+        assert_eq!(wei("1000wei"), U256::from(1000u64));
+        assert_eq!(wei("1.000wei"), U256::from(1u64));
+        assert_eq!(wei(".25eth"), one_eth / U256::from(4u64));
+        assert_eq!(wei("0.000000000000000001"), U256::from(1u64));
+        assert_eq!(wei("0"), U256::ZERO);
+    }
+
+    #[test]
+    fn test_reject_invalid_amounts() {
+        for input in [
+            "",
+            "eth",
+            ".",
+            "-1",
+            "1e18",
+            "1.2.3",
+            "0.1btc",
+            "1 0",
+            "0.5wei",
+            "1.0000000001gwei",
+            "0.0000000000000000001",
+        ] {
+            assert!(input.parse::<Amount>().is_err(), "{} parsed", input);
+        }
+        let too_large = format!("{}wei", "9".repeat(80));
+        assert!(too_large.parse::<Amount>().is_err());
+    }
+
+    #[test]
+    fn test_display_roundtrip() {
+        for (input, shown) in [
+            ("0.1", "0.1 ETH"),
+            ("12", "12 ETH"),
+            ("1wei", "0.000000000000000001 ETH"),
+            ("250gwei", "0.00000025 ETH"),
+            ("0", "0 ETH"),
+        ] {
+            let amount: Amount = input.parse().unwrap();
+            assert_eq!(amount.to_string(), shown);
+            assert_eq!(shown.parse::<Amount>().unwrap(), amount);
+        }
+    }
+}
@@ -3,14 +3,19 @@
 //! This tool allows you to:
 //! - Burn ETH to create private burn addresses
 //! - Generate zero-knowledge proofs for minting private coins
+//! - Disclose encrypted coins to auditors and verify such disclosures
+//! - Hand coins to other wallets as portable notes
 //!
 //! Usage:
 //! ```shell
 //! # Burn coins
-//! cargo run --bin darkmint -- burn --amount 0.1 --priv-src $PRIVATE_KEY
+//! cargo run --bin darkmint -- burn --amount 0.1 --keystore $KEYSTORE
 //!
-//! # Generate minting proof
-//! cargo run --bin darkmint -- prove --dst-addr $DEST --src-burn-addr $BURN_ADDR --priv-src $PRIVATE_KEY
+//! # Generate minting proof on another network profile
+//! cargo run --bin darkmint -- --network local prove --dst-addr $DEST --src-burn-addr $BURN_ADDR --keystore $KEYSTORE
+//!
+//! # Disclose an encrypted coin to an auditor
+//! cargo run --bin darkmint -- disclose --coin 0
 //! ```
 
 use clap::{Parser, Subcommand};
@@ -19,7 +24,12 @@ use console::Term;
 use std::process;
 
 // Import CLI command modules
-use fibonacci_script::commands::{BurnCommand, ProveCommand};
+use fibonacci_script::commands::{
+    BurnCommand, DiscloseCommand, NoteCommand, OfflineCommand, ProveCommand,
+    VerifyDisclosureCommand, WalletCommand,
+};
+use fibonacci_script::network::NETWORK_ENV;
+use fibonacci_script::wallet_location::WALLET_NAME_ENV;
 
 #[derive(Parser)]
 #[command(
@@ -38,6 +48,14 @@ struct Cli {
     /// Enable verbose logging
     #[arg(long, short = 'v', global = true)]
     verbose: bool,
+
+    /// Name of the wallet to use (defaults to the configured default wallet)
+    #[arg(long, short = 'w', global = true)]
+    wallet: Option<String>,
+
+    /// Network profile to use (defaults to the configured default network)
+    #[arg(long, global = true)]
+    network: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -47,6 +65,16 @@ enum Commands {
     Burn(BurnCommand),
     /// 🔮 Generate zero-knowledge proof for minting private coins
     Prove(ProveCommand),
+    /// 🔍 Disclose the amount and block of an encrypted coin to an auditor
+    Disclose(DiscloseCommand),
+    /// 🔍 Verify a coin disclosure received from a wallet owner
+    VerifyDisclosure(VerifyDisclosureCommand),
+    /// 👛 Manage the local wallet
+    Wallet(WalletCommand),
+    /// 📝 Export and import coins as portable notes
+    Note(NoteCommand),
+    /// 🧊 Burn and prove with the wallet and keys on an air-gapped machine
+    Offline(OfflineCommand),
 }
 
 fn print_banner() {
@@ -107,6 +135,15 @@ async fn main() {
     // Setup logging
     setup_logging(cli.verbose);
 
+    // The selected wallet is handed to library code and worker processes
+    // through the environment
+    if let Some(wallet) = &cli.wallet {
+        std::env::set_var(WALLET_NAME_ENV, wallet);
+    }
+    if let Some(network) = &cli.network {
+        std::env::set_var(NETWORK_ENV, network);
+    }
+
     // Print banner for all commands
     print_banner();
 
@@ -114,6 +151,11 @@ async fn main() {
     let result = match cli.command {
         Commands::Burn(burn_cmd) => burn_cmd.execute().await,
         Commands::Prove(prove_cmd) => prove_cmd.execute().await,
+        Commands::Disclose(disclose_cmd) => disclose_cmd.execute().await,
+        Commands::VerifyDisclosure(verify_cmd) => verify_cmd.execute().await,
+        Commands::Wallet(wallet_cmd) => wallet_cmd.execute().await,
+        Commands::Note(note_cmd) => note_cmd.execute().await,
+        Commands::Offline(offline_cmd) => offline_cmd.execute().await,
     };
 
     // Handle results with beautiful error messages
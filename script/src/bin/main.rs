@@ -10,10 +10,25 @@
 //! RUST_LOG=info cargo run --release -- --prove
 //! ```
 use ark_ff::{BigInteger, PrimeField};
-use fibonacci_script::{burn_cmd, mint_cmd, BurnAddress, Coin, MintContext};
+use fibonacci_script::amount::Amount;
+use fibonacci_script::burn::{check_split_burn, BurnChecks};
+use fibonacci_script::burn_index::DEFAULT_GAP_LIMIT;
+use fibonacci_script::denomination::{split_amount, split_remainder, warn_unique_amounts};
+use fibonacci_script::linkability::{check_burn_linkability, check_mint_linkability};
+use fibonacci_script::mint::{
+    is_canonical, mint_calldata, preview_mint, refetch_proof_inputs, MAX_REPROVE_ATTEMPTS,
+    MINT_GAS_LIMIT,
+};
+use fibonacci_script::network::{NetworkConfig, NetworkProfile, ProofBlock};
+use fibonacci_script::offline::{self, ProvingRequest};
+use fibonacci_script::signer::{self, FEE_PAYER_KEY_ENV, KEYSTORE_PASSWORD_ENV};
+use fibonacci_script::simulate::simulate_mint;
+use fibonacci_script::{
+    burn_cmd, mint_cmd, BurnAddress, BurnContext, Coin, CoinState, MintContext, Wallet,
+};
 
 use alloy::{
-    primitives::{address, Bytes, B256, U256},
+    primitives::{Address, Bytes, B256, U256},
     providers::{Caller, Provider, ProviderBuilder},
     rpc::types::TransactionRequest,
     rpc::types::{Block, EIP1186AccountProofResponse},
@@ -24,6 +39,8 @@ use alloy::{
 use tiny_keccak::{Hasher, Keccak};
 
 use clap::Parser;
+use dialoguer::Password;
+use zeroize::Zeroizing;
 
 use rlp::RlpStream;
 use sp1_sdk::{include_elf, HashableKey, ProverClient, SP1ProofWithPublicValues, SP1Stdin};
@@ -35,6 +52,7 @@ use rustls::crypto::ring::default_provider;
 use rustls::crypto::CryptoProvider;
 use serde::{Deserialize, Serialize};
 use sp1_zkv_sdk::*; // for the ⁠ convert_to_zkv ⁠ and ⁠ hash_bytes ⁠ methods.
+use std::path::{Path, PathBuf};
 use std::{fs::File, io::Write};
 
 sol! {
@@ -83,12 +101,34 @@ struct Args {
     #[arg(long)]
     prove: bool,
 
+    /// Prove a burn from a proving request without network access
+    #[arg(long, requires = "proving_request")]
+    prove_offline: bool,
+
+    /// Proving request written by `darkmint offline proving-request`
     #[arg(long)]
-    amount: Option<f64>,
+    proving_request: Option<PathBuf>,
+
+    /// File the proof artifact is written to
+    #[arg(long, default_value = "proof.json")]
+    proof_out: PathBuf,
 
+    /// Amount to burn, e.g. 0.1eth or 250gwei
     #[arg(long)]
+    amount: Option<Amount>,
+
+    /// V3 JSON keystore of the fee payer (password from DARKMINT_KEYSTORE_PASSWORD or a prompt)
+    #[arg(long)]
+    keystore: Option<PathBuf>,
+
+    /// Raw fee payer key, only with --allow-raw-key (prefer DARKMINT_FEE_PAYER_KEY)
+    #[arg(long, requires = "allow_raw_key")]
     priv_src: Option<String>,
 
+    /// Accept a raw private key instead of a keystore
+    #[arg(long, conflicts_with = "keystore")]
+    allow_raw_key: bool,
+
     #[arg(long, default_value = "false")]
     encrypted: bool,
 
@@ -98,11 +138,61 @@ struct Args {
     #[arg(long)]
     src_burn_addr: Option<String>,
 
-    #[arg(long, default_value = "https://horizen-rpc-testnet.appchain.base.org/")]
-    provider_url: String,
-}
+    /// RPC provider URL, defaults to the endpoints of the network profile
+    #[arg(long)]
+    provider_url: Option<String>,
+
+    /// Network profile (defaults to DARKMINT_NETWORK or the configured default)
+    #[arg(long, env = "DARKMINT_NETWORK")]
+    network: Option<String>,
+
+    #[arg(long)]
+    label: Option<String>,
+
+    #[arg(long, default_value_t = DEFAULT_GAP_LIMIT)]
+    gap_limit: u64,
+
+    /// Block to prove against: latest, safe, finalized or a number of confirmations
+    /// (defaults to the proof_block of the network profile)
+    #[arg(long)]
+    proof_block: Option<ProofBlock>,
+
+    /// Send the burn as a legacy transaction instead of an EIP-1559 one
+    #[arg(long)]
+    legacy: bool,
+
+    /// Simulate the burn or mint without sending, proving or recording anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Split the burn into standard denominations, each to a fresh burn address
+    #[arg(long)]
+    split: bool,
+
+    /// Refuse to broadcast burns and mints that anything links, instead of warning
+    #[arg(long)]
+    strict_privacy: bool,
+
+    /// Burn even if the chain is not the network's or lacks the DarkMint contract
+    #[arg(long)]
+    skip_chain_check: bool,
+
+    /// Burn amounts worth less than the gas of minting them
+    #[arg(long)]
+    skip_amount_check: bool,
 
-pub const DOMAIN_ID: u64 = 113;
+    /// Accept burn addresses that have code
+    #[arg(long)]
+    skip_code_check: bool,
+
+    /// Accept burn addresses whose nullifier was already used on the contract
+    #[arg(long)]
+    skip_nullifier_check: bool,
+
+    /// Aggregation receipt of the proof, to simulate checkHash in a dry run
+    #[arg(long, requires = "dry_run")]
+    aggregation: Option<PathBuf>,
+}
 
 // Struct of the output we need
 
@@ -184,6 +274,355 @@ fn calculate_lower_layer_prefix(
         hex::encode(account_proof)
     ))
 }
+/// Load the fee payer from a keystore, or from a raw key when explicitly allowed
+fn fee_payer(args: &Args) -> Result<PrivateKeySigner, Box<dyn std::error::Error>> {
+    if let Some(keystore) = &args.keystore {
+        let password = match std::env::var(KEYSTORE_PASSWORD_ENV) {
+            Ok(password) => Zeroizing::new(password),
+            Err(_) => Zeroizing::new(
+                Password::new()
+                    .with_prompt("Keystore password")
+                    .interact()?,
+            ),
+        };
+        return signer::decrypt_keystore(keystore, &password);
+    }
+    if !args.allow_raw_key {
+        return Err("--keystore is required (raw keys need --allow-raw-key)".into());
+    }
+    let key = match &args.priv_src {
+        Some(key) => Zeroizing::new(key.clone()),
+        None => Zeroizing::new(
+            std::env::var(FEE_PAYER_KEY_ENV)
+                .map_err(|_| format!("--priv-src or {} is required", FEE_PAYER_KEY_ENV))?,
+        ),
+    };
+    signer::parse_raw_key(&key)
+}
+
+/// Parse the account the minted tokens are sent to
+fn dst_addr(args: &Args) -> Result<Address, Box<dyn std::error::Error>> {
+    args.dst_addr
+        .as_deref()
+        .ok_or("--dst-addr is required when not using --burn")?
+        .parse()
+        .map_err(|e| format!("Invalid --dst-addr: {e}").into())
+}
+
+/// Write the program inputs of a burn
+///
+/// # Arguments
+/// * `burn_addr` - Burn address with its preimage
+/// * `proof` - Account proof of the burn address
+/// * `block` - Block the account proof is rooted in
+/// * `coin` - Coin being minted, for its salt and encryption mode
+///
+/// # Returns
+/// * Result containing the program inputs or an error
+fn proof_stdin(
+    burn_addr: &BurnAddress,
+    proof: &EIP1186AccountProofResponse,
+    block: &Block,
+    coin: &Coin,
+) -> Result<SP1Stdin, Box<dyn std::error::Error>> {
+    // Calculate lower layer prefix from the MPT proof
+    let (lower_layer_prefix_len, lower_layer_prefix) = calculate_lower_layer_prefix(proof)?;
+
+    // Setup the inputs.
+    let mut stdin = SP1Stdin::new();
+
+    println!("burn_addr.preimage: {:?}", burn_addr.preimage);
+    println!("lower_layer_prefix_len: {lower_layer_prefix_len:?}");
+    println!("lower_layer_prefix: {lower_layer_prefix:?}");
+    println!("proof.nonce: {:?}", proof.nonce);
+    println!("proof.balance: {:?}", proof.balance);
+    println!("proof.storage_hash: {:?}", proof.storage_hash);
+    println!("proof.code_hash: {:?}", proof.code_hash);
+
+    let preimage = burn_addr.preimage.into_bigint();
+    println!("preimage: {preimage:?}");
+
+    stdin.write(&preimage.to_bytes_be());
+    stdin.write(&lower_layer_prefix_len);
+    stdin.write(&lower_layer_prefix);
+    stdin.write(&proof.nonce);
+    stdin.write(&(proof.balance.to::<u128>())); // Convert U256 to u128
+    stdin.write(&proof.storage_hash.0); // Convert B256 to [u8; 32]
+    stdin.write(&proof.code_hash.0); // Convert B256 to [u8; 32]
+    stdin.write(&proof.account_proof);
+    stdin.write(&block.header.state_root.0); // Convert B256 to [u8; 32]
+    stdin.write(&coin.salt);
+    stdin.write(&coin.encrypted);
+
+    Ok(stdin)
+}
+
+/// Run the SP1 prover on a burn and write the zkVerify proof artifact
+///
+/// # Arguments
+/// * `burn_addr` - Burn address with its preimage
+/// * `proof` - Account proof of the burn address
+/// * `block` - Block the account proof is rooted in
+/// * `coin` - Coin being minted, for its salt and encryption mode
+/// * `out` - File the proof artifact is written to
+///
+/// # Returns
+/// * Result containing the proof artifact or an error
+fn generate_proof(
+    burn_addr: &BurnAddress,
+    proof: &EIP1186AccountProofResponse,
+    block: &Block,
+    coin: &Coin,
+    out: &Path,
+) -> Result<Output, Box<dyn std::error::Error>> {
+    let stdin = proof_stdin(burn_addr, proof, block, coin)?;
+
+    // Setup the prover client.
+    let client = ProverClient::from_env();
+
+    // // Setup the program for proving.
+    let (pk, vk) = client.setup(FIBONACCI_ELF);
+    println!("Generating Proof .......");
+
+    // Generate the proof
+    let proof = client
+        .prove(&pk, &stdin)
+        .compressed()
+        .run()
+        .map_err(|e| format!("Failed to generate proof: {e}"))?;
+    println!("🎉🎉 Proof Generated Successfully 🎉🎉");
+    println!("Converting proof and vk into a zkVerify-compatible proof .....");
+    // Convert proof and vk into a zkVerify-compatible proof.
+    let SP1ZkvProofWithPublicValues {
+        proof: shrunk_proof,
+        public_values,
+    } = client
+        .convert_proof_to_zkv(proof, Default::default())
+        .map_err(|e| format!("Failed to convert proof for zkVerify: {e}"))?;
+    let vk_hash = vk.hash_bytes();
+
+    // Serialize the proof
+    let serialized_proof = bincode::serde::encode_to_vec(&shrunk_proof, bincode::config::legacy())?;
+
+    // Convert to required struct
+    let output = Output {
+        proof: to_hex_with_prefix(&serialized_proof),
+        image_id: to_hex_with_prefix(&vk_hash),
+        pub_inputs: to_hex_with_prefix(&public_values),
+    };
+
+    // Convert to JSON and store in the file
+    let json_string = serde_json::to_string_pretty(&output)?;
+
+    let mut file = File::create(out)?;
+    file.write_all(json_string.as_bytes())?;
+    println!("Proof saved to {}", out.display());
+
+    Ok(output)
+}
+
+/// Generate the proof of a coin, marking the coin failed when proving fails
+///
+/// # Arguments
+/// * `wallet` - Wallet holding the coin
+/// * `burn_addr` - Burn address with its preimage
+/// * `proof` - Account proof of the burn address
+/// * `block` - Block the account proof is rooted in
+/// * `coin` - Coin being minted, for its salt and encryption mode
+/// * `out` - File the proof artifact is written to
+///
+/// # Returns
+/// * Result containing the proof artifact or an error
+fn prove_coin(
+    wallet: &mut Wallet,
+    burn_addr: &BurnAddress,
+    proof: &EIP1186AccountProofResponse,
+    block: &Block,
+    coin: &Coin,
+    out: &Path,
+) -> Result<Output, Box<dyn std::error::Error>> {
+    generate_proof(burn_addr, proof, block, coin, out).map_err(|e| {
+        let reason = e.to_string();
+        if let Err(update) =
+            wallet.update_coin_for_burn_address(burn_addr.address, |coin| coin.fail(reason.clone()))
+        {
+            eprintln!("Failed to record the proving error on the coin: {update}");
+        }
+        e
+    })
+}
+
+/// Prove a burn from a proving request, without network access
+fn prove_offline(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let path = args
+        .proving_request
+        .as_ref()
+        .ok_or("--proving-request is required with --prove-offline")?;
+    let request: ProvingRequest = offline::read_bundle(path)?;
+
+    let mut wallet = Wallet::open_selected()?;
+    let (burn_addr, coin) =
+        offline::start_offline_proof(&mut wallet, &request, args.encrypted, args.gap_limit)?;
+    println!("Proof block: {}", request.block.header.number);
+
+    prove_coin(
+        &mut wallet,
+        &burn_addr,
+        &request.account_proof,
+        &request.block,
+        &coin,
+        &args.proof_out,
+    )?;
+    let proof_path = std::fs::canonicalize(&args.proof_out)?;
+    wallet.update_coin_for_burn_address(burn_addr.address, |coin| {
+        coin.transition(CoinState::Proven)?;
+        coin.proof_path = Some(proof_path.display().to_string());
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Parse the Merkle path of an aggregation receipt
+///
+/// # Arguments
+/// * `agg` - Aggregation receipt of the proof
+///
+/// # Returns
+/// * Result containing the path hashes, or an error for malformed entries
+fn merkle_path(agg: &AggregatorInput) -> Result<Vec<B256>, Box<dyn std::error::Error>> {
+    agg.merkleProof
+        .iter()
+        .map(|hash| {
+            hash.parse::<B256>()
+                .map_err(|e| format!("Invalid Merkle path entry '{hash}': {e}").into())
+        })
+        .collect()
+}
+
+/// Build the checkHash call of an aggregated proof
+///
+/// # Arguments
+/// * `agg` - Aggregation receipt of the proof
+/// * `hash_bytes` - Public values of the proof
+/// * `vkey` - Verification key hash of the program
+/// * `domain_id` - zkVerify domain the proof was aggregated in
+///
+/// # Returns
+/// * Result containing the checkHash call, or an error for a malformed receipt
+fn check_hash_call(
+    agg: &AggregatorInput,
+    hash_bytes: Vec<u8>,
+    vkey: B256,
+    domain_id: u64,
+) -> Result<DarkMint::checkHashCall, Box<dyn std::error::Error>> {
+    Ok(DarkMint::checkHashCall {
+        _hash: Bytes::from(hash_bytes),
+        _aggregationId: U256::from(agg.aggregationId),
+        _domainId: U256::from(domain_id),
+        _merklePath: merkle_path(agg)?,
+        _leafCount: U256::from(agg.numberOfLeaves),
+        _index: U256::from(agg.leafIndex),
+        _vkey: vkey,
+    })
+}
+
+/// Simulate proving and minting a burn
+///
+/// Runs the program in the SP1 executor, which gives the exact public values
+/// without generating a proof, then simulates the contract calls. Nothing is
+/// submitted, sent or recorded in the wallet: it is read into memory, so even
+/// layout upgrades are not saved.
+async fn dry_run_mint(
+    args: &Args,
+    network: &NetworkProfile,
+    provider_url: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fee_payer = fee_payer(args)?;
+    let dst_addr = dst_addr(args)?;
+    let src_burn_addr = args
+        .src_burn_addr
+        .as_deref()
+        .ok_or("--src-burn-addr is required when not using --burn")?
+        .parse()?;
+
+    let mut wallet = Wallet::open_selected_in_memory()?;
+    let (burn_addr, block, proof, coin) = preview_mint(
+        &mut wallet,
+        provider_url,
+        src_burn_addr,
+        args.encrypted,
+        args.gap_limit,
+        args.proof_block.unwrap_or(network.proof_block_policy()),
+    )
+    .await?;
+    println!("Dry run: nothing is proven, submitted, sent or recorded");
+    println!("Burn address: {}", burn_addr.address);
+    println!("Amount: {}", Amount::from_wei(coin.amount));
+    println!("Proof block: {}", block.header.number);
+
+    let stdin = proof_stdin(&burn_addr, &proof, &block, &coin)?;
+    let client = ProverClient::builder().cpu().build();
+    let (public_values, report) = client
+        .execute(FIBONACCI_ELF, &stdin)
+        .run()
+        .map_err(|e| format!("Program execution failed: {e}"))?;
+    println!(
+        "Program executed in {} cycles",
+        report.total_instruction_count()
+    );
+    let public_values = public_values.to_vec();
+    println!("pub_inputs: {}", to_hex_with_prefix(&public_values));
+
+    // checkHash needs the aggregation the relayer reports once the proof is aggregated
+    let check_hash_input = match &args.aggregation {
+        Some(path) => {
+            let agg: AggregatorInput = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            let (_, vk) = client.setup(FIBONACCI_ELF);
+            let vkey = B256::from(vk.hash_bytes());
+            let call = check_hash_call(&agg, public_values.clone(), vkey, network.domain_id)?;
+            Some(Bytes::from(call.abi_encode()))
+        }
+        None => None,
+    };
+
+    let simulation = simulate_mint(
+        provider_url,
+        network.contract_address,
+        fee_payer.address(),
+        dst_addr,
+        coin.amount,
+        &public_values,
+        check_hash_input,
+    )
+    .await?;
+    simulation.print();
+
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+    check_mint_linkability(
+        &wallet,
+        &provider,
+        fee_payer.address(),
+        dst_addr,
+        burn_addr.address,
+        coin.amount,
+        args.strict_privacy,
+    )
+    .await?;
+    if simulation.nullifier != burn_addr.nullifier() {
+        println!(
+            "⚠️ The program committed nullifier {}, the wallet expects {}",
+            simulation.nullifier,
+            burn_addr.nullifier()
+        );
+    }
+    if simulation.succeeds() {
+        println!("✅ The mint is expected to go through");
+    } else {
+        println!("❌ The mint is not expected to go through");
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize rustls crypto provider
@@ -196,31 +635,139 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse the command line arguments.
     let args = Args::parse();
 
+    // Offline proving never touches the network
+    if args.prove_offline {
+        return prove_offline(&args);
+    }
+
+    // Contract, relayer, zkVerify domain and RPC endpoints come from the network profile
+    let network = NetworkConfig::load()?.profile(args.network.as_deref())?;
+    let provider_url = network.rpc_url(args.provider_url.as_deref()).await?;
+
     if args.burn {
-        let amount = args.amount.expect("--amount is required when using --burn");
-        let priv_src = args
-            .priv_src
-            .expect("--priv-src is required when using --burn");
-        burn_cmd(amount, priv_src).await?;
+        let amount = args
+            .amount
+            .ok_or("--amount is required when using --burn")?;
+        let fee_payer = fee_payer(&args)?;
+        let mut wallet = Wallet::open_selected()?;
+        let amounts = if args.split {
+            split_amount(amount.wei())?
+        } else {
+            vec![amount.wei()]
+        };
+        if args.split {
+            let parts: Vec<String> = amounts
+                .iter()
+                .map(|&part| Amount::from_wei(part).to_string())
+                .collect();
+            println!(
+                "Splitting {} into {} burns: {}",
+                amount,
+                amounts.len(),
+                parts.join(", ")
+            );
+            let remainder = split_remainder(amount.wei());
+            if !remainder.is_zero() {
+                println!(
+                    "⚠️ {} is below the smallest denomination and burned on its own, its amount stands out",
+                    Amount::from_wei(remainder)
+                );
+            }
+        }
+
+        // Amounts few past mints share link the burn to its mint
+        warn_unique_amounts(
+            &provider_url,
+            network.contract_address,
+            network.deployment_block,
+            &amounts,
+        )
+        .await?;
+
+        // Accounts and amounts that link the burn to the other coins of the wallet
+        let provider = ProviderBuilder::new().connect(&provider_url).await?;
+        check_burn_linkability(
+            &wallet,
+            &provider,
+            fee_payer.address(),
+            &amounts,
+            args.strict_privacy,
+        )
+        .await?;
+
+        let context = |amount: U256, checks: BurnChecks| BurnContext {
+            amount: Amount::from_wei(amount),
+            signer: fee_payer.clone(),
+            label: args.label.clone(),
+            gap_limit: args.gap_limit,
+            confirmations: network.confirmations,
+            legacy: args.legacy,
+            dry_run: args.dry_run,
+            contract_address: network.contract_address,
+            chain_id: network.chain_id,
+            checks,
+        };
+        let mut checks = BurnChecks {
+            chain: !args.skip_chain_check,
+            min_amount: !args.skip_amount_check,
+            code: !args.skip_code_check,
+            nullifier: !args.skip_nullifier_check,
+        };
+
+        // A split is checked as a whole, so that it is never left half sent
+        if amounts.len() > 1 {
+            let split = context(amount.wei(), checks);
+            check_split_burn(&mut wallet, &provider_url, &split, &amounts).await?;
+            // The parts passed the amount check together, fee moves between
+            // the parts must not stop the split halfway
+            checks.min_amount = false;
+        }
+
+        for (part, &part_amount) in amounts.iter().enumerate() {
+            if amounts.len() > 1 {
+                println!("Burn {} of {}", part + 1, amounts.len());
+            }
+            burn_cmd(&mut wallet, &provider_url, context(part_amount, checks)).await?;
+        }
+    } else if args.prove && args.dry_run {
+        dry_run_mint(&args, &network, &provider_url).await?;
     } else if args.prove {
-        let dst_addr = args
-            .dst_addr
-            .expect("--dst-addr is required when not using --burn");
-        let src_burn_addr = args
+        let fee_payer = fee_payer(&args)?;
+        let proof_block = args.proof_block.unwrap_or(network.proof_block_policy());
+        let dst_addr = dst_addr(&args)?;
+        let src_burn_addr: Address = args
             .src_burn_addr
-            .expect("--src-burn-addr is required when not using --burn");
-        let priv_src = args
-            .priv_src
-            .expect("--priv-src is required when not using --burn");
+            .as_deref()
+            .ok_or("--src-burn-addr is required when not using --burn")?
+            .parse()
+            .map_err(|e| format!("Invalid --src-burn-addr: {e}"))?;
+
+        // The wallet stays open (and locked against other processes) until the coin is minted
+        let mut wallet = Wallet::open_selected()?;
+
+        // What links the mint to its burn is checked before proving
+        let provider = ProviderBuilder::new().connect(&provider_url).await?;
+        check_mint_linkability(
+            &wallet,
+            &provider,
+            fee_payer.address(),
+            dst_addr,
+            src_burn_addr,
+            provider.get_balance(src_burn_addr).await?,
+            args.strict_privacy,
+        )
+        .await?;
 
         let context = MintContext {
-            src_burn_addr: src_burn_addr.parse().unwrap(),
-            dst_addr: dst_addr.parse().unwrap(),
+            src_burn_addr,
+            dst_addr,
             encrypted: args.encrypted,
-            priv_fee_payer: priv_src.parse().unwrap(),
+            fee_payer: fee_payer.clone(),
+            gap_limit: args.gap_limit,
+            proof_block,
         };
 
-        let (burn_addr, block, proof, coin, _prefix, _state_root, _postfix): (
+        let (burn_addr, mut block, mut proof, mut coin, _prefix, _state_root, _postfix): (
             BurnAddress,
             Block,
             EIP1186AccountProofResponse,
@@ -228,90 +775,71 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             Bytes,
             B256,
             Bytes,
-        ) = mint_cmd(&args.provider_url, context).await?;
-
-        // Calculate lower layer prefix from the MPT proof
-        let (lower_layer_prefix_len, lower_layer_prefix) = calculate_lower_layer_prefix(&proof)?;
-
-        // Setup the prover client.
-        let client = ProverClient::from_env();
-
-        // Setup the inputs.
-        let mut stdin = SP1Stdin::new();
-
-        println!("burn_addr.preimage: {:?}", burn_addr.preimage);
-        println!("lower_layer_prefix_len: {lower_layer_prefix_len:?}");
-        println!("lower_layer_prefix: {lower_layer_prefix:?}");
-        println!("proof.nonce: {:?}", proof.nonce);
-        println!("proof.balance: {:?}", proof.balance);
-        println!("proof.storage_hash: {:?}", proof.storage_hash);
-        println!("proof.code_hash: {:?}", proof.code_hash);
-
-        let preimage = burn_addr.preimage.into_bigint();
-        println!("preimage: {preimage:?}");
-
-        stdin.write(&preimage.to_bytes_be());
-        stdin.write(&lower_layer_prefix_len);
-        stdin.write(&lower_layer_prefix);
-        stdin.write(&proof.nonce);
-        stdin.write(&(proof.balance.to::<u128>())); // Convert U256 to u128
-        stdin.write(&proof.storage_hash.0); // Convert B256 to [u8; 32]
-        stdin.write(&proof.code_hash.0); // Convert B256 to [u8; 32]
-        stdin.write(&proof.account_proof);
-        stdin.write(&block.header.state_root.0); // Convert B256 to [u8; 32]
-        stdin.write(&coin.salt);
-        stdin.write(&coin.encrypted);
-
-        // // Setup the program for proving.
-        let (pk, vk) = client.setup(FIBONACCI_ELF);
-        println!("Generating Proof .......");
-
-        let contract_address = address!("0x157E135Fe3B6d853fb263f9E07DAda1C31361076");
-        let signer: PrivateKeySigner = priv_src.parse()?;
+        ) = mint_cmd(&mut wallet, &provider_url, context).await?;
+
+        println!(
+            "Proof block: {} ({} block)",
+            block.header.number, proof_block
+        );
 
+        // Every step of the pipeline is recorded on the coin
+        let burn_address = burn_addr.address;
+
+        let mut artifact = prove_coin(
+            &mut wallet,
+            &burn_addr,
+            &proof,
+            &block,
+            &coin,
+            &args.proof_out,
+        )?;
+
+        let contract_address = network.contract_address;
         let provider = ProviderBuilder::new()
-            .wallet(signer.clone())
-            .connect("https://horizen-rpc-testnet.appchain.base.org/")
+            .wallet(fee_payer.clone())
+            .connect(&provider_url)
             .await?;
 
-        // Generate the proof
-        let proof = client
-            .prove(&pk, &stdin)
-            .compressed()
-            .run()
-            .expect("failed to generate proof");
-        println!("🎉🎉 Proof Generated Successfully 🎉🎉");
-        println!("Converting proof and vk into a zkVerify-compatible proof .....");
-        // Convert proof and vk into a zkVerify-compatible proof.
-        let SP1ZkvProofWithPublicValues {
-            proof: shrunk_proof,
-            public_values,
-        } = client
-            .convert_proof_to_zkv(proof, Default::default())
-            .unwrap();
-        let vk_hash = vk.hash_bytes();
-
-        // Serialize the proof
-        let serialized_proof =
-            bincode::serde::encode_to_vec(&shrunk_proof, bincode::config::legacy())
-                .expect("failed to serialize proof");
-
-        // Convert to required struct
-        let output = Output {
-            proof: to_hex_with_prefix(&serialized_proof),
-            image_id: to_hex_with_prefix(&vk_hash),
-            pub_inputs: to_hex_with_prefix(&public_values),
-        };
-
-        // Convert to JSON and store in the file
-        let json_string =
-            serde_json::to_string_pretty(&output).expect("Failed to serialize to JSON.");
+        // A proof of a block that was reorged out can never be minted, prove again
+        let mut reproved = 0;
+        while !is_canonical(&provider, &block).await? {
+            if reproved == MAX_REPROVE_ATTEMPTS {
+                wallet.update_coin_for_burn_address(burn_address, |coin| {
+                    coin.fail(format!(
+                        "Proof block reorged out {} times in a row",
+                        MAX_REPROVE_ATTEMPTS + 1
+                    ))
+                })?;
+                return Err(
+                    "Proof blocks keep getting reorged out, try a deeper --proof-block".into(),
+                );
+            }
+            reproved += 1;
+            println!(
+                "⚠️ Proof block {} ({}) was reorged out, proving again against a new {} block",
+                block.header.number, block.header.hash, proof_block
+            );
+            (block, proof, coin) =
+                refetch_proof_inputs(&mut wallet, &provider, &burn_addr, proof_block).await?;
+            println!("Proof block: {}", block.header.number);
+            artifact = prove_coin(
+                &mut wallet,
+                &burn_addr,
+                &proof,
+                &block,
+                &coin,
+                &args.proof_out,
+            )?;
+        }
 
-        let mut file = File::create("proof.json").unwrap();
-        file.write_all(json_string.as_bytes()).unwrap();
-        println!("Proof saved to proof.json");
+        let proof_path = std::fs::canonicalize(&args.proof_out)?;
+        wallet.update_coin_for_burn_address(burn_address, |coin| {
+            coin.transition(CoinState::Proven)?;
+            coin.proof_path = Some(proof_path.display().to_string());
+            Ok(())
+        })?;
 
-        // Submit proof to API
+        // Submit the proof of the canonical block, as generated in this run
         println!("Submitting proof to API for verification and aggregation...");
 
         // Call the proof verification API with extended timeout (3 minutes)
@@ -319,50 +847,60 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .timeout(std::time::Duration::from_secs(180)) // 3 minutes timeout
             .build()?;
         let response = client
-            .post("http://localhost:3001/verify-proof")
+            .post(&network.relayer_url)
+            .json(&artifact)
             .send()
             .await?;
 
-        if response.status().is_success() {
+        let agg: AggregatorInput = if response.status().is_success() {
             let result: serde_json::Value = response.json().await?;
             println!("Proof verification and aggregation completed successfully!");
-            println!("Result: {}", serde_json::to_string_pretty(&result).unwrap());
+            println!("Result: {}", serde_json::to_string_pretty(&result)?);
+
+            let job_id = result
+                .get("jobId")
+                .or_else(|| result.get("job_id"))
+                .and_then(|id| id.as_str())
+                .map(str::to_string);
+            wallet.update_coin_for_burn_address(burn_address, |coin| {
+                coin.transition(CoinState::Submitted)?;
+                coin.zkverify_job_id = job_id;
+                Ok(())
+            })?;
+
+            // The relayer answers once the proof is aggregated, with its receipt
+            let aggregation = result
+                .get("aggregationData")
+                .cloned()
+                .ok_or("Relayer response has no aggregationData")?;
+            serde_json::from_value(aggregation)?
         } else {
             let error_text = response.text().await?;
             eprintln!("Error calling proof verification API: {error_text}");
+            wallet.update_coin_for_burn_address(burn_address, |coin| {
+                coin.fail(format!("Proof submission failed: {error_text}"))
+            })?;
             return Err("Failed to verify and aggregate proof".into());
-        }
-
-        let aggregator_json =
-            std::fs::read_to_string("/Users/shivanshgupta/Desktop/DarkMint/proof-sub/aggregation.json")?;
-        let agg: AggregatorInput = serde_json::from_str(&aggregator_json)?;
-
-        let proof_json =
-            std::fs::read_to_string("/Users/shivanshgupta/Desktop/DarkMint/script/proof.json")?;
-        let proof: Output = serde_json::from_str(&proof_json)?;
+        };
+        wallet.update_coin_for_burn_address(burn_address, |coin| {
+            coin.transition(CoinState::Aggregated)
+        })?;
 
         // Convert to correct types for Solidity function - matching Remix format
         // The hash should be the raw public values, not ABI encoded
-        let hash_hex = proof.pub_inputs.trim_start_matches("0x");
+        let hash_hex = artifact.pub_inputs.trim_start_matches("0x");
         let hash_bytes = hex::decode(hash_hex)?;
 
         let aggregation_id = U256::from(agg.aggregationId);
-        let domain_id = U256::from(DOMAIN_ID);
-
-        let merkle_path: Vec<alloy::primitives::B256> = agg
-            .merkleProof
-            .iter()
-            .map(|s| {
-                alloy::primitives::B256::from_slice(
-                    &hex::decode(s.trim_start_matches("0x")).unwrap(),
-                )
-            })
-            .collect();
+        let domain_id = U256::from(network.domain_id);
+
+        let merkle_path = merkle_path(&agg)?;
         let leaf_count = U256::from(agg.numberOfLeaves);
         let index = U256::from(agg.leafIndex);
-        let vkey = alloy::primitives::B256::from_slice(
-            &hex::decode(proof.image_id.trim_start_matches("0x")).unwrap(),
-        );
+        let vkey: B256 = artifact
+            .image_id
+            .parse()
+            .map_err(|e| format!("Invalid verification key hash: {e}"))?;
 
         println!("Calling checkhash with:");
         println!("  hash (bytes): 0x{}", hex::encode(&hash_bytes));
@@ -384,15 +922,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("  uint256 _index: \"{}\"", index);
         println!("  bytes32 _vkey: \"0x{}\"", hex::encode(&vkey));
 
-        let call_data = DarkMint::checkHashCall {
-            _hash: Bytes::from(hash_bytes),
-            _aggregationId: aggregation_id,
-            _domainId: domain_id,
-            _merklePath: merkle_path.into(),
-            _leafCount: leaf_count,
-            _index: index,
-            _vkey: vkey,
-        };
+        let call_data = check_hash_call(&agg, hash_bytes, vkey, network.domain_id)?;
 
         let tx = TransactionRequest::default()
             .to(contract_address)
@@ -406,65 +936,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         println!("Hash check passed! Now minting tokens...");
 
-        // Extract recipient address from the signer
-        let recipient = signer.address();
+        // The fee payer only pays the gas, the tokens go to the destination
+        let recipient = dst_addr;
 
-        // Parse public inputs to extract amount and nullifier
-        let pub_inputs_hex = proof.pub_inputs.trim_start_matches("0x");
+        // Parse public inputs to extract the nullifier
+        let pub_inputs_hex = artifact.pub_inputs.trim_start_matches("0x");
         let pub_inputs_bytes = hex::decode(pub_inputs_hex)?;
 
-        // Extract amount and nullifier from public inputs structure
-        // Based on the structure seen in logs, these values are at specific offsets
-        let mut amount = U256::ZERO;
-        let mut nullifier = U256::ZERO;
-
-        if pub_inputs_bytes.len() >= 160 {
-            // Ensure we have enough data
-            // Extract amount (assuming it's at a specific offset in the public inputs)
-            // This is based on the structure visible in your logs
-            let amount_bytes = &pub_inputs_bytes[92..124]; // 32 bytes for amount
-            amount = U256::from_be_slice(amount_bytes) / U256::from(1_000_000_000_000_000_000u64);
-
-            // Extract nullifier (assuming it's at another offset)
-            let nullifier_bytes = &pub_inputs_bytes[124..156]; // 32 bytes for nullifier
-            nullifier = U256::from_be_slice(nullifier_bytes);
-        } else {
-            // Fallback values if parsing fails
-            amount = U256::from(1000000000000000000u64); // 1 token
-            nullifier = U256::from(12345);
-            println!("Warning: Could not parse public inputs, using fallback values");
-        }
+        // The token has 18 decimals, so the coin mints exactly the wei it burned
+        let amount = Amount::from_wei(coin.amount);
+        let (mint_input, nullifier) = mint_calldata(recipient, amount.wei(), &pub_inputs_bytes)?;
 
-        println!("Extracted from public inputs:");
-        println!("  Amount: 30000000000000 wei");
+        println!("Mint parameters:");
+        println!("  Amount: {}", amount);
         println!("  Nullifier: {}", nullifier);
 
-        // Convert hash bytes to bytes32 array for publicInputHashes
-
-        // Prepare mint transaction with correct parameters
-        let mint_call_data = DarkMint::mintCall {
-            recipient,
-            amount,
-            nullifier,
-            publicInputHashes: pub_inputs_bytes
-                .chunks(32)
-                .map(|chunk| {
-                    let mut hash_32 = [0u8; 32];
-                    hash_32[..chunk.len()].copy_from_slice(chunk);
-                    alloy::primitives::B256::from(hash_32)
-                })
-                .collect::<Vec<_>>(),
-        };
-
         let mut mint_tx = TransactionRequest::default()
             .to(contract_address)
-            .input(mint_call_data.abi_encode().into());
+            .input(mint_input.into());
 
-        mint_tx.gas = Some(500000); // Set gas limit directly on the field
+        mint_tx.gas = Some(MINT_GAS_LIMIT); // Set gas limit directly on the field
 
         println!("Sending mint transaction...");
         println!("  Recipient: {:?}", recipient);
-        println!("  Amount: 30000000000000 wei");
+        println!("  Amount: {}", amount);
         println!("  Nullifier: {}", nullifier);
 
         let mint_result = provider.send_transaction(mint_tx).await?;
@@ -478,8 +973,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         if receipt.status() {
             println!("✅ Tokens minted successfully!");
-        } else {    
+            wallet.update_coin_for_burn_address(burn_address, |coin| {
+                coin.transition(CoinState::Minted)?;
+                coin.mint_tx_hash = Some(receipt.transaction_hash);
+                Ok(())
+            })?;
+        } else {
             println!("❌ Mint transaction failed!");
+            wallet.update_coin_for_burn_address(burn_address, |coin| {
+                coin.mint_tx_hash = Some(receipt.transaction_hash);
+                coin.fail("Mint transaction reverted")
+            })?;
         }
     }
     Ok(())
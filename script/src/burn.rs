@@ -1,98 +1,788 @@
+use crate::amount::Amount;
+use crate::mint::MINT_GAS_LIMIT;
+use crate::pending_burn::{BurnFees, PendingBurn, RECEIPT_TIMEOUT_SECS};
+use crate::recovery::IDarkMint;
+use crate::simulate::{simulate_call, CallOutcome};
+use crate::store::WalletStore;
 use crate::Wallet;
 use alloy::{
-    primitives::U256,
-    providers::{Provider, ProviderBuilder},
-    rpc::types::TransactionRequest,
-    signers::local::PrivateKeySigner,
+    network::{Ethereum, EthereumWallet, TxSigner},
+    primitives::{Address, Signature, B256, U256},
+    providers::{PendingTransactionBuilder, Provider, ProviderBuilder},
+    rpc::types::{TransactionReceipt, TransactionRequest},
 };
 use std::io::{self, Write};
+use std::time::Duration;
 
-pub async fn burn_cmd(amount: f64, priv_src: String) -> Result<(), Box<dyn std::error::Error>> {
-    let signer: PrivateKeySigner = priv_src.parse()?;
+/// Context for burn operations
+///
+/// Contains the parameters of a burn besides the wallet and the RPC endpoint.
+#[derive(Debug)]
+pub struct BurnContext<T> {
+    /// Amount to burn
+    pub amount: Amount,
+    /// Signer of the account the ETH is sent from
+    pub signer: T,
+    /// Label stored with the burn address
+    pub label: Option<String>,
+    /// Maximum number of already funded burn addresses to skip
+    pub gap_limit: u64,
+    /// Number of blocks the burn transaction needs before it is recorded
+    pub confirmations: u64,
+    /// Send a legacy transaction with a gas price instead of an EIP-1559 one
+    pub legacy: bool,
+    /// Simulate the burn without reserving a burn address or sending anything
+    pub dry_run: bool,
+    /// DarkMint contract the burn is minted on
+    pub contract_address: Address,
+    /// Chain id of the network, not checked when unset
+    pub chain_id: Option<u64>,
+    /// Pre-burn checks to run
+    pub checks: BurnChecks,
+}
 
-    let provider = ProviderBuilder::new()
-        .wallet(signer.clone())
-        .connect("https://horizen-rpc-testnet.appchain.base.org/")
-        .await?;
-    println!("Provider connected");
-    let wallet = Wallet::open_or_create()?;
-    println!("Wallet opened");
+/// Pre-burn checks, each of which can be skipped explicitly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BurnChecks {
+    /// Check that the chain is the network's and has the DarkMint contract
+    pub chain: bool,
+    /// Refuse amounts worth less than the gas of minting them
+    pub min_amount: bool,
+    /// Skip burn addresses that have code, their balance cannot be proven
+    pub code: bool,
+    /// Skip burn addresses whose nullifier the contract already recorded
+    pub nullifier: bool,
+}
 
-    // Find a burn address with zero balance
-    let mut burn_addr = None;
-    for i in 0..10 {
-        let burn_address = wallet.derive_burn_address(i)?;
-        let balance = provider.get_balance(burn_address.address).await?;
-        if balance == U256::ZERO {
-            burn_addr = Some(burn_address);
-            break;
+impl Default for BurnChecks {
+    fn default() -> Self {
+        BurnChecks {
+            chain: true,
+            min_amount: true,
+            code: true,
+            nullifier: true,
         }
     }
-    println!("Burn address found");
-    let burn_addr = burn_addr.ok_or("No available burn address found")?;
-    println!("Burn address: {}", burn_addr.address);
-    println!(
-        "Burning {} ETH by sending them to {}",
-        amount, burn_addr.address
-    );
-    // Convert ETH to Wei
-    let amount_wei = U256::from((amount * 1_000_000_000_000_000_000.0) as u128);
+}
+
+/// Get the smallest amount worth burning: the most its mint can cost in gas
+pub fn min_economic_amount(max_fee_per_gas: u128) -> U256 {
+    U256::from(MINT_GAS_LIMIT) * U256::from(max_fee_per_gas)
+}
+
+/// Check that burns on this chain can be minted
+async fn check_chain<P: Provider>(
+    provider: &P,
+    chain_id: u64,
+    expected_chain_id: Option<u64>,
+    contract_address: Address,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(expected) = expected_chain_id.filter(|&expected| expected != chain_id) {
+        return Err(format!(
+            "Connected to chain {}, but the network is chain {} (override with --skip-chain-check)",
+            chain_id, expected
+        )
+        .into());
+    }
+    if provider.get_code_at(contract_address).await?.is_empty() {
+        return Err(format!(
+            "No DarkMint contract at {} on chain {}, a burn here could not be minted \
+             (override with --skip-chain-check)",
+            contract_address, chain_id
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Safety margin added to the estimated gas limit, in percent
+pub const GAS_LIMIT_MARGIN_PERCENT: u64 = 20;
+
+/// Add the safety margin to a gas estimate
+pub(crate) fn with_gas_margin(estimated_gas: u64) -> u64 {
+    estimated_gas.saturating_add(estimated_gas / 100 * GAS_LIMIT_MARGIN_PERCENT)
+}
+
+/// Get the most a transaction can cost: its value plus the gas limit at the max fee
+pub(crate) fn max_transaction_cost(value: U256, gas_limit: u64, max_fee_per_gas: u128) -> U256 {
+    value + U256::from(gas_limit) * U256::from(max_fee_per_gas)
+}
+
+/// Get the current network fees, of the legacy or EIP-1559 kind
+pub(crate) async fn current_fees<P: Provider>(
+    provider: &P,
+    legacy: bool,
+) -> Result<BurnFees, Box<dyn std::error::Error>> {
+    if legacy {
+        Ok(BurnFees {
+            max_fee_per_gas: provider.get_gas_price().await?,
+            max_priority_fee_per_gas: None,
+        })
+    } else {
+        let fees = provider.estimate_eip1559_fees().await?;
+        Ok(BurnFees {
+            max_fee_per_gas: fees.max_fee_per_gas,
+            max_priority_fee_per_gas: Some(fees.max_priority_fee_per_gas),
+        })
+    }
+}
+
+/// Set the fees of a transaction
+fn with_fees(tx: TransactionRequest, fees: BurnFees) -> TransactionRequest {
+    match fees.max_priority_fee_per_gas {
+        Some(priority_fee) => tx
+            .max_fee_per_gas(fees.max_fee_per_gas)
+            .max_priority_fee_per_gas(priority_fee),
+        None => tx.gas_price(fees.max_fee_per_gas),
+    }
+}
 
+/// Print the fees of a transaction
+fn print_fees(fees: BurnFees) {
+    match fees.max_priority_fee_per_gas {
+        Some(priority_fee) => println!(
+            "Max fee: {} wei, priority fee: {} wei (EIP-1559 transaction)",
+            fees.max_fee_per_gas, priority_fee
+        ),
+        None => println!("Gas price: {} wei (legacy transaction)", fees.max_fee_per_gas),
+    }
+}
+
+/// Record the outcome of a mined transaction of a pending burn
+///
+/// A confirmed burn becomes a coin and uses its burn address. A confirmed
+/// cancellation, or a reverted burn, only drops the pending burn: the burn
+/// address stays reserved and is not handed out again.
+fn settle_receipt<S: WalletStore>(
+    wallet: &mut Wallet<S>,
+    receipt: &TransactionReceipt,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tx_hash = receipt.transaction_hash;
+    let pending = wallet
+        .pending_burn(tx_hash)
+        .cloned()
+        .ok_or_else(|| format!("Transaction {} is not a pending burn", tx_hash))?;
+
+    if pending.cancel_tx_hashes.contains(&tx_hash) {
+        wallet.drop_pending_burn(tx_hash)?;
+        println!(
+            "✅ Burn cancelled by {}, burn address #{} stays unused",
+            tx_hash, pending.burn_index
+        );
+        return Ok(());
+    }
+    if !receipt.status() {
+        wallet.drop_pending_burn(tx_hash)?;
+        return Err(format!("Burn transaction {} reverted", tx_hash).into());
+    }
+
+    println!("Transaction sent! Hash: {:?}", tx_hash);
+    wallet.confirm_pending_burn(tx_hash)?;
+    println!("✅ Burn successful!");
+    Ok(())
+}
+
+/// Wait for a burn transaction, leaving the burn pending if it takes too long
+async fn wait_for_burn<S: WalletStore>(
+    wallet: &mut Wallet<S>,
+    pending_tx: PendingTransactionBuilder<Ethereum>,
+    confirmations: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tx_hash = *pending_tx.tx_hash();
+    println!("Pending transaction... {}", tx_hash);
+
+    match pending_tx
+        .with_required_confirmations(confirmations)
+        .with_timeout(Some(Duration::from_secs(RECEIPT_TIMEOUT_SECS)))
+        .get_receipt()
+        .await
+    {
+        Ok(receipt) => settle_receipt(wallet, &receipt),
+        Err(e) => {
+            println!("❌ Failed to get receipt: {}", e);
+            Err(format!(
+                "Burn {} is not confirmed yet and stays pending. Check it with \
+                 'darkmint burn pending', or replace it with 'darkmint burn speedup {}' \
+                 or 'darkmint burn cancel {}'",
+                tx_hash, tx_hash, tx_hash
+            )
+            .into())
+        }
+    }
+}
+
+/// Check whether a pending burn was mined
+///
+/// Looks for a receipt of the burn and of every replacement. A burn whose
+/// nonce was used by a transaction this wallet did not send is dropped.
+///
+/// # Arguments
+/// * `wallet` - Wallet the burn is pending in
+/// * `provider` - Provider of the burn's chain
+/// * `tx_hash` - Hash of any transaction of the pending burn
+/// * `confirmations` - Number of blocks a transaction needs before it is recorded
+///
+/// # Returns
+/// * Result containing true if the burn is settled, false if it is still pending
+pub async fn check_pending_burn<S, P>(
+    wallet: &mut Wallet<S>,
+    provider: &P,
+    tx_hash: B256,
+    confirmations: u64,
+) -> Result<bool, Box<dyn std::error::Error>>
+where
+    S: WalletStore,
+    P: Provider,
+{
+    let pending = wallet
+        .pending_burn(tx_hash)
+        .cloned()
+        .ok_or_else(|| format!("Transaction {} is not a pending burn of this wallet", tx_hash))?;
+
+    let latest_block = provider.get_block_number().await?;
+    for hash in pending.tx_hashes.iter().chain(&pending.cancel_tx_hashes) {
+        let Some(receipt) = provider.get_transaction_receipt(*hash).await? else {
+            continue;
+        };
+        let depth = receipt
+            .block_number
+            .map_or(0, |block| latest_block.saturating_sub(block) + 1);
+        if depth < confirmations {
+            println!(
+                "Transaction {} is mined, {}/{} confirmations",
+                hash, depth, confirmations
+            );
+            return Ok(false);
+        }
+        settle_receipt(wallet, &receipt)?;
+        return Ok(true);
+    }
+
+    if provider.get_transaction_count(pending.from).await? > pending.nonce {
+        wallet.drop_pending_burn(tx_hash)?;
+        println!(
+            "⚠️ Nonce {} of {} was used by another transaction, burn address #{} stays unused",
+            pending.nonce, pending.from, pending.burn_index
+        );
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Check the parts of a split burn against the fees and the account balance
+///
+/// Every part must be worth minting and the account must afford all of them
+/// with their fees, so that a split is never left half sent.
+///
+/// # Arguments
+/// * `parts` - Amount of each burn in wei
+/// * `fees` - Current network fees
+/// * `gas_limit` - Gas limit of each burn transaction
+/// * `balance` - Balance of the account the burns are sent from
+/// * `check_amount` - Whether parts worth less than their mint gas are refused
+///
+/// # Returns
+/// * Result indicating whether the split may be sent
+pub fn check_split_parts(
+    parts: &[U256],
+    fees: BurnFees,
+    gas_limit: u64,
+    balance: U256,
+    check_amount: bool,
+) -> Result<(), String> {
+    let min_amount = min_economic_amount(fees.max_fee_per_gas);
+    if check_amount {
+        if let Some(&part) = parts.iter().find(|&&part| part < min_amount) {
+            return Err(format!(
+                "The split has a part of {}, which is not worth burning: minting it can cost up to {}                  in gas at the current fees. Nothing was sent, burn a larger amount or wait for                  lower fees (override with --skip-amount-check)",
+                Amount::from_wei(part),
+                Amount::from_wei(min_amount)
+            ));
+        }
+    }
+
+    let max_cost = parts
+        .iter()
+        .map(|&part| max_transaction_cost(part, gas_limit, fees.max_fee_per_gas))
+        .fold(U256::ZERO, |total, cost| total + cost);
+    if balance < max_cost {
+        return Err(format!(
+            "Insufficient balance for the {} parts of the split. Need {} with fees, have {}. Nothing was sent",
+            parts.len(),
+            Amount::from_wei(max_cost),
+            Amount::from_wei(balance)
+        ));
+    }
+    Ok(())
+}
+
+/// Run the pre-burn checks of all parts of a split burn before any is sent
+///
+/// # Arguments
+/// * `wallet` - Wallet the parts are burned from
+/// * `provider_url` - RPC endpoint
+/// * `context` - Burn context shared by the parts
+/// * `parts` - Amount of each burn in wei
+///
+/// # Returns
+/// * Result indicating whether the split may be sent
+pub async fn check_split_burn<S, T>(
+    wallet: &mut Wallet<S>,
+    provider_url: &str,
+    context: &BurnContext<T>,
+    parts: &[U256],
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    S: WalletStore,
+    T: TxSigner<Signature> + Send + Sync,
+{
+    let account = context.signer.address();
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+    let chain_id = provider.get_chain_id().await?;
+    wallet.set_chain_id(chain_id);
+
+    println!("Checking all {} parts before sending any:", parts.len());
+    if context.checks.chain {
+        check_chain(
+            &provider,
+            chain_id,
+            context.chain_id,
+            context.contract_address,
+        )
+        .await?;
+    }
+
+    // Each part is a plain transfer to a fresh burn address
+    let fees = current_fees(&provider, context.legacy).await?;
+    let first_part = *parts.first().ok_or("A split has at least one part")?;
+    let burn_address = wallet.derive_burn_address(wallet.next_burn_index())?;
+    let tx = TransactionRequest::default()
+        .from(account)
+        .to(burn_address.address)
+        .value(first_part);
+    let gas_limit = with_gas_margin(provider.estimate_gas(with_fees(tx, fees)).await?);
+    let balance = provider.get_balance(account).await?;
+
+    check_split_parts(parts, fees, gas_limit, balance, context.checks.min_amount)?;
+    println!("  ✅ Every part covers its mint gas and the account can pay for all of them");
+    Ok(())
+}
+
+pub async fn burn_cmd<S, T>(
+    wallet: &mut Wallet<S>,
+    provider_url: &str,
+    context: BurnContext<T>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    S: WalletStore,
+    T: TxSigner<Signature> + Send + Sync + 'static,
+{
+    let BurnContext {
+        amount,
+        signer,
+        label,
+        gap_limit,
+        confirmations,
+        legacy,
+        dry_run,
+        contract_address,
+        chain_id: expected_chain_id,
+        checks,
+    } = context;
     let account = signer.address();
+    let amount_wei = amount.wei();
 
-    // Check account balance
-    let account_balance = provider.get_balance(account).await?;
-    println!("Account balance: {} wei", account_balance);
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::from(signer))
+        .connect(provider_url)
+        .await?;
+    println!("Provider connected");
+    let chain_id = provider.get_chain_id().await?;
+    wallet.set_chain_id(chain_id);
+
+    // Pre-burn checks that do not depend on the burn address, before one is reserved
+    println!("Pre-burn checks:");
+    if checks.chain {
+        check_chain(&provider, chain_id, expected_chain_id, contract_address).await?;
+        println!(
+            "  ✅ Chain {} has the DarkMint contract at {}",
+            chain_id, contract_address
+        );
+    } else {
+        println!("  ⏭️ Chain check skipped");
+    }
 
-    if account_balance < amount_wei {
+    // Fees: type-2 from the recent fee history, or a legacy gas price
+    let fees = current_fees(&provider, legacy).await?;
+    let min_amount = min_economic_amount(fees.max_fee_per_gas);
+    if !checks.min_amount {
+        println!("  ⏭️ Amount check skipped");
+    } else if amount_wei < min_amount {
         return Err(format!(
-            "Insufficient balance. Need {} wei, have {} wei",
-            amount_wei, account_balance
+            "Burning {} is not worth it, minting it can cost up to {} in gas at the current fees \
+             (override with --skip-amount-check)",
+            amount,
+            Amount::from_wei(min_amount)
         )
         .into());
+    } else {
+        println!(
+            "  ✅ Amount covers the mint gas (up to {})",
+            Amount::from_wei(min_amount)
+        );
     }
 
-    // Get gas price
-    let gas_price = provider.get_gas_price().await?;
-    println!("Gas price: {} wei", gas_price);
+    // Reserve the next unused burn address, skipping ones funded elsewhere, ones
+    // with code and ones whose nullifier was already used. A dry run only derives
+    // the candidates and leaves the wallet as it is
+    let darkmint = IDarkMint::new(contract_address, &provider);
+    let mut burn_addr = None;
+    for offset in 0..gap_limit {
+        let (index, burn_address) = if dry_run {
+            let index = wallet.next_burn_index() + offset;
+            (index, wallet.derive_burn_address(index)?)
+        } else {
+            wallet.reserve_burn_address(label.as_deref())?
+        };
+        let skip_reason = if provider.get_balance(burn_address.address).await? != U256::ZERO {
+            Some("is already funded")
+        } else if checks.code && !provider.get_code_at(burn_address.address).await?.is_empty() {
+            Some("has code")
+        } else if checks.nullifier && darkmint.nullifiers(burn_address.nullifier()).call().await? {
+            Some("has a nullifier that was already used on the DarkMint contract")
+        } else {
+            None
+        };
+        let Some(reason) = skip_reason else {
+            burn_addr = Some((index, burn_address));
+            break;
+        };
+        println!("Burn address #{} {}, skipping it", index, reason);
+        if !dry_run {
+            wallet.mark_burn_address_used(index)?;
+        }
+    }
+    let (burn_index, burn_addr) = burn_addr.ok_or("No available burn address found")?;
+    for (enabled, check) in [
+        (checks.code, "Burn address has no code"),
+        (checks.nullifier, "Nullifier of the burn address is unused"),
+    ] {
+        if enabled {
+            println!("  ✅ {}", check);
+        } else {
+            println!("  ⏭️ {} (not checked)", check);
+        }
+    }
+    println!("Burn address found");
+    println!("Burn address #{}: {}", burn_index, burn_addr.address);
+    println!("Burning {} by sending it to {}", amount, burn_addr.address);
 
     // Get nonce
     let nonce = provider.get_transaction_count(account).await?;
 
     // Create transaction
-    let mut tx = TransactionRequest::default()
+    let tx = TransactionRequest::default()
         .from(account)
         .to(burn_addr.address)
         .value(amount_wei)
-        .nonce(nonce)
-        .gas_price(gas_price);
+        .nonce(nonce);
+
+    print_fees(fees);
+    let max_fee_per_gas = fees.max_fee_per_gas;
+    let tx = with_fees(tx, fees);
 
     // Estimate gas
-  
+    let estimated_gas = provider.estimate_gas(tx.clone()).await?;
+    let gas_limit = with_gas_margin(estimated_gas);
+    println!(
+        "Gas limit: {} ({} estimated + {}%)",
+        gas_limit, estimated_gas, GAS_LIMIT_MARGIN_PERCENT
+    );
+    let tx = tx.gas_limit(gas_limit);
+
+    // Check account balance against the amount plus the highest possible fee
+    let account_balance = provider.get_balance(account).await?;
+    println!("Account balance: {}", Amount::from_wei(account_balance));
+
+    let max_cost = max_transaction_cost(amount_wei, gas_limit, max_fee_per_gas);
+    if account_balance < max_cost {
+        return Err(format!(
+            "Insufficient balance. Need {} ({} burned + up to {} in fees), have {}",
+            Amount::from_wei(max_cost),
+            amount,
+            Amount::from_wei(max_cost - amount_wei),
+            Amount::from_wei(account_balance)
+        )
+        .into());
+    }
 
     println!("Transaction created");
 
+    if dry_run {
+        let outcome = simulate_call(&provider, tx).await?;
+        println!("Dry run: nothing was sent and no burn address was reserved");
+        outcome.print("Burn transfer");
+        if let CallOutcome::Success { gas } = outcome {
+            println!(
+                "Expected cost: {} burned + {} in fees (up to {})",
+                amount,
+                Amount::from_wei(U256::from(gas) * U256::from(max_fee_per_gas)),
+                Amount::from_wei(max_cost - amount_wei)
+            );
+        }
+        return Ok(());
+    }
+
     // Send transaction (provider handles signing automatically)
-    match provider.send_transaction(tx).await {
-        Ok(pending_tx) => {
-            println!("Pending transaction... {}", pending_tx.tx_hash());
-
-            match pending_tx.get_receipt().await {
-                Ok(receipt) => {
-                    println!("Transaction sent! Hash: {:?}", receipt.transaction_hash);
-                    println!("✅ Burn successful!");
-                }
-                Err(e) => {
-                    println!("❌ Failed to get receipt: {}", e);
-                    return Err(e.into());
-                }
-            }
+    let pending_tx = match provider.send_transaction(tx).await {
+        Ok(pending_tx) => pending_tx,
+        Err(e) => {
+            println!("❌ Failed to send transaction: {}", e);
+            return Err(e.into());
         }
+    };
+
+    // The burn address stays reserved until the burn confirms
+    wallet.add_pending_burn(PendingBurn {
+        burn_index,
+        burn_address: burn_addr.address,
+        from: account,
+        amount: amount_wei,
+        nonce,
+        gas_limit,
+        max_fee_per_gas: fees.max_fee_per_gas,
+        max_priority_fee_per_gas: fees.max_priority_fee_per_gas,
+        tx_hashes: vec![*pending_tx.tx_hash()],
+        cancel_tx_hashes: vec![],
+    })?;
+
+    wait_for_burn(wallet, pending_tx, confirmations).await
+}
+
+/// Context for replacing a stuck burn
+#[derive(Debug)]
+pub struct ReplaceBurnContext<T> {
+    /// Hash of the burn or of one of its replacements
+    pub tx_hash: B256,
+    /// Signer of the account the burn was sent from
+    pub signer: T,
+    /// Send a 0-value transfer to the fee payer instead of the burn
+    pub cancel: bool,
+    /// Fee increase in percent, at least `MIN_FEE_BUMP_PERCENT`
+    pub bump_percent: u64,
+    /// Number of blocks the transaction needs before it is recorded
+    pub confirmations: u64,
+}
+
+/// Speed up or cancel a pending burn
+///
+/// Sends a transaction with the nonce of the pending burn and higher fees:
+/// the same burn to speed it up, or a 0-value transfer to the fee payer to
+/// cancel it. Nothing is sent if one of the burn's transactions was mined.
+///
+/// # Arguments
+/// * `wallet` - Wallet the burn is pending in
+/// * `provider_url` - RPC endpoint of the burn's chain
+/// * `context` - Transaction to replace and how
+///
+/// # Returns
+/// * Result indicating success or error
+pub async fn replace_burn_cmd<S, T>(
+    wallet: &mut Wallet<S>,
+    provider_url: &str,
+    context: ReplaceBurnContext<T>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    S: WalletStore,
+    T: TxSigner<Signature> + Send + Sync + 'static,
+{
+    let ReplaceBurnContext {
+        tx_hash,
+        signer,
+        cancel,
+        bump_percent,
+        confirmations,
+    } = context;
+
+    let pending = wallet
+        .pending_burn(tx_hash)
+        .cloned()
+        .ok_or_else(|| format!("Transaction {} is not a pending burn of this wallet", tx_hash))?;
+    let account = signer.address();
+    if account != pending.from {
+        return Err(format!(
+            "Burn {} was sent from {}, unlock that account to replace it (got {})",
+            tx_hash, pending.from, account
+        )
+        .into());
+    }
+
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::from(signer))
+        .connect(provider_url)
+        .await?;
+    wallet.set_chain_id(provider.get_chain_id().await?);
+
+    if check_pending_burn(wallet, &provider, tx_hash, confirmations).await? {
+        return Ok(());
+    }
+
+    let previous = pending.fees();
+    let fees = previous.bumped(
+        current_fees(&provider, previous.is_legacy()).await?,
+        bump_percent,
+    )?;
+    println!(
+        "{} burn #{} (nonce {}), max fee {} -> {} wei",
+        if cancel { "Cancelling" } else { "Speeding up" },
+        pending.burn_index,
+        pending.nonce,
+        previous.max_fee_per_gas,
+        fees.max_fee_per_gas
+    );
+    print_fees(fees);
+
+    let (to, value) = if cancel {
+        (pending.from, U256::ZERO)
+    } else {
+        (pending.burn_address, pending.amount)
+    };
+    let tx = TransactionRequest::default()
+        .from(account)
+        .to(to)
+        .value(value)
+        .nonce(pending.nonce)
+        .gas_limit(pending.gas_limit);
+    let tx = with_fees(tx, fees);
+
+    let account_balance = provider.get_balance(account).await?;
+    let max_cost = max_transaction_cost(value, pending.gas_limit, fees.max_fee_per_gas);
+    if account_balance < max_cost {
+        return Err(format!(
+            "Insufficient balance. Need {}, have {}",
+            Amount::from_wei(max_cost),
+            Amount::from_wei(account_balance)
+        )
+        .into());
+    }
+
+    let pending_tx = match provider.send_transaction(tx).await {
+        Ok(pending_tx) => pending_tx,
         Err(e) => {
+            // The burn may have been mined in the meantime
+            if check_pending_burn(wallet, &provider, tx_hash, confirmations).await? {
+                return Ok(());
+            }
             println!("❌ Failed to send transaction: {}", e);
             return Err(e.into());
         }
+    };
+    wallet.record_burn_replacement(tx_hash, *pending_tx.tx_hash(), fees, cancel)?;
+
+    wait_for_burn(wallet, pending_tx, confirmations).await
+}
+
+/// Show the pending burns of a wallet and record the ones that were mined
+///
+/// # Arguments
+/// * `wallet` - Wallet to check
+/// * `provider_url` - RPC endpoint of the burns' chain
+/// * `confirmations` - Number of blocks a transaction needs before it is recorded
+///
+/// # Returns
+/// * Result indicating success or error
+pub async fn pending_burns_cmd<S: WalletStore>(
+    wallet: &mut Wallet<S>,
+    provider_url: &str,
+    confirmations: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if wallet.pending_burns().is_empty() {
+        println!("No pending burns");
+        return Ok(());
     }
 
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+    wallet.set_chain_id(provider.get_chain_id().await?);
+
+    for pending in wallet.pending_burns().to_vec() {
+        println!(
+            "Burn #{}: {} to {} (nonce {} from {})",
+            pending.burn_index,
+            Amount::from_wei(pending.amount),
+            pending.burn_address,
+            pending.nonce,
+            pending.from
+        );
+        for hash in &pending.tx_hashes {
+            println!("  burn   {}", hash);
+        }
+        for hash in &pending.cancel_tx_hashes {
+            println!("  cancel {}", hash);
+        }
+
+        let Some(tx_hash) = pending.latest_tx_hash() else {
+            continue;
+        };
+        match check_pending_burn(wallet, &provider, tx_hash, confirmations).await {
+            Ok(true) => {}
+            Ok(false) => println!(
+                "  Still pending, replace it with 'darkmint burn speedup {}' or 'darkmint burn cancel {}'",
+                tx_hash, tx_hash
+            ),
+            Err(e) => println!("  ❌ {}", e),
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gas_margin() {
+        assert_eq!(with_gas_margin(21_000), 25_200);
+        assert_eq!(with_gas_margin(0), 0);
+        assert_eq!(with_gas_margin(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_max_cost_covers_amount_and_fees() {
+        let amount = U256::from(10u64).pow(U256::from(17u64));
+        let cost = max_transaction_cost(amount, 25_200, 30_000_000_000);
+        assert_eq!(cost, amount + U256::from(756_000_000_000_000u64));
+    }
+
+    #[test]
+    fn test_split_with_an_uneconomic_part_sends_nothing() {
+        let eth = |amount: &str| amount.parse::<Amount>().unwrap().wei();
+        let fees = BurnFees {
+            max_fee_per_gas: 30_000_000_000,
+            max_priority_fee_per_gas: Some(1_000_000_000),
+        };
+        let balance = eth("10");
+
+        // 0.123456 ETH splits into 0.1, 0.01, 0.01, 0.001 (x3) and 0.000456;
+        // all but 0.1 cost more to mint than they are worth at 30 gwei
+        let parts = crate::denomination::split_amount(eth("0.123456")).unwrap();
+        assert!(check_split_parts(&parts, fees, 25_200, balance, true).is_err());
+        assert!(check_split_parts(&parts, fees, 25_200, balance, false).is_ok());
+
+        assert!(check_split_parts(&[eth("1"), eth("0.1")], fees, 25_200, balance, true).is_ok());
+
+        // The balance must cover every part and its fees, not just the first
+        let parts = vec![eth("1"); 10];
+        assert!(check_split_parts(&parts, fees, 25_200, eth("10"), true).is_err());
+        assert!(check_split_parts(&parts, fees, 25_200, eth("10.01"), true).is_ok());
+    }
+
+    #[test]
+    fn test_min_economic_amount_is_the_mint_gas() {
+        // 500k gas at 30 gwei
+        assert_eq!(
+            min_economic_amount(30_000_000_000),
+            U256::from(15_000_000_000_000_000u64)
+        );
+        assert_eq!(min_economic_amount(0), U256::ZERO);
+    }
+}
@@ -0,0 +1,286 @@
+//! Burn address index tracking
+//!
+//! The wallet hands out burn addresses in order. It persists the next unused
+//! index and a record of every index it reserved or saw funded, optionally
+//! labelled. Looking up which index a burn address belongs to goes through an
+//! in-memory reverse index, filled by deriving addresses up to the next unused
+//! index plus a gap limit.
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::store::WalletStore;
+use crate::{BurnAddress, Wallet, WalletData};
+
+/// Number of consecutive unused burn addresses scanned past the last known one
+pub const DEFAULT_GAP_LIMIT: u64 = 20;
+
+/// Maximum length of a burn address label
+pub const MAX_LABEL_LEN: usize = 64;
+
+/// Usage of a burn address index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BurnAddressStatus {
+    /// Handed out for a burn that has not been confirmed yet
+    Reserved,
+    /// Funded by a confirmed burn
+    Used,
+}
+
+impl fmt::Display for BurnAddressStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BurnAddressStatus::Reserved => write!(f, "reserved"),
+            BurnAddressStatus::Used => write!(f, "used"),
+        }
+    }
+}
+
+/// Burn address index known to the wallet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BurnAddressRecord {
+    /// Burn address index
+    pub index: u64,
+    /// Whether the address is reserved or used
+    pub status: BurnAddressStatus,
+    /// User label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Reverse index from burn addresses to their indexes
+///
+/// Addresses depend on the chain id for hierarchical wallets, so the index is
+/// cleared whenever the chain id changes.
+#[derive(Debug, Default)]
+pub(crate) struct AddressIndex {
+    addresses: std::collections::HashMap<Address, u64>,
+    /// Number of indexes derived into `addresses`, starting from 0
+    derived: u64,
+}
+
+impl AddressIndex {
+    pub(crate) fn clear(&mut self) {
+        self.addresses.clear();
+        self.derived = 0;
+    }
+}
+
+/// Check a burn address label
+fn validate_label(label: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if label.is_empty() || label.len() > MAX_LABEL_LEN {
+        return Err(format!("Labels must be 1 to {} characters long", MAX_LABEL_LEN).into());
+    }
+    if label.chars().any(char::is_control) {
+        return Err("Labels must not contain control characters".into());
+    }
+    Ok(())
+}
+
+impl WalletData {
+    /// Record that a burn address index was funded
+    pub(crate) fn mark_burn_index_used(&mut self, index: u64) {
+        match self.burn_addresses.iter_mut().find(|r| r.index == index) {
+            Some(record) => record.status = BurnAddressStatus::Used,
+            None => {
+                self.burn_addresses.push(BurnAddressRecord {
+                    index,
+                    status: BurnAddressStatus::Used,
+                    label: None,
+                });
+                self.burn_addresses.sort_by_key(|record| record.index);
+            }
+        }
+        self.next_burn_index = self.next_burn_index.max(index + 1);
+    }
+}
+
+impl<S: WalletStore> Wallet<S> {
+    /// Get the first burn address index that was never handed out
+    ///
+    /// # Returns
+    /// * Next unused burn address index
+    pub fn next_burn_index(&self) -> u64 {
+        self.data.next_burn_index
+    }
+
+    /// Get the burn address indexes the wallet reserved or saw funded
+    ///
+    /// # Returns
+    /// * Records ordered by index
+    pub fn burn_addresses(&self) -> &[BurnAddressRecord] {
+        &self.data.burn_addresses
+    }
+
+    /// Reserve the next unused burn address
+    ///
+    /// # Arguments
+    /// * `label` - Optional label for the address
+    ///
+    /// # Returns
+    /// * Result containing the index and burn address, or an error
+    pub fn reserve_burn_address(
+        &mut self,
+        label: Option<&str>,
+    ) -> Result<(u64, BurnAddress), Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        if let Some(label) = label {
+            validate_label(label)?;
+        }
+
+        let index = self.data.next_burn_index;
+        let burn_address = self.derive_burn_address(index)?;
+        self.data.burn_addresses.push(BurnAddressRecord {
+            index,
+            status: BurnAddressStatus::Reserved,
+            label: label.map(str::to_string),
+        });
+        self.data.next_burn_index = index + 1;
+        self.address_index
+            .addresses
+            .insert(burn_address.address, index);
+        self.save()?;
+
+        Ok((index, burn_address))
+    }
+
+    /// Mark a burn address index as funded
+    ///
+    /// # Arguments
+    /// * `index` - Burn address index
+    ///
+    /// # Returns
+    /// * Result indicating success or error
+    pub fn mark_burn_address_used(&mut self, index: u64) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        self.data.mark_burn_index_used(index);
+        self.save()
+    }
+
+    /// Set or clear the label of a burn address index
+    ///
+    /// # Arguments
+    /// * `index` - Burn address index, reserved or used
+    /// * `label` - New label, `None` to clear it
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error for unknown indexes
+    pub fn set_burn_address_label(
+        &mut self,
+        index: u64,
+        label: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        if let Some(label) = label {
+            validate_label(label)?;
+        }
+
+        let record = self
+            .data
+            .burn_addresses
+            .iter_mut()
+            .find(|record| record.index == index)
+            .ok_or_else(|| format!("Burn address #{} was never reserved", index))?;
+        record.label = label.map(str::to_string);
+        self.save()
+    }
+
+    /// Find the index of a burn address
+    ///
+    /// Derives addresses up to `next_burn_index() + gap_limit` into the
+    /// reverse index, so repeated lookups do not derive again.
+    ///
+    /// # Arguments
+    /// * `address` - Burn address to look for
+    /// * `gap_limit` - Number of indexes to scan past the next unused one
+    ///
+    /// # Returns
+    /// * Result containing the index and burn address if found, or an error
+    pub fn find_burn_address(
+        &mut self,
+        address: Address,
+        gap_limit: u64,
+    ) -> Result<Option<(u64, BurnAddress)>, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        if let Some(&index) = self.address_index.addresses.get(&address) {
+            return Ok(Some((index, self.derive_burn_address(index)?)));
+        }
+
+        let end = self.data.next_burn_index.saturating_add(gap_limit);
+        while self.address_index.derived < end {
+            let index = self.address_index.derived;
+            let burn_address = self.derive_burn_address(index)?;
+            self.address_index
+                .addresses
+                .insert(burn_address.address, index);
+            self.address_index.derived = index + 1;
+            if burn_address.address == address {
+                return Ok(Some((index, burn_address)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derivation::DerivationScheme;
+    use crate::store::MemoryStore;
+    use crate::NOTE_SIZE;
+
+    fn test_wallet() -> Wallet<MemoryStore> {
+        let mut wallet = Wallet::from_entropy(&[2u8; NOTE_SIZE], DerivationScheme::Hierarchical);
+        wallet.set_chain_id(1);
+        wallet
+    }
+
+    #[test]
+    fn test_reserve_hands_out_increasing_indexes() {
+        let mut wallet = test_wallet();
+        let (first, _) = wallet.reserve_burn_address(Some("rent")).unwrap();
+        let (second, _) = wallet.reserve_burn_address(None).unwrap();
+
+        assert_eq!((first, second), (0, 1));
+        assert_eq!(wallet.next_burn_index(), 2);
+        assert_eq!(wallet.burn_addresses()[0].label.as_deref(), Some("rent"));
+        assert_eq!(
+            wallet.burn_addresses()[1].status,
+            BurnAddressStatus::Reserved
+        );
+
+        wallet.mark_burn_address_used(1).unwrap();
+        assert_eq!(wallet.burn_addresses()[1].status, BurnAddressStatus::Used);
+    }
+
+    #[test]
+    fn test_find_beyond_ten_addresses() {
+        let mut wallet = test_wallet();
+        for _ in 0..15 {
+            wallet.reserve_burn_address(None).unwrap();
+        }
+        let target = wallet.derive_burn_address(25).unwrap().address;
+
+        // Index 25 is within next index (15) + gap limit (20)
+        let (index, found) = wallet.find_burn_address(target, 20).unwrap().unwrap();
+        assert_eq!(index, 25);
+        assert_eq!(found.address, target);
+
+        let far = wallet.derive_burn_address(40).unwrap().address;
+        assert!(wallet.find_burn_address(far, 20).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_used_index_moves_next_index() {
+        let mut wallet = test_wallet();
+        wallet.mark_burn_address_used(7).unwrap();
+
+        assert_eq!(wallet.next_burn_index(), 8);
+        assert_eq!(wallet.reserve_burn_address(None).unwrap().0, 8);
+        assert!(wallet.set_burn_address_label(3, Some("x")).is_err());
+        assert!(wallet.set_burn_address_label(7, Some("")).is_err());
+        wallet.set_burn_address_label(7, Some("savings")).unwrap();
+    }
+}
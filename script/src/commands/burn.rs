@@ -1,66 +1,168 @@
-use clap::Args;
+use clap::{Args, Subcommand};
 use colored::*;
 
-use dialoguer::{Confirm, Input, Password};
+use dialoguer::{Confirm, Input};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::time::Duration;
 use tokio::time::sleep;
 
-use std::env;
 use std::io::{BufRead, BufReader};
 use std::process::{Command, Stdio};
 
-/// Expand environment variables in a string (e.g., "$PRIVATE_KEY" -> actual value)
-fn expand_env_vars(input: &str) -> Result<String, Box<dyn std::error::Error>> {
-    if input.starts_with('$') {
-        let var_name = &input[1..]; // Remove the '$' prefix
-        match env::var(var_name) {
-            Ok(value) => {
-                println!(
-                    "{} Expanded {} to environment variable value",
-                    "🔧".bright_green(),
-                    input
-                );
-                Ok(value)
-            }
-            Err(_) => {
-                eprintln!(
-                    "{} Environment variable {} not found",
-                    "❌".bright_red(),
-                    var_name
-                );
-                Err(format!("Environment variable {} not found", var_name).into())
-            }
-        }
-    } else {
-        Ok(input.to_string())
-    }
-}
+use alloy::primitives::B256;
+
+use super::fee_payer::FeePayerArgs;
+use super::password::{open_wallet, wallet_password};
+use crate::amount::Amount;
+use crate::burn::{pending_burns_cmd, replace_burn_cmd, ReplaceBurnContext};
+use crate::burn_index::DEFAULT_GAP_LIMIT;
+use crate::denomination::{split_amount, split_remainder};
+use crate::network::NetworkProfile;
+use crate::pending_burn::DEFAULT_FEE_BUMP_PERCENT;
+use crate::wallet_file::WALLET_PASSWORD_ENV;
 
 /// Burn private coins by sending ETH to a burn address
 #[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct BurnCommand {
+    #[command(subcommand)]
+    pub action: Option<BurnAction>,
+
     /// Amount of ETH to burn
     #[arg(long, short = 'a')]
-    pub amount: Option<f64>,
+    pub amount: Option<Amount>,
+
+    /// Account the burned ETH is sent from
+    #[command(flatten)]
+    pub fee_payer: FeePayerArgs,
+
+    /// RPC provider URL, defaults to the endpoints of the network profile
+    #[arg(long, short = 'r')]
+    pub provider_url: Option<String>,
+
+    /// Label stored with the burn address in the wallet
+    #[arg(long, short = 'l')]
+    pub label: Option<String>,
+
+    /// Maximum number of already funded burn addresses to skip
+    #[arg(long, short = 'g', default_value_t = DEFAULT_GAP_LIMIT)]
+    pub gap_limit: u64,
+
+    /// Send a legacy transaction with a gas price instead of an EIP-1559 one
+    #[arg(long)]
+    pub legacy: bool,
+
+    /// Simulate the burn and show its cost without sending anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Split the burn into standard denominations, each to a fresh burn address
+    #[arg(long)]
+    pub split: bool,
 
-    /// Private key for the source account (will prompt if not provided)
-    #[arg(long, short = 'p')]
-    pub priv_src: Option<String>,
+    /// Refuse to burn when anything links it to other coins, instead of warning
+    #[arg(long)]
+    pub strict_privacy: bool,
 
-    /// RPC provider URL
-    #[arg(
-        long,
-        short = 'r',
-        default_value = "https://horizen-rpc-testnet.appchain.base.org/"
-    )]
-    pub provider_url: String,
+    /// Burn even if the chain is not the network's or lacks the DarkMint contract
+    #[arg(long)]
+    pub skip_chain_check: bool,
+
+    /// Burn amounts worth less than the gas of minting them
+    #[arg(long)]
+    pub skip_amount_check: bool,
+
+    /// Accept burn addresses that have code
+    #[arg(long)]
+    pub skip_code_check: bool,
+
+    /// Accept burn addresses whose nullifier was already used on the contract
+    #[arg(long)]
+    pub skip_nullifier_check: bool,
 
     /// Skip confirmation prompts
     #[arg(long, short = 'y')]
     pub yes: bool,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum BurnAction {
+    /// ⏩ Rebroadcast a stuck burn with higher fees
+    Speedup(ReplaceArgs),
+    /// 🛑 Cancel a stuck burn with a 0-value transfer to the source account
+    Cancel(ReplaceArgs),
+    /// ⏳ List burns that are not confirmed yet and record mined ones
+    Pending {
+        /// RPC provider URL, defaults to the endpoints of the network profile
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+}
+
+/// Stuck burn to replace
+#[derive(Args, Debug)]
+pub struct ReplaceArgs {
+    /// Hash of the burn transaction, or of an earlier replacement
+    pub tx: B256,
+
+    /// Fee increase in percent (nodes require at least 10)
+    #[arg(long, short = 'b', default_value_t = DEFAULT_FEE_BUMP_PERCENT)]
+    pub bump: u64,
+
+    /// Account the burn was sent from
+    #[command(flatten)]
+    pub fee_payer: FeePayerArgs,
+
+    /// RPC provider URL, defaults to the endpoints of the network profile
+    #[arg(long, short = 'r')]
+    pub provider_url: Option<String>,
+}
+
+impl BurnAction {
+    async fn execute(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let network = NetworkProfile::selected()?;
+        match self {
+            BurnAction::Speedup(args) => replace(args, false, &network).await,
+            BurnAction::Cancel(args) => replace(args, true, &network).await,
+            BurnAction::Pending { provider_url } => {
+                let provider_url = network.rpc_url(provider_url.as_deref()).await?;
+                let mut wallet = open_wallet()?;
+                pending_burns_cmd(&mut wallet, &provider_url, network.confirmations).await
+            }
+        }
+    }
+}
+
+/// Speed up or cancel a pending burn
+async fn replace(
+    args: &ReplaceArgs,
+    cancel: bool,
+    network: &NetworkProfile,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let provider_url = network.rpc_url(args.provider_url.as_deref()).await?;
+    let mut wallet = open_wallet()?;
+    let pending = wallet
+        .pending_burn(args.tx)
+        .ok_or_else(|| format!("{} is not a pending burn of this wallet", args.tx))?;
+    println!(
+        "{} {} burn #{} of {}",
+        "⏳".bright_yellow(),
+        if cancel { "Cancelling" } else { "Speeding up" },
+        pending.burn_index,
+        Amount::from_wei(pending.amount).to_string().bright_cyan()
+    );
+
+    let fee_payer = args.fee_payer.unlock()?;
+    let context = ReplaceBurnContext {
+        tx_hash: args.tx,
+        signer: fee_payer.signer(),
+        cancel,
+        bump_percent: args.bump,
+        confirmations: network.confirmations,
+    };
+    replace_burn_cmd(&mut wallet, &provider_url, context).await
+}
+
 impl BurnCommand {
     pub async fn execute(&self) -> Result<(), Box<dyn std::error::Error>> {
         // Print beautiful header
@@ -68,74 +170,99 @@ impl BurnCommand {
         println!("{}", "═".repeat(50).bright_black());
         println!();
 
+        if let Some(action) = &self.action {
+            return action.execute().await;
+        }
+
         // Get amount interactively if not provided
         let amount = match self.amount {
             Some(amt) => amt,
             None => {
-                let amount: f64 = Input::new()
-                    .with_prompt(&format!("{} Amount of ETH to burn", "💰".bright_yellow()))
-                    .interact()?;
-                amount
+                let amount: String = Input::new()
+                    .with_prompt(&format!(
+                        "{} Amount to burn (e.g. 0.1eth, 250gwei)",
+                        "💰".bright_yellow()
+                    ))
+                    .validate_with(|input: &String| input.parse::<Amount>().map(|_| ()))
+                    .interact_text()?;
+                amount.parse()?
             }
         };
 
         // Validate amount
-        if amount <= 0.0 {
+        if amount.is_zero() {
             eprintln!("{} Amount must be greater than 0", "❌".bright_red());
             return Err("Invalid amount".into());
         }
 
-        // Get private key interactively if not provided
-        let priv_src_raw = match &self.priv_src {
-            Some(key) => key.clone(),
-            None => {
-                println!(
-                    "{} {}",
-                    "🔐".bright_blue(),
-                    "Private key required for burning".bright_cyan()
-                );
-                let key = Input::new()
-                    .with_prompt("Enter your private key")
-                    .interact()?;
-                key
-            }
+        // Standard denominations to burn, checked before anything is unlocked
+        let split = if self.split {
+            Some(split_amount(amount.wei())?)
+        } else {
+            None
         };
 
-        // Expand environment variables if needed (e.g., "$PRIVATE_KEY" -> actual value)
-        let priv_src = expand_env_vars(&priv_src_raw)?;
+        // Unlock the source account from its keystore (or an explicitly allowed raw key)
+        let fee_payer = self.fee_payer.unlock()?;
 
-        // Validate private key format
-        if !priv_src.starts_with("0x") || priv_src.len() != 66 {
-            eprintln!(
-                "{} Private key must be a valid hex string starting with 0x",
-                "❌".bright_red()
-            );
-            return Err("Invalid private key format".into());
-        }
+        // RPC endpoint of the selected network, checked before anything is spent
+        let network = NetworkProfile::selected()?;
+        let provider_url = network.rpc_url(self.provider_url.as_deref()).await?;
+
+        // Wallet password is handed to the worker process through the environment
+        let wallet_password = wallet_password()?;
 
         // Display transaction details
         println!();
         println!("{}", "📋 Transaction Details".bright_green().bold());
         println!("{}", "─".repeat(30).bright_black());
-        println!("{} {:.6} ETH", "Amount:".bright_white(), amount);
+        println!("{} {}", "Amount:".bright_white(), amount);
+        println!("{} {}", "Network:".bright_white(), network.name.bright_cyan());
         println!(
             "{} {}",
             "Provider:".bright_white(),
-            self.provider_url.bright_cyan()
+            provider_url.bright_cyan()
         );
+        if let Some(parts) = &split {
+            let parts: Vec<String> = parts
+                .iter()
+                .map(|&part| Amount::from_wei(part).to_string())
+                .collect();
+            println!(
+                "{} {} burns of {}",
+                "Split:".bright_white(),
+                parts.len(),
+                parts.join(", ").bright_cyan()
+            );
+            let remainder = split_remainder(amount.wei());
+            if !remainder.is_zero() {
+                println!(
+                    "{} {} is below the smallest denomination and burned on its own, its amount stands out",
+                    "⚠️".bright_yellow(),
+                    Amount::from_wei(remainder)
+                );
+            }
+        }
+        if let Some(label) = &self.label {
+            println!("{} {}", "Label:".bright_white(), label.bright_cyan());
+        }
         println!(
-            "{} {}...{}",
-            "Private Key:".bright_white(),
-            &priv_src[0..8].bright_yellow(),
-            &priv_src[priv_src.len() - 6..].bright_yellow()
+            "{} {}",
+            "Transaction Type:".bright_white(),
+            if self.legacy { "Legacy" } else { "EIP-1559" }.bright_cyan()
+        );
+        println!(
+            "{} {}",
+            "Source Account:".bright_white(),
+            fee_payer.address().to_string().bright_yellow()
         );
 
-        // Confirmation unless --yes flag is used
-        if !self.yes {
+        // Confirmation unless --yes flag is used, a dry run spends nothing
+        if !self.yes && !self.dry_run {
             println!();
             let confirmed = Confirm::new()
                 .with_prompt(&format!(
-                    "{} Do you want to proceed with burning {} ETH?",
+                    "{} Do you want to proceed with burning {}?",
                     "⚠️".bright_yellow(),
                     amount.to_string().bright_red().bold()
                 ))
@@ -180,10 +307,39 @@ impl BurnCommand {
             .arg("--burn")
             .arg("--amount")
             .arg(&amount.to_string())
-            .arg("--priv-src")
-            .arg(&priv_src)
             .arg("--provider-url")
-            .arg(&self.provider_url);
+            .arg(&provider_url)
+            .arg("--gap-limit")
+            .arg(self.gap_limit.to_string());
+
+        if let Some(label) = &self.label {
+            cmd.arg("--label").arg(label);
+        }
+        if self.legacy {
+            cmd.arg("--legacy");
+        }
+        if self.dry_run {
+            cmd.arg("--dry-run");
+        }
+        if self.split {
+            cmd.arg("--split");
+        }
+        if self.strict_privacy {
+            cmd.arg("--strict-privacy");
+        }
+        for (skip, flag) in [
+            (self.skip_chain_check, "--skip-chain-check"),
+            (self.skip_amount_check, "--skip-amount-check"),
+            (self.skip_code_check, "--skip-code-check"),
+            (self.skip_nullifier_check, "--skip-nullifier-check"),
+        ] {
+            if skip {
+                cmd.arg(flag);
+            }
+        }
+
+        fee_payer.pass_to(&mut cmd);
+        cmd.env(WALLET_PASSWORD_ENV, &wallet_password);
 
         // Set up command to show real-time output
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
@@ -221,7 +377,14 @@ impl BurnCommand {
                 // Wait for the process to complete
                 match child.wait() {
                     Ok(status) => {
-                        if status.success() {
+                        if status.success() && self.dry_run {
+                            println!();
+                            println!(
+                                "{} {}",
+                                "🧪".bright_blue(),
+                                "Dry run finished, nothing was sent".bright_blue().bold()
+                            );
+                        } else if status.success() {
                             println!();
                             println!(
                                 "{} {}",
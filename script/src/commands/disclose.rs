@@ -0,0 +1,155 @@
+use clap::Args;
+use colored::*;
+
+use super::password::open_wallet;
+use crate::amount::Amount;
+use crate::disclosure::Disclosure;
+use crate::network::NetworkProfile;
+
+/// Disclose the opening of a minted encrypted coin to an auditor
+#[derive(Args, Debug)]
+pub struct DiscloseCommand {
+    /// Index of the coin in the wallet
+    #[arg(long, short = 'c')]
+    pub coin: usize,
+
+    /// Output file for the disclosure
+    #[arg(long, short = 'o', default_value = "disclosure.json")]
+    pub out: String,
+}
+
+impl DiscloseCommand {
+    pub async fn execute(&self) -> Result<(), Box<dyn std::error::Error>> {
+        println!("{}", "🔍 DarkMint - Coin Disclosure".bright_cyan().bold());
+        println!("{}", "═".repeat(50).bright_black());
+        println!();
+
+        let wallet = open_wallet()?;
+        let coin = wallet
+            .get_coins()
+            .get(self.coin)
+            .ok_or_else(|| format!("Coin #{} not found in wallet", self.coin))?;
+
+        let disclosure = Disclosure::new(coin)?;
+        disclosure.save_to_file(&self.out)?;
+
+        println!("{}", "📋 Disclosure Details".bright_green().bold());
+        println!("{}", "─".repeat(30).bright_black());
+        println!(
+            "{} {}",
+            "Burn Address:".bright_white(),
+            disclosure.burn_address.to_string().bright_red()
+        );
+        println!("{} {}", "Block:".bright_white(), disclosure.block_number);
+        println!(
+            "{} {}",
+            "Amount:".bright_white(),
+            Amount::from_wei(disclosure.amount)
+        );
+        println!(
+            "{} {:#x}",
+            "Commitment:".bright_white(),
+            disclosure.commitment
+        );
+        println!("{} {}", "Nullifier:".bright_white(), disclosure.nullifier);
+        println!(
+            "{} {}",
+            "Mint Transaction:".bright_white(),
+            disclosure.mint_tx_hash
+        );
+        println!();
+        println!(
+            "{} Disclosure saved to '{}'",
+            "💾".bright_blue(),
+            self.out.bright_cyan()
+        );
+        println!(
+            "{} Share this file only with the auditor - it reveals the amount and salt",
+            "⚠️".bright_yellow()
+        );
+
+        Ok(())
+    }
+}
+
+/// Verify a coin disclosure received from a wallet owner
+#[derive(Args, Debug)]
+pub struct VerifyDisclosureCommand {
+    /// Disclosure file to verify
+    #[arg(long, short = 'f', default_value = "disclosure.json")]
+    pub file: String,
+
+    /// Commitment the disclosure is expected to open (e.g. taken from the mint record)
+    #[arg(long, short = 'c')]
+    pub commitment: Option<String>,
+
+    /// RPC provider URL used to check the mint and the burn address balance
+    #[arg(long, short = 'r')]
+    pub provider_url: Option<String>,
+
+    /// Only check the opening, without looking up the mint on chain
+    #[arg(long)]
+    pub offline: bool,
+}
+
+impl VerifyDisclosureCommand {
+    pub async fn execute(&self) -> Result<(), Box<dyn std::error::Error>> {
+        println!(
+            "{}",
+            "🔍 DarkMint - Disclosure Verification".bright_cyan().bold()
+        );
+        println!("{}", "═".repeat(50).bright_black());
+        println!();
+
+        let disclosure = Disclosure::load_from_file(&self.file)?;
+
+        disclosure.verify_opening()?;
+        println!(
+            "{} Amount {} and salt open commitment {:#x}",
+            "✅".bright_green(),
+            Amount::from_wei(disclosure.amount),
+            disclosure.commitment
+        );
+
+        if let Some(expected) = &self.commitment {
+            if expected.parse::<alloy::primitives::U256>()? != disclosure.commitment {
+                return Err("Disclosure does not open the expected commitment".into());
+            }
+            println!(
+                "{} Commitment matches the expected value",
+                "✅".bright_green()
+            );
+        }
+
+        if self.offline {
+            println!(
+                "{} Offline check only: the opening is not tied to a mint on chain",
+                "⚠️".bright_yellow()
+            );
+            return Ok(());
+        }
+
+        // The mint is looked up on the DarkMint contract of the selected network
+        let network = NetworkProfile::selected()?;
+        let provider_url = network.rpc_url(self.provider_url.as_deref()).await?;
+        disclosure
+            .verify_on_chain(&provider_url, network.contract_address)
+            .await?;
+        println!(
+            "{} Transaction {} minted {} under nullifier {}",
+            "✅".bright_green(),
+            disclosure.mint_tx_hash,
+            Amount::from_wei(disclosure.amount),
+            disclosure.nullifier
+        );
+        println!(
+            "{} Burn address {} held {} at block {}",
+            "✅".bright_green(),
+            disclosure.burn_address,
+            Amount::from_wei(disclosure.amount),
+            disclosure.block_number
+        );
+
+        Ok(())
+    }
+}
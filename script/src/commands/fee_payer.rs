@@ -0,0 +1,131 @@
+use clap::Args;
+use colored::*;
+use dialoguer::{Input, Password};
+use zeroize::Zeroizing;
+
+use alloy::primitives::Address;
+use alloy::signers::local::PrivateKeySigner;
+
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::signer::{self, FEE_PAYER_KEY_ENV, KEYSTORE_PASSWORD_ENV};
+
+/// Account paying the transaction fees
+#[derive(Args, Debug, Clone)]
+pub struct FeePayerArgs {
+    /// V3 JSON keystore of the fee paying account (will prompt if not provided)
+    #[arg(long, short = 'k', env = "DARKMINT_KEYSTORE")]
+    pub keystore: Option<PathBuf>,
+
+    /// Use a raw private key instead of a keystore (read from DARKMINT_FEE_PAYER_KEY or a hidden prompt)
+    #[arg(long, conflicts_with = "keystore")]
+    pub allow_raw_key: bool,
+}
+
+/// Secret the fee payer was unlocked with
+enum Secret {
+    Keystore {
+        path: PathBuf,
+        password: Zeroizing<String>,
+    },
+    RawKey(Zeroizing<String>),
+}
+
+/// Unlocked fee payer
+pub struct FeePayer {
+    signer: PrivateKeySigner,
+    secret: Secret,
+}
+
+impl fmt::Debug for FeePayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FeePayer")
+            .field("address", &self.signer.address())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FeePayerArgs {
+    /// Unlock the fee payer, prompting for the keystore path and password as needed
+    pub fn unlock(&self) -> Result<FeePayer, Box<dyn std::error::Error>> {
+        if self.allow_raw_key {
+            println!(
+                "{} {}",
+                "⚠️".bright_yellow(),
+                "Using a raw private key, prefer a keystore (--keystore)".bright_yellow()
+            );
+            let key = match env::var(FEE_PAYER_KEY_ENV) {
+                Ok(key) => Zeroizing::new(key),
+                Err(_) => Zeroizing::new(
+                    Password::new()
+                        .with_prompt(format!("{} Fee payer private key", "🔐".bright_blue()))
+                        .interact()?,
+                ),
+            };
+            let signer = signer::parse_raw_key(&key)?;
+            return Ok(FeePayer {
+                signer,
+                secret: Secret::RawKey(key),
+            });
+        }
+
+        let path = match &self.keystore {
+            Some(path) => path.clone(),
+            None => {
+                println!(
+                    "{} {}",
+                    "🔐".bright_blue(),
+                    "Keystore of the fee paying account required".bright_cyan()
+                );
+                let path: String = Input::new()
+                    .with_prompt("Path to the keystore file")
+                    .interact_text()?;
+                PathBuf::from(path)
+            }
+        };
+        let password = match env::var(KEYSTORE_PASSWORD_ENV) {
+            Ok(password) => Zeroizing::new(password),
+            Err(_) => Zeroizing::new(
+                Password::new()
+                    .with_prompt(format!("{} Keystore password", "🔑".bright_yellow()))
+                    .interact()?,
+            ),
+        };
+        let signer = signer::decrypt_keystore(&path, &password)?;
+        Ok(FeePayer {
+            signer,
+            secret: Secret::Keystore { path, password },
+        })
+    }
+}
+
+impl FeePayer {
+    /// Address of the fee paying account
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Signer of the fee paying account, for transactions sent in-process
+    pub fn signer(&self) -> PrivateKeySigner {
+        self.signer.clone()
+    }
+
+    /// Hand the fee payer to the worker process
+    ///
+    /// Secrets go through the environment, never the command line.
+    pub fn pass_to(&self, cmd: &mut Command) {
+        match &self.secret {
+            Secret::Keystore { path, password } => {
+                cmd.arg("--keystore").arg(path);
+                cmd.env(KEYSTORE_PASSWORD_ENV, password.as_str());
+            }
+            Secret::RawKey(key) => {
+                cmd.arg("--allow-raw-key");
+                cmd.env(FEE_PAYER_KEY_ENV, key.as_str());
+            }
+        }
+    }
+}
@@ -1,5 +1,15 @@
 pub mod burn;
+pub mod disclose;
+pub mod fee_payer;
+pub mod note;
+pub mod offline;
+pub mod password;
 pub mod prove;
+pub mod wallet;
 
 pub use burn::*;
+pub use disclose::*;
+pub use note::*;
+pub use offline::*;
 pub use prove::*;
+pub use wallet::*;
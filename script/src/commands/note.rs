@@ -0,0 +1,274 @@
+use clap::{Args, Subcommand};
+use colored::*;
+use dialoguer::Input;
+
+use alloy::providers::{Provider, ProviderBuilder};
+use std::path::Path;
+
+use super::password::open_wallet;
+use crate::amount::Amount;
+use crate::network::NetworkProfile;
+use crate::note::{self, Note, NoteImport, NOTE_HRP};
+use crate::viewing_key::WatchOnlyWallet;
+
+/// Hand coins to another wallet or device as portable notes
+#[derive(Args, Debug)]
+pub struct NoteCommand {
+    #[command(subcommand)]
+    pub action: NoteAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NoteAction {
+    /// 📤 Export a coin as a dmnote string
+    Export {
+        /// Index of the coin in the wallet
+        #[arg(long, short = 'c')]
+        coin: usize,
+
+        /// Seal the note to this viewing public key (hex)
+        #[arg(long, short = 't')]
+        to: Option<String>,
+
+        /// Also show the note as a QR code
+        #[arg(long, short = 'q')]
+        qr: bool,
+
+        /// RPC provider URL, defaults to the network's (the note records its chain)
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+    /// 📥 Import a dmnote string into the wallet
+    Import {
+        /// Note to import (will prompt if not provided)
+        note: Option<String>,
+
+        /// Keep the coin in this watch-only wallet file instead
+        #[arg(long, short = 'w')]
+        watch_only: Option<String>,
+
+        /// RPC provider URL, defaults to the network's (notes of other chains are refused)
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+    /// 🔑 Show the viewing public key senders seal notes to
+    Key,
+}
+
+impl NoteCommand {
+    pub async fn execute(&self) -> Result<(), Box<dyn std::error::Error>> {
+        println!("{}", "📝 DarkMint - Notes".bright_blue().bold());
+        println!("{}", "═".repeat(50).bright_black());
+        println!();
+
+        match &self.action {
+            NoteAction::Export {
+                coin,
+                to,
+                qr,
+                provider_url,
+            } => export(*coin, to.as_deref(), *qr, provider_url.as_deref()).await,
+            NoteAction::Import {
+                note,
+                watch_only,
+                provider_url,
+            } => match watch_only {
+                Some(file) => import_watch_only(note.as_deref(), file),
+                None => import(note.as_deref(), provider_url.as_deref()).await,
+            },
+            NoteAction::Key => key(),
+        }
+    }
+}
+
+/// Parse a hex-encoded viewing public key
+fn parse_viewing_public_key(hex_key: &str) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+    let mut key = [0u8; 32];
+    hex::decode_to_slice(hex_key.trim_start_matches("0x"), &mut key)
+        .map_err(|e| format!("Invalid viewing public key: {}", e))?;
+    Ok(key)
+}
+
+/// Get the chain id of the selected network's provider
+async fn connected_chain_id(provider_url: Option<&str>) -> Result<u64, Box<dyn std::error::Error>> {
+    let provider_url = NetworkProfile::selected()?.rpc_url(provider_url).await?;
+    let provider = ProviderBuilder::new().connect(&provider_url).await?;
+    Ok(provider.get_chain_id().await?)
+}
+
+async fn export(
+    coin: usize,
+    to: Option<&str>,
+    qr: bool,
+    provider_url: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let recipient = to.map(parse_viewing_public_key).transpose()?;
+    let mut wallet = open_wallet()?;
+    wallet.set_chain_id(connected_chain_id(provider_url).await?);
+
+    let exported = wallet.export_note(coin)?;
+    let text = match &recipient {
+        Some(recipient) => exported.encode_sealed(recipient)?,
+        None => exported.encode()?.to_string(),
+    };
+
+    println!("{}", "📤 Note".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    println!("{}", text.bright_white());
+    if qr {
+        println!();
+        println!("{}", note::render_qr(&text)?);
+    }
+    println!();
+    println!(
+        "{} {}",
+        "Amount:".bright_white(),
+        Amount::from_wei(exported.amount)
+    );
+    if let Some(address) = exported.burn_address {
+        println!(
+            "{} {}",
+            "Burn Address:".bright_white(),
+            address.to_string().bright_red()
+        );
+    }
+    println!();
+
+    if exported.burn_preimage.is_some() {
+        println!(
+            "{} {}",
+            "⚠️".bright_yellow(),
+            "The note contains the burn preimage: whoever holds it can mint this coin."
+                .bright_yellow()
+                .bold()
+        );
+        println!(
+            "{} This wallet can still mint it too, the first mint wins",
+            "⚠️".bright_yellow()
+        );
+    } else {
+        println!(
+            "{} The coin is minted, the note only reveals its amount and salt",
+            "💡".bright_yellow()
+        );
+    }
+    if recipient.is_none() {
+        println!(
+            "{} Pass --to with the recipient's viewing public key to seal the note",
+            "💡".bright_yellow()
+        );
+    }
+
+    Ok(())
+}
+
+/// Take the note from the argument or prompt for it
+fn read_note(text: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(match text {
+        Some(text) => text.to_string(),
+        None => Input::new()
+            .with_prompt(format!(
+                "{} Paste the {} note",
+                "📥".bright_green(),
+                NOTE_HRP
+            ))
+            .interact_text()?,
+    })
+}
+
+async fn import(
+    text: Option<&str>,
+    provider_url: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = read_note(text)?;
+    let mut wallet = open_wallet()?;
+    wallet.set_chain_id(connected_chain_id(provider_url).await?);
+    let received = Note::decode(&text, Some(&wallet.viewing_key()?))?;
+
+    let outcome = wallet.import_note(&received)?;
+    let message = match outcome {
+        NoteImport::Added => "Coin added to the wallet",
+        NoteImport::Updated => "Coin already in the wallet, its burn details were completed",
+        NoteImport::Known => "Coin already in the wallet, nothing to import",
+    };
+    println!("{} {}", "✅".bright_green(), message.bright_green().bold());
+    println!(
+        "{} {}",
+        "Amount:".bright_white(),
+        Amount::from_wei(received.amount)
+    );
+    if let Some(address) = received.burn_address {
+        println!(
+            "{} {}",
+            "Burn Address:".bright_white(),
+            address.to_string().bright_red()
+        );
+    }
+    if outcome == NoteImport::Added && received.burn_preimage.is_some() {
+        println!(
+            "{} Run 'darkmint prove --src-burn-addr <BURN_ADDRESS>' to mint it",
+            "💡".bright_yellow()
+        );
+    }
+
+    Ok(())
+}
+
+fn import_watch_only(text: Option<&str>, file: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let text = read_note(text)?;
+    let mut watch_only = WatchOnlyWallet::load(Path::new(file))?;
+    let received = Note::decode(&text, Some(&watch_only.viewing_key()?))?;
+
+    let (coin, added) = watch_only.receive_note(&received)?;
+    if added {
+        watch_only.save(Path::new(file))?;
+        println!(
+            "{} {}",
+            "✅".bright_green(),
+            "Coin added to the watch-only wallet".bright_green().bold()
+        );
+    } else {
+        println!(
+            "{} {}",
+            "✅".bright_green(),
+            "Coin already in the watch-only wallet, nothing to import"
+                .bright_green()
+                .bold()
+        );
+    }
+    println!(
+        "{} {}",
+        "Amount:".bright_white(),
+        Amount::from_wei(coin.amount)
+    );
+    if let Some(address) = coin.burn_address {
+        println!(
+            "{} {}",
+            "Burn Address:".bright_white(),
+            address.to_string().bright_red()
+        );
+    }
+    if received.burn_preimage.is_some() {
+        println!(
+            "{} The burn preimage was not kept, import the note into a full wallet to mint it",
+            "💡".bright_yellow()
+        );
+    }
+
+    Ok(())
+}
+
+fn key() -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = open_wallet()?;
+
+    println!(
+        "{} {}",
+        "Viewing public key:".bright_white(),
+        hex::encode(wallet.viewing_key()?.public_key()).bright_cyan()
+    );
+    println!(
+        "{} Share it with senders so they can seal notes to this wallet",
+        "💡".bright_yellow()
+    );
+    Ok(())
+}
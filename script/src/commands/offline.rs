@@ -0,0 +1,540 @@
+use clap::{Args, Subcommand};
+use colored::*;
+
+use alloy::{primitives::Address, providers::ProviderBuilder};
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::fee_payer::FeePayerArgs;
+use super::password::{open_wallet, wallet_password};
+use crate::amount::Amount;
+use crate::burn_index::DEFAULT_GAP_LIMIT;
+use crate::linkability::{check_mint_against_history, check_mint_linkability, WalletHistory};
+use crate::network::{NetworkProfile, ProofBlock};
+use crate::offline::{self, ProvingRequest, SignedTx, TxPurpose, TxRequest};
+use crate::wallet_file::WALLET_PASSWORD_ENV;
+use crate::wallet_location::resolve_existing_wallet_path;
+
+/// Split burns and proofs between an online and an air-gapped machine
+#[derive(Args, Debug)]
+pub struct OfflineCommand {
+    #[command(subcommand)]
+    pub action: OfflineAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OfflineAction {
+    /// 🌐 Prepare an unsigned burn (online)
+    BurnRequest {
+        /// Fee paying account the burn is sent from
+        #[arg(long, short = 'f')]
+        from: Address,
+
+        /// Amount of ETH to burn
+        #[arg(long, short = 'a')]
+        amount: Amount,
+
+        /// Use a gas price instead of EIP-1559 fees
+        #[arg(long)]
+        legacy: bool,
+
+        /// Output file for the request
+        #[arg(long, short = 'o', default_value = "burn-request.json")]
+        out: PathBuf,
+
+        /// RPC provider URL, defaults to the endpoints of the network profile
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+    /// ✍️ Sign a burn or mint request (offline)
+    Sign {
+        /// Request written by burn-request or mint-request
+        #[arg(long, short = 'i', default_value = "burn-request.json")]
+        request: PathBuf,
+
+        /// Label stored with the burn address, for burns
+        #[arg(long, short = 'l')]
+        label: Option<String>,
+
+        /// Output file for the signed transaction
+        #[arg(long, short = 'o', default_value = "signed-tx.json")]
+        out: PathBuf,
+
+        /// Account that signs the transaction
+        #[command(flatten)]
+        fee_payer: FeePayerArgs,
+    },
+    /// 📡 Broadcast a transaction signed offline (online)
+    Broadcast {
+        /// Signed transaction written by sign
+        #[arg(default_value = "signed-tx.json")]
+        file: PathBuf,
+
+        /// RPC provider URL, defaults to the endpoints of the network profile
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+    /// 🌐 Fetch the account proof and block header of a burn address (online)
+    ProvingRequest {
+        /// Funded burn address to prove
+        #[arg(long, short = 's')]
+        burn_addr: Address,
+
+        /// Block to prove against: latest, safe, finalized or a number of
+        /// confirmations (defaults to the network's proof_block)
+        #[arg(long)]
+        proof_block: Option<ProofBlock>,
+
+        /// Output file for the request
+        #[arg(long, short = 'o', default_value = "proving-request.json")]
+        out: PathBuf,
+
+        /// RPC provider URL, defaults to the endpoints of the network profile
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+    /// 🔮 Generate the proof of a proving request with the local prover (offline)
+    Prove {
+        /// Request written by proving-request
+        #[arg(long, short = 'i', default_value = "proving-request.json")]
+        request: PathBuf,
+
+        /// Enable encryption for the coin
+        #[arg(long, short = 'e')]
+        encrypted: bool,
+
+        /// Number of burn address indexes searched past the next unused one
+        #[arg(long, short = 'g', default_value_t = DEFAULT_GAP_LIMIT)]
+        gap_limit: u64,
+
+        /// Output file for the proof artifact
+        #[arg(long, short = 'o', default_value = "proof.json")]
+        out: PathBuf,
+    },
+    /// 🌐 Prepare an unsigned mint of an aggregated proof (online)
+    MintRequest {
+        /// Fee paying account
+        #[arg(long, short = 'f')]
+        from: Address,
+
+        /// Account receiving the minted tokens, not the account that funded the burn
+        #[arg(long, short = 'd')]
+        dst_addr: Address,
+
+        /// Proof artifact written by prove
+        #[arg(long, short = 'p', default_value = "proof.json")]
+        proof: PathBuf,
+
+        /// Proving request the proof was generated from, for the amount
+        #[arg(long, short = 'i', default_value = "proving-request.json")]
+        request: PathBuf,
+
+        /// Use a gas price instead of EIP-1559 fees
+        #[arg(long)]
+        legacy: bool,
+
+        /// Output file for the request
+        #[arg(long, short = 'o', default_value = "mint-request.json")]
+        out: PathBuf,
+
+        /// Refuse the mint when anything links it to its burn
+        #[arg(long)]
+        strict_privacy: bool,
+
+        /// RPC provider URL, defaults to the endpoints of the network profile
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+}
+
+impl OfflineCommand {
+    pub async fn execute(&self) -> Result<(), Box<dyn std::error::Error>> {
+        println!("{}", "🧊 DarkMint - Offline Workflow".bright_blue().bold());
+        println!("{}", "═".repeat(50).bright_black());
+        println!();
+
+        match &self.action {
+            OfflineAction::BurnRequest {
+                from,
+                amount,
+                legacy,
+                out,
+                provider_url,
+            } => burn_request(*from, *amount, *legacy, out, provider_url.as_deref()).await,
+            OfflineAction::Sign {
+                request,
+                label,
+                out,
+                fee_payer,
+            } => sign(request, label.as_deref(), out, fee_payer).await,
+            OfflineAction::Broadcast { file, provider_url } => {
+                broadcast(file, provider_url.as_deref()).await
+            }
+            OfflineAction::ProvingRequest {
+                burn_addr,
+                proof_block,
+                out,
+                provider_url,
+            } => proving_request(*burn_addr, *proof_block, out, provider_url.as_deref()).await,
+            OfflineAction::Prove {
+                request,
+                encrypted,
+                gap_limit,
+                out,
+            } => prove(request, *encrypted, *gap_limit, out),
+            OfflineAction::MintRequest {
+                from,
+                dst_addr,
+                proof,
+                request,
+                legacy,
+                out,
+                strict_privacy,
+                provider_url,
+            } => {
+                let accounts = MintAccounts {
+                    fee_payer: *from,
+                    recipient: *dst_addr,
+                };
+                mint_request(
+                    accounts,
+                    proof,
+                    request,
+                    *legacy,
+                    out,
+                    *strict_privacy,
+                    provider_url.as_deref(),
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Print where a bundle was written and where it goes next
+fn print_saved(path: &Path, next: &str) {
+    println!();
+    println!(
+        "{} Saved to '{}'",
+        "💾".bright_blue(),
+        path.display().to_string().bright_cyan()
+    );
+    println!("{} {}", "💡".bright_yellow(), next);
+}
+
+/// Print the fees of a transaction request
+fn print_request(request: &TxRequest) {
+    println!("{} {}", "Chain:".bright_white(), request.chain_id);
+    println!(
+        "{} {}",
+        "From:".bright_white(),
+        request.from.to_string().bright_yellow()
+    );
+    println!("{} {}", "Nonce:".bright_white(), request.nonce);
+    println!("{} {}", "Gas Limit:".bright_white(), request.gas_limit);
+    match request.max_priority_fee_per_gas {
+        Some(priority_fee) => println!(
+            "{} {} wei (priority fee {} wei)",
+            "Max Fee:".bright_white(),
+            request.max_fee_per_gas,
+            priority_fee
+        ),
+        None => println!(
+            "{} {} wei (legacy)",
+            "Gas Price:".bright_white(),
+            request.max_fee_per_gas
+        ),
+    }
+}
+
+async fn burn_request(
+    from: Address,
+    amount: Amount,
+    legacy: bool,
+    out: &Path,
+    provider_url: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let network = NetworkProfile::selected()?;
+    let provider_url = network.rpc_url(provider_url).await?;
+
+    let request = offline::prepare_burn_request(&provider_url, from, amount, legacy).await?;
+    offline::write_bundle(out, &request)?;
+
+    println!("{}", "📋 Burn Request".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    println!("{} {}", "Amount:".bright_white(), amount);
+    print_request(&request);
+    print_saved(
+        out,
+        "Sign it on the offline machine with 'darkmint offline sign', which picks the burn address",
+    );
+    Ok(())
+}
+
+async fn sign(
+    request_path: &Path,
+    label: Option<&str>,
+    out: &Path,
+    fee_payer: &FeePayerArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let request: TxRequest = offline::read_bundle(request_path)?;
+    println!("{}", "📋 Transaction to Sign".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    print_request(&request);
+
+    let signer = fee_payer.unlock()?.signer();
+    let signed = match request.purpose {
+        TxPurpose::Burn => {
+            println!(
+                "{} {}",
+                "Burn Amount:".bright_white(),
+                Amount::from_wei(request.value)
+            );
+            let mut wallet = open_wallet()?;
+            let signed = offline::sign_burn_request(&mut wallet, &request, &signer, label).await?;
+            let burn_index = signed.burn_index.unwrap_or_default();
+            println!(
+                "{} #{} {}",
+                "Burn Address:".bright_white(),
+                burn_index,
+                wallet
+                    .derive_burn_address(burn_index)?
+                    .address
+                    .to_string()
+                    .bright_red()
+            );
+            signed
+        }
+        TxPurpose::Mint => {
+            let to = request
+                .to
+                .ok_or("Mint request without a contract address")?;
+            println!("{} {}", "Contract:".bright_white(), to);
+            offline::sign_tx_request(&request, to, &signer).await?
+        }
+    };
+    offline::write_bundle(out, &signed)?;
+
+    println!("{} {}", "Transaction:".bright_white(), signed.tx_hash);
+    print_saved(
+        out,
+        "Broadcast it from the online machine with 'darkmint offline broadcast'",
+    );
+    Ok(())
+}
+
+async fn broadcast(
+    file: &Path,
+    provider_url: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let signed: SignedTx = offline::read_bundle(file)?;
+    let network = NetworkProfile::selected()?;
+    let provider_url = network.rpc_url(provider_url).await?;
+
+    println!(
+        "{} Broadcasting {:?} transaction {} from {}",
+        "📡".bright_blue(),
+        signed.purpose,
+        signed.tx_hash,
+        signed.from
+    );
+    if !offline::broadcast_signed_tx(&provider_url, &signed, network.confirmations).await? {
+        return Err(format!("Transaction {} reverted", signed.tx_hash).into());
+    }
+
+    println!(
+        "{} {}",
+        "✅".bright_green(),
+        "Transaction confirmed".bright_green()
+    );
+    if signed.purpose == TxPurpose::Burn {
+        println!(
+            "{} Fetch the proving data with 'darkmint offline proving-request --burn-addr <burn address>'",
+            "💡".bright_yellow()
+        );
+    }
+    Ok(())
+}
+
+async fn proving_request(
+    burn_addr: Address,
+    proof_block: Option<ProofBlock>,
+    out: &Path,
+    provider_url: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let network = NetworkProfile::selected()?;
+    let provider_url = network.rpc_url(provider_url).await?;
+
+    let proof_block = proof_block.unwrap_or(network.proof_block_policy());
+    let request = offline::prepare_proving_request(&provider_url, burn_addr, proof_block).await?;
+    offline::write_bundle(out, &request)?;
+
+    println!("{}", "📋 Proving Request".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    println!(
+        "{} {}",
+        "Burn Address:".bright_white(),
+        burn_addr.to_string().bright_red()
+    );
+    println!(
+        "{} {}",
+        "Amount:".bright_white(),
+        Amount::from_wei(request.account_proof.balance)
+    );
+    println!(
+        "{} {}",
+        "Block:".bright_white(),
+        request.block.header.number
+    );
+    print_saved(
+        out,
+        "Prove it on the offline machine with 'darkmint offline prove'",
+    );
+    Ok(())
+}
+
+fn prove(
+    request_path: &Path,
+    encrypted: bool,
+    gap_limit: u64,
+    out: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Check the request before spending time on the prover
+    let request: ProvingRequest = offline::read_bundle(request_path)?;
+    request.validate()?;
+
+    // The network prover would see the burn preimage
+    let prover = env::var("SP1_PROVER").unwrap_or_else(|_| "cpu".to_string());
+    if prover == "network" {
+        return Err("Offline proving needs a local prover, set SP1_PROVER to cpu or cuda".into());
+    }
+    println!(
+        "{} Proving burn address {} with the {} prover, this may take a while",
+        "🔮".bright_magenta(),
+        request.burn_address.to_string().bright_red(),
+        prover
+    );
+
+    let wallet_password = wallet_password()?;
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--bin", "fibonacci", "--release", "--"])
+        .arg("--prove-offline")
+        .arg("--proving-request")
+        .arg(request_path)
+        .arg("--proof-out")
+        .arg(out)
+        .arg("--gap-limit")
+        .arg(gap_limit.to_string());
+    if encrypted {
+        cmd.arg("--encrypted");
+    }
+    cmd.env("SP1_PROVER", &prover);
+    cmd.env(WALLET_PASSWORD_ENV, &wallet_password);
+
+    let status = cmd.status()?;
+    if !status.success() {
+        return Err(format!(
+            "Proof generation failed with exit code: {:?}",
+            status.code()
+        )
+        .into());
+    }
+
+    print_saved(
+        out,
+        "Take it to the online machine, submit it for aggregation, then run 'darkmint offline mint-request'",
+    );
+    Ok(())
+}
+
+/// Accounts of a mint request
+struct MintAccounts {
+    /// Account paying the mint gas
+    fee_payer: Address,
+    /// Account receiving the minted tokens
+    recipient: Address,
+}
+
+async fn mint_request(
+    accounts: MintAccounts,
+    proof: &Path,
+    request_path: &Path,
+    legacy: bool,
+    out: &Path,
+    strict_privacy: bool,
+    provider_url: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let proving_request: ProvingRequest = offline::read_bundle(request_path)?;
+    let public_values = offline::read_public_values(proof)?;
+    let network = NetworkProfile::selected()?;
+    let provider_url = network.rpc_url(provider_url).await?;
+
+    // What links the mint to its burn is checked before the request is written
+    let provider = ProviderBuilder::new().connect(&provider_url).await?;
+    let burn_address = proving_request.burn_address;
+    let balance = proving_request.account_proof.balance;
+    if resolve_existing_wallet_path().is_ok() {
+        let wallet = open_wallet()?;
+        check_mint_linkability(
+            &wallet,
+            &provider,
+            accounts.fee_payer,
+            accounts.recipient,
+            burn_address,
+            balance,
+            strict_privacy,
+        )
+        .await?;
+    } else {
+        println!(
+            "{} No wallet on this machine, the mint is checked without the wallet history",
+            "⚠️".bright_yellow()
+        );
+        check_mint_against_history(
+            &WalletHistory::default(),
+            &provider,
+            accounts.fee_payer,
+            accounts.recipient,
+            burn_address,
+            balance,
+            strict_privacy,
+        )
+        .await?;
+    }
+
+    let amount = Amount::from_wei(balance);
+    let (request, nullifier) = offline::prepare_mint_request(
+        &provider_url,
+        accounts.fee_payer,
+        accounts.recipient,
+        network.contract_address,
+        &proving_request,
+        &public_values,
+        legacy,
+    )
+    .await?;
+    offline::write_bundle(out, &request)?;
+
+    println!("{}", "📋 Mint Request".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    println!("{} {}", "Amount:".bright_white(), amount);
+    println!("{} {}", "Nullifier:".bright_white(), nullifier);
+    println!(
+        "{} {}",
+        "Recipient:".bright_white(),
+        accounts.recipient.to_string().bright_yellow()
+    );
+    println!(
+        "{} {}",
+        "Contract:".bright_white(),
+        network.contract_address
+    );
+    print_request(&request);
+    print_saved(
+        out,
+        "Sign it offline with 'darkmint offline sign --request <file>', then broadcast it",
+    );
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use colored::*;
+use dialoguer::Password;
+
+use std::env;
+use std::fs;
+
+use crate::wallet_file::{self, WALLET_PASSWORD_ENV};
+use crate::wallet_location::{resolve_existing_wallet_path, resolve_wallet_path};
+use crate::Wallet;
+
+/// Get the wallet password from `DARKMINT_WALLET_PASSWORD` or prompt for it
+///
+/// When the wallet file is not encrypted yet (new wallet or legacy plaintext
+/// wallet about to be migrated) the new password has to be entered twice.
+pub fn wallet_password() -> Result<String, Box<dyn std::error::Error>> {
+    if let Ok(password) = env::var(WALLET_PASSWORD_ENV) {
+        return Ok(password);
+    }
+
+    let encrypted = fs::read_to_string(resolve_wallet_path(None)?)
+        .map(|content| wallet_file::is_encrypted(&content))
+        .unwrap_or(false);
+
+    let prompt = Password::new().with_prompt(format!("{} Wallet password", "🔑".bright_yellow()));
+    let password = if encrypted {
+        prompt.interact()?
+    } else {
+        println!(
+            "{} {}",
+            "🔒".bright_blue(),
+            "Your wallet will be encrypted with this password".bright_cyan()
+        );
+        prompt
+            .with_confirmation("Confirm wallet password", "Passwords do not match")
+            .interact()?
+    };
+
+    Ok(password)
+}
+
+/// Open the wallet and unlock it with the password from the environment or a prompt
+pub fn open_wallet() -> Result<Wallet, Box<dyn std::error::Error>> {
+    // Fail on a missing wallet before asking for its password
+    resolve_existing_wallet_path()?;
+    let password = wallet_password()?;
+    Wallet::open_selected_with_password(Some(&password))
+}
@@ -1,41 +1,21 @@
 use clap::Args;
 use colored::*;
 
-use dialoguer::{Confirm, Input, Password};
+use dialoguer::{Confirm, Input};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::time::Duration;
 use tokio::time::sleep;
 
 use std::env;
 use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
 use std::process::{Command, Stdio};
 
-/// Expand environment variables in a string (e.g., "$PRIVATE_KEY" -> actual value)
-fn expand_env_vars(input: &str) -> Result<String, Box<dyn std::error::Error>> {
-    if input.starts_with('$') {
-        let var_name = &input[1..]; // Remove the '$' prefix
-        match env::var(var_name) {
-            Ok(value) => {
-                println!(
-                    "{} Expanded {} to environment variable value",
-                    "🔧".bright_green(),
-                    input
-                );
-                Ok(value)
-            }
-            Err(_) => {
-                eprintln!(
-                    "{} Environment variable {} not found",
-                    "❌".bright_red(),
-                    var_name
-                );
-                Err(format!("Environment variable {} not found", var_name).into())
-            }
-        }
-    } else {
-        Ok(input.to_string())
-    }
-}
+use super::fee_payer::FeePayerArgs;
+use super::password::wallet_password;
+use crate::burn_index::DEFAULT_GAP_LIMIT;
+use crate::network::{NetworkProfile, ProofBlock};
+use crate::wallet_file::WALLET_PASSWORD_ENV;
 
 /// Generate proof to mint private coins
 #[derive(Args, Debug)]
@@ -48,22 +28,39 @@ pub struct ProveCommand {
     #[arg(long, short = 's')]
     pub src_burn_addr: Option<String>,
 
-    /// Private key for fee payment (will prompt if not provided)
-    #[arg(long, short = 'p')]
-    pub priv_src: Option<String>,
+    /// Account paying the mint fees
+    #[command(flatten)]
+    pub fee_payer: FeePayerArgs,
 
-    /// RPC provider URL
-    #[arg(
-        long,
-        short = 'r',
-        default_value = "https://horizen-rpc-testnet.appchain.base.org/"
-    )]
-    pub provider_url: String,
+    /// RPC provider URL, defaults to the endpoints of the network profile
+    #[arg(long, short = 'r')]
+    pub provider_url: Option<String>,
 
     /// Enable encryption for the coin
     #[arg(long, short = 'e')]
     pub encrypted: bool,
 
+    /// Number of burn address indexes searched past the next unused one
+    #[arg(long, short = 'g', default_value_t = DEFAULT_GAP_LIMIT)]
+    pub gap_limit: u64,
+
+    /// Block to prove against: latest, safe, finalized or a number of confirmations
+    /// (defaults to the proof_block of the network profile)
+    #[arg(long)]
+    pub proof_block: Option<ProofBlock>,
+
+    /// Execute the program and simulate the mint without proving or sending anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Aggregation receipt of the proof, to also simulate checkHash in a dry run
+    #[arg(long, requires = "dry_run")]
+    pub aggregation: Option<PathBuf>,
+
+    /// Refuse to mint when anything links it to its burn, instead of warning
+    #[arg(long)]
+    pub strict_privacy: bool,
+
     /// Skip confirmation prompts
     #[arg(long, short = 'y')]
     pub yes: bool,
@@ -121,33 +118,15 @@ impl ProveCommand {
             }
         };
 
-        // Get private key interactively if not provided
-        let priv_src_raw = match &self.priv_src {
-            Some(key) => key.clone(),
-            None => {
-                println!(
-                    "{} {}",
-                    "🔐".bright_blue(),
-                    "Private key required for fee payment".bright_cyan()
-                );
-                let key = Input::new()
-                    .with_prompt("Enter your private key")
-                    .interact()?;
-                key
-            }
-        };
+        // Unlock the fee payer from its keystore (or an explicitly allowed raw key)
+        let fee_payer = self.fee_payer.unlock()?;
 
-        // Expand environment variables if needed (e.g., "$PRIVATE_KEY" -> actual value)
-        let priv_src = expand_env_vars(&priv_src_raw)?;
+        // RPC endpoint of the selected network, checked before anything is spent
+        let network = NetworkProfile::selected()?;
+        let provider_url = network.rpc_url(self.provider_url.as_deref()).await?;
 
-        // Validate private key format
-        if !priv_src.starts_with("0x") || priv_src.len() != 66 {
-            eprintln!(
-                "{} Private key must be a valid hex string starting with 0x",
-                "❌".bright_red()
-            );
-            return Err("Invalid private key format".into());
-        }
+        // Wallet password is handed to the worker process through the environment
+        let wallet_password = wallet_password()?;
 
         // Display proof generation details
         println!();
@@ -163,10 +142,17 @@ impl ProveCommand {
             "Burn Address:".bright_white(),
             src_burn_addr.bright_red()
         );
+        println!("{} {}", "Network:".bright_white(), network.name.bright_cyan());
+        let proof_block = self.proof_block.unwrap_or(network.proof_block_policy());
+        println!(
+            "{} {}",
+            "Proof Block:".bright_white(),
+            proof_block.to_string().bright_cyan()
+        );
         println!(
             "{} {}",
             "Provider:".bright_white(),
-            self.provider_url.bright_cyan()
+            provider_url.bright_cyan()
         );
         println!(
             "{} {}",
@@ -178,10 +164,9 @@ impl ProveCommand {
             }
         );
         println!(
-            "{} {}...{}",
+            "{} {}",
             "Fee Payer:".bright_white(),
-            &priv_src[0..8].bright_yellow(),
-            &priv_src[priv_src.len() - 6..].bright_yellow()
+            fee_payer.address().to_string().bright_yellow()
         );
 
         // Encryption warning/info
@@ -201,8 +186,8 @@ impl ProveCommand {
             );
         }
 
-        // Confirmation unless --yes flag is used
-        if !self.yes {
+        // Confirmation unless --yes flag is used, a dry run spends nothing
+        if !self.yes && !self.dry_run {
             println!();
             let confirmed = Confirm::new()
                 .with_prompt(&format!(
@@ -264,22 +249,35 @@ impl ProveCommand {
             .arg(&dst_addr)
             .arg("--src-burn-addr")
             .arg(&src_burn_addr)
-            .arg("--priv-src")
-            .arg(&priv_src)
             .arg("--provider-url")
-            .arg(&self.provider_url);
+            .arg(&provider_url)
+            .arg("--gap-limit")
+            .arg(self.gap_limit.to_string())
+            .arg("--proof-block")
+            .arg(proof_block.to_string());
 
         if self.encrypted {
             cmd.arg("--encrypted");
         }
 
+        // A dry run executes the program locally, without the prover network
+        if self.dry_run {
+            cmd.arg("--dry-run");
+            if let Some(aggregation) = &self.aggregation {
+                cmd.arg("--aggregation").arg(aggregation);
+            }
+        }
+        if self.strict_privacy {
+            cmd.arg("--strict-privacy");
+        }
+
         // Set SP1 environment variables
         cmd.env("SP1_PROVER", "network");
 
         // Get SP1 private key from environment and pass it to the command
         if let Ok(sp1_private_key) = env::var("NETWORK_PRIVATE_KEY") {
             cmd.env("NETWORK_PRIVATE_KEY", sp1_private_key);
-        } else {
+        } else if !self.dry_run {
             // If NETWORK_PRIVATE_KEY is not set, show a warning but continue
             println!();
             println!(
@@ -295,6 +293,9 @@ impl ProveCommand {
             println!();
         }
 
+        fee_payer.pass_to(&mut cmd);
+        cmd.env(WALLET_PASSWORD_ENV, &wallet_password);
+
         // Set up command to show real-time output
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
@@ -331,7 +332,16 @@ impl ProveCommand {
                 // Wait for the process to complete
                 match child.wait() {
                     Ok(status) => {
-                        if status.success() {
+                        if status.success() && self.dry_run {
+                            println!();
+                            println!(
+                                "{} {}",
+                                "🧪".bright_blue(),
+                                "Dry run finished, nothing was proven or sent"
+                                    .bright_blue()
+                                    .bold()
+                            );
+                        } else if status.success() {
                             println!();
                             println!(
                                 "{} {}",
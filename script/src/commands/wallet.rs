@@ -0,0 +1,812 @@
+use clap::{Args, Subcommand};
+use colored::*;
+use dialoguer::{Confirm, Input, Password};
+
+use alloy::providers::{Provider, ProviderBuilder};
+use std::path::Path;
+
+use super::password::{open_wallet, wallet_password};
+use crate::amount::Amount;
+use crate::burn_index::{BurnAddressStatus, DEFAULT_GAP_LIMIT};
+use crate::derivation::DerivationScheme;
+use crate::mnemonic::MNEMONIC_WORDS;
+use crate::network::NetworkProfile;
+use crate::shamir::{EntropyShare, SHARE_CHECK_ADDRESSES};
+use crate::store::FileStore;
+use crate::viewing_key::{WatchOnlyWallet, WATCH_ONLY_FILENAME};
+use crate::wallet_location::{self, resolve_wallet_path, Settings};
+use crate::{CoinState, Wallet};
+
+/// Manage the local DarkMint wallet
+#[derive(Args, Debug)]
+pub struct WalletCommand {
+    #[command(subcommand)]
+    pub action: WalletAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum WalletAction {
+    /// ✨ Create a new wallet (named with the global --wallet flag)
+    Create,
+    /// 📂 List the wallets in the wallet directory
+    List,
+    /// ⭐ Use a wallet by default when --wallet is not given
+    SetDefault {
+        /// Wallet name
+        name: String,
+    },
+    /// 🔑 Change the wallet password
+    ChangePassword,
+    /// 📝 Show the 24-word mnemonic backup of the wallet entropy
+    Backup,
+    /// ♻️ Restore the wallet from a 24-word mnemonic
+    Restore {
+        /// Restore a wallet created before hierarchical derivation
+        #[arg(long)]
+        legacy: bool,
+
+        /// Chain id used to show the first burn address of a hierarchical wallet, defaults to the network's
+        #[arg(long)]
+        chain_id: Option<u64>,
+    },
+    /// 🧩 Split the wallet entropy into Shamir shares, any THRESHOLD of which restore it
+    BackupShares {
+        /// Number of shares needed to restore the wallet
+        #[arg(long, short = 't')]
+        threshold: u8,
+
+        /// Number of shares to create
+        #[arg(long, short = 'n')]
+        shares: u8,
+
+        /// Chain id the burn addresses are checked on (hierarchical wallets), defaults to the network's
+        #[arg(long)]
+        chain_id: Option<u64>,
+    },
+    /// 🧩 Restore the wallet from Shamir shares
+    RestoreShares {
+        /// Chain id used to show the first burn address of a hierarchical wallet, defaults to the network's
+        #[arg(long)]
+        chain_id: Option<u64>,
+    },
+    /// 🧩 Check that Shamir shares restore this wallet
+    VerifyShares {
+        /// Chain id the burn addresses are checked on (hierarchical wallets), defaults to the network's
+        #[arg(long)]
+        chain_id: Option<u64>,
+    },
+    /// 🪙 Show the coins of the wallet and where they are in the mint pipeline
+    Coins,
+    /// 📇 List the reserved and used burn addresses with their labels
+    Addresses {
+        /// RPC provider URL, defaults to the network's (hierarchical wallets derive addresses for its chain)
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+    /// 🏷️ Set or clear the label of a burn address
+    Label {
+        /// Burn address index
+        index: u64,
+
+        /// New label, omit to clear it
+        label: Option<String>,
+    },
+    /// 🔎 Rediscover funded burn addresses and minted coins on chain
+    Recover {
+        /// Stop after this many consecutive unfunded burn addresses
+        #[arg(long, short = 'g', default_value_t = DEFAULT_GAP_LIMIT)]
+        gap_limit: u64,
+
+        /// RPC provider URL, defaults to the network's
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+
+        /// Whether the coins were minted in encrypted mode
+        #[arg(long, short = 'e')]
+        encrypted: bool,
+    },
+    /// 👀 Export a watch-only wallet (viewing key and burn addresses)
+    ExportWatchOnly {
+        /// Number of burn addresses to export
+        #[arg(long, short = 'n', default_value_t = DEFAULT_GAP_LIMIT)]
+        count: u64,
+
+        /// Output file for the watch-only wallet
+        #[arg(long, short = 'o', default_value = WATCH_ONLY_FILENAME)]
+        out: String,
+
+        /// RPC provider URL, defaults to the network's (hierarchical wallets derive addresses for its chain)
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+    /// 📡 Show deposits on the burn addresses of a watch-only wallet
+    Watch {
+        /// Watch-only wallet file
+        #[arg(long, short = 'f', default_value = WATCH_ONLY_FILENAME)]
+        file: String,
+
+        /// RPC provider URL, defaults to the network's
+        #[arg(long, short = 'r')]
+        provider_url: Option<String>,
+    },
+}
+
+impl WalletCommand {
+    pub async fn execute(&self) -> Result<(), Box<dyn std::error::Error>> {
+        println!("{}", "👛 DarkMint - Wallet".bright_blue().bold());
+        println!("{}", "═".repeat(50).bright_black());
+        println!();
+
+        match &self.action {
+            WalletAction::Create => create(),
+            WalletAction::List => list(),
+            WalletAction::SetDefault { name } => set_default(name),
+            WalletAction::ChangePassword => change_password(),
+            WalletAction::Backup => backup(),
+            WalletAction::Restore { legacy, chain_id } => restore(*legacy, *chain_id),
+            WalletAction::BackupShares {
+                threshold,
+                shares,
+                chain_id,
+            } => backup_shares(*threshold, *shares, *chain_id),
+            WalletAction::RestoreShares { chain_id } => restore_shares(*chain_id),
+            WalletAction::VerifyShares { chain_id } => verify_shares(*chain_id),
+            WalletAction::Coins => coins(),
+            WalletAction::Addresses { provider_url } => addresses(provider_url.as_deref()).await,
+            WalletAction::Label { index, label } => set_label(*index, label.as_deref()),
+            WalletAction::Recover {
+                gap_limit,
+                provider_url,
+                encrypted,
+            } => recover(*gap_limit, provider_url.as_deref(), *encrypted).await,
+            WalletAction::ExportWatchOnly {
+                count,
+                out,
+                provider_url,
+            } => export_watch_only(*count, out, provider_url.as_deref()).await,
+            WalletAction::Watch { file, provider_url } => {
+                watch(file, provider_url.as_deref()).await
+            }
+        }
+    }
+}
+
+fn create() -> Result<(), Box<dyn std::error::Error>> {
+    let path = resolve_wallet_path(None)?;
+    if path.exists() {
+        return Err(format!("Wallet file '{}' already exists", path.display()).into());
+    }
+
+    let password = wallet_password()?;
+    let wallet = Wallet::create(&path, Some(&password))?;
+
+    println!(
+        "{} {} {}",
+        "✅".bright_green(),
+        "Wallet created at".bright_green().bold(),
+        wallet.path().display().to_string().bright_cyan()
+    );
+    println!(
+        "{} Run 'darkmint wallet backup' and write the mnemonic down",
+        "💡".bright_yellow()
+    );
+    Ok(())
+}
+
+fn list() -> Result<(), Box<dyn std::error::Error>> {
+    let names = wallet_location::list_wallets()?;
+    let default_wallet = Settings::load()?.default_wallet;
+
+    println!(
+        "{} {}",
+        "Wallet directory:".bright_white(),
+        wallet_location::wallets_dir()?
+            .display()
+            .to_string()
+            .bright_cyan()
+    );
+    println!();
+    for name in &names {
+        if default_wallet.as_deref() == Some(name.as_str()) {
+            println!(
+                "  {} {}",
+                name.bright_green().bold(),
+                "(default)".bright_black()
+            );
+        } else {
+            println!("  {}", name);
+        }
+    }
+    if names.is_empty() {
+        println!("No wallets yet, create one with 'darkmint --wallet <NAME> wallet create'");
+    }
+    Ok(())
+}
+
+fn set_default(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = wallet_location::wallet_path(name)?;
+    if !path.exists() {
+        return Err(format!("No wallet named '{}' at '{}'", name, path.display()).into());
+    }
+
+    let mut settings = Settings::load()?;
+    settings.default_wallet = Some(name.to_string());
+    settings.save()?;
+
+    println!(
+        "{} '{}' is now the default wallet",
+        "✅".bright_green(),
+        name.bright_cyan()
+    );
+    Ok(())
+}
+
+fn change_password() -> Result<(), Box<dyn std::error::Error>> {
+    let old_password = wallet_password()?;
+    let mut wallet = Wallet::open(&resolve_wallet_path(None)?, Some(&old_password))?;
+
+    let new_password = Password::new()
+        .with_prompt(format!("{} New wallet password", "🔑".bright_yellow()))
+        .with_confirmation("Confirm new wallet password", "Passwords do not match")
+        .interact()?;
+
+    wallet.change_password(&old_password, &new_password)?;
+
+    println!(
+        "{} {}",
+        "✅".bright_green(),
+        "Wallet password changed".bright_green().bold()
+    );
+    Ok(())
+}
+
+/// Prompt for an optional mnemonic passphrase
+fn mnemonic_passphrase(confirm: bool) -> Result<String, Box<dyn std::error::Error>> {
+    let prompt = Password::new()
+        .with_prompt(format!(
+            "{} Mnemonic passphrase (leave empty for none)",
+            "🔐".bright_blue()
+        ))
+        .allow_empty_password(true);
+
+    let passphrase = if confirm {
+        prompt
+            .with_confirmation("Confirm mnemonic passphrase", "Passphrases do not match")
+            .interact()?
+    } else {
+        prompt.interact()?
+    };
+    Ok(passphrase)
+}
+
+fn backup() -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = open_wallet()?;
+
+    println!(
+        "{} {}",
+        "⚠️".bright_yellow(),
+        "Anyone with these words (and the passphrase) can mint your coins."
+            .bright_yellow()
+            .bold()
+    );
+    println!(
+        "{} {}",
+        "⚠️".bright_yellow(),
+        "Write them down offline and never store them in plain text.".bright_yellow()
+    );
+    println!();
+
+    let confirmed = Confirm::new()
+        .with_prompt("Show the mnemonic now?")
+        .default(false)
+        .interact()?;
+    if !confirmed {
+        println!("{} Operation cancelled by user", "🚫".bright_red());
+        return Ok(());
+    }
+
+    let passphrase = mnemonic_passphrase(true)?;
+    let phrase = wallet.export_mnemonic(&passphrase)?;
+
+    println!();
+    println!("{}", "📝 Wallet Mnemonic".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    for (i, word) in phrase.split_whitespace().enumerate() {
+        println!("{:>4}. {}", i + 1, word.bright_white().bold());
+    }
+    println!();
+    if !passphrase.is_empty() {
+        println!(
+            "{} The passphrase is required to restore this wallet",
+            "🔐".bright_blue()
+        );
+    }
+
+    Ok(())
+}
+
+fn restore(legacy: bool, chain_id: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
+    let chain_id = chain_id.or(NetworkProfile::selected()?.chain_id);
+    let phrase: String = Input::new()
+        .with_prompt(format!(
+            "{} Enter your {}-word mnemonic",
+            "📝".bright_green(),
+            MNEMONIC_WORDS
+        ))
+        .validate_with(|input: &String| -> Result<(), String> {
+            let words = input.split_whitespace().count();
+            if words == MNEMONIC_WORDS {
+                Ok(())
+            } else {
+                Err(format!("Expected {} words, got {}", MNEMONIC_WORDS, words))
+            }
+        })
+        .interact_text()?;
+    let passphrase = mnemonic_passphrase(false)?;
+    let password = wallet_password()?;
+
+    let derivation = if legacy {
+        DerivationScheme::Legacy
+    } else {
+        DerivationScheme::Hierarchical
+    };
+    let path = resolve_wallet_path(None)?;
+    let mut wallet = Wallet::restore_from_mnemonic(
+        FileStore::open(&path)?,
+        &phrase,
+        &passphrase,
+        derivation,
+        Some(&password),
+    )?;
+
+    println!(
+        "{} {} ({} derivation)",
+        "✅".bright_green(),
+        "Wallet restored".bright_green().bold(),
+        derivation
+    );
+    if let Some(chain_id) = chain_id {
+        wallet.set_chain_id(chain_id);
+    }
+    if legacy || chain_id.is_some() {
+        let first_address = wallet.derive_burn_address(0)?;
+        println!(
+            "{} {}",
+            "First burn address:".bright_white(),
+            first_address.address.to_string().bright_cyan()
+        );
+        println!(
+            "{} Compare it with the original wallet to make sure the passphrase is right",
+            "💡".bright_yellow()
+        );
+    } else {
+        println!(
+            "{} Pass --chain-id to show the first burn address and check the passphrase",
+            "💡".bright_yellow()
+        );
+    }
+    println!(
+        "{} Run 'darkmint wallet recover' to rediscover your coins",
+        "💡".bright_yellow()
+    );
+
+    Ok(())
+}
+
+/// Set the chain id shares are checked on, required for hierarchical wallets
+fn set_check_chain_id(
+    wallet: &mut Wallet,
+    chain_id: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match chain_id.or(NetworkProfile::selected()?.chain_id) {
+        Some(chain_id) => wallet.set_chain_id(chain_id),
+        None if wallet.derivation_scheme() == DerivationScheme::Hierarchical => {
+            return Err(
+                "Pass --chain-id to check the burn addresses of a hierarchical wallet".into(),
+            )
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Prompt for shares until the threshold of the first one is reached
+fn prompt_shares() -> Result<Vec<EntropyShare>, Box<dyn std::error::Error>> {
+    let mut shares: Vec<EntropyShare> = Vec::new();
+    loop {
+        let needed = shares.first().map(EntropyShare::threshold);
+        if needed.is_some_and(|needed| shares.len() >= needed as usize) {
+            return Ok(shares);
+        }
+
+        let prompt = match needed {
+            Some(needed) => format!(
+                "{} Share {} of {}",
+                "🧩".bright_green(),
+                shares.len() + 1,
+                needed
+            ),
+            None => format!("{} First share", "🧩".bright_green()),
+        };
+        let text: String = Input::new()
+            .with_prompt(prompt)
+            .validate_with(|input: &String| -> Result<(), String> {
+                EntropyShare::decode(input)
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            })
+            .interact_text()?;
+        let share = EntropyShare::decode(&text)?;
+        if shares.iter().any(|known| known.index() == share.index()) {
+            println!(
+                "{} Share {} was already entered",
+                "⚠️".bright_yellow(),
+                share.index()
+            );
+            continue;
+        }
+        shares.push(share);
+    }
+}
+
+fn backup_shares(
+    threshold: u8,
+    count: u8,
+    chain_id: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wallet = open_wallet()?;
+    set_check_chain_id(&mut wallet, chain_id)?;
+
+    println!(
+        "{} {}",
+        "⚠️".bright_yellow(),
+        format!(
+            "Any {} of these {} shares can mint your coins, fewer reveal nothing.",
+            threshold, count
+        )
+        .bright_yellow()
+        .bold()
+    );
+    println!(
+        "{} {}",
+        "⚠️".bright_yellow(),
+        "Hand each share to a different holder and never store them together.".bright_yellow()
+    );
+    println!();
+
+    let confirmed = Confirm::new()
+        .with_prompt("Show the shares now?")
+        .default(false)
+        .interact()?;
+    if !confirmed {
+        println!("{} Operation cancelled by user", "🚫".bright_red());
+        return Ok(());
+    }
+
+    let shares = wallet.split_entropy(threshold, count)?;
+
+    println!();
+    println!("{}", "🧩 Wallet Shares".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    for share in &shares {
+        println!(
+            "{:>4}. {}",
+            share.index(),
+            share.encode()?.as_str().bright_white().bold()
+        );
+    }
+    println!();
+    println!(
+        "{} Checked that {} shares restore the same first {} burn addresses",
+        "✅".bright_green(),
+        threshold,
+        SHARE_CHECK_ADDRESSES
+    );
+    println!(
+        "{} Backup id: {}",
+        "💡".bright_yellow(),
+        shares[0].backup_id().bright_cyan()
+    );
+
+    Ok(())
+}
+
+fn restore_shares(chain_id: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
+    let chain_id = chain_id.or(NetworkProfile::selected()?.chain_id);
+    let shares = prompt_shares()?;
+    let password = wallet_password()?;
+
+    let path = resolve_wallet_path(None)?;
+    let mut wallet =
+        Wallet::restore_from_shares(FileStore::open(&path)?, &shares, Some(&password))?;
+
+    println!(
+        "{} {} ({} derivation, backup id {})",
+        "✅".bright_green(),
+        "Wallet restored".bright_green().bold(),
+        wallet.derivation_scheme(),
+        shares[0].backup_id()
+    );
+    if let Some(chain_id) = chain_id {
+        wallet.set_chain_id(chain_id);
+    }
+    if wallet.derivation_scheme() == DerivationScheme::Legacy || chain_id.is_some() {
+        println!(
+            "{} {}",
+            "First burn address:".bright_white(),
+            wallet
+                .derive_burn_address(0)?
+                .address
+                .to_string()
+                .bright_cyan()
+        );
+    }
+    println!(
+        "{} Run 'darkmint wallet recover' to rediscover your coins",
+        "💡".bright_yellow()
+    );
+
+    Ok(())
+}
+
+fn verify_shares(chain_id: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wallet = open_wallet()?;
+    set_check_chain_id(&mut wallet, chain_id)?;
+
+    let shares = prompt_shares()?;
+    wallet.verify_shares(&shares)?;
+
+    println!(
+        "{} {}",
+        "✅".bright_green(),
+        format!(
+            "The shares restore this wallet (first {} burn addresses match)",
+            SHARE_CHECK_ADDRESSES
+        )
+        .bright_green()
+        .bold()
+    );
+    Ok(())
+}
+
+fn coins() -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = open_wallet()?;
+    if wallet.coin_count() == 0 {
+        println!("{} The wallet has no coins", "📭".bright_yellow());
+        return Ok(());
+    }
+
+    println!("{}", "🪙 Coins".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    for coin in wallet.get_coins() {
+        let index = coin
+            .burn_index
+            .map_or_else(|| "-".to_string(), |index| index.to_string());
+        let state = match coin.state {
+            CoinState::Minted => coin.state.to_string().bright_green(),
+            CoinState::Failed => coin.state.to_string().bright_red(),
+            _ => coin.state.to_string().bright_yellow(),
+        };
+        println!("{:>4}. {} {}", index, state, Amount::from_wei(coin.amount));
+        if let Some(address) = coin.burn_address {
+            println!("      burn address: {}", address);
+        }
+        if let Some(tx) = coin.burn_tx_hash {
+            println!("      burn tx:      {}", tx);
+        }
+        if let Some(block) = coin.proof_block {
+            println!("      proof block:  {}", block);
+        }
+        if let Some(path) = &coin.proof_path {
+            println!("      proof:        {}", path);
+        }
+        if let Some(job_id) = &coin.zkverify_job_id {
+            println!("      zkVerify job: {}", job_id);
+        }
+        if let Some(tx) = coin.mint_tx_hash {
+            println!("      mint tx:      {}", tx);
+        }
+        if let Some(error) = &coin.error {
+            println!("      error:        {}", error.bright_red());
+        }
+    }
+
+    Ok(())
+}
+
+async fn addresses(provider_url: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wallet = open_wallet()?;
+    if wallet.burn_addresses().is_empty() {
+        println!(
+            "{} No burn address was handed out yet",
+            "📭".bright_yellow()
+        );
+        return Ok(());
+    }
+    if wallet.derivation_scheme() == DerivationScheme::Hierarchical {
+        let provider_url = NetworkProfile::selected()?.rpc_url(provider_url).await?;
+        let provider = ProviderBuilder::new().connect(&provider_url).await?;
+        wallet.set_chain_id(provider.get_chain_id().await?);
+    }
+
+    println!("{}", "📇 Burn Addresses".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    for record in wallet.burn_addresses().to_vec() {
+        let status = match record.status {
+            BurnAddressStatus::Used => record.status.to_string().bright_green(),
+            BurnAddressStatus::Reserved => record.status.to_string().bright_yellow(),
+        };
+        let address = wallet.derive_burn_address(record.index)?.address;
+        println!(
+            "{:>4}. {} {:<8} {}",
+            record.index,
+            address.to_string().bright_red(),
+            status,
+            record.label.as_deref().unwrap_or("")
+        );
+    }
+    println!();
+    println!(
+        "{} Next burn address index: {}",
+        "💡".bright_yellow(),
+        wallet.next_burn_index()
+    );
+
+    Ok(())
+}
+
+fn set_label(index: u64, label: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wallet = open_wallet()?;
+    wallet.set_burn_address_label(index, label)?;
+
+    match label {
+        Some(label) => println!(
+            "{} Burn address #{} labelled '{}'",
+            "✅".bright_green(),
+            index,
+            label.bright_cyan()
+        ),
+        None => println!(
+            "{} Label of burn address #{} cleared",
+            "✅".bright_green(),
+            index
+        ),
+    }
+    Ok(())
+}
+
+async fn recover(
+    gap_limit: u64,
+    provider_url: Option<&str>,
+    encrypted: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wallet = open_wallet()?;
+    let network = NetworkProfile::selected()?;
+    let provider_url = network.rpc_url(provider_url).await?;
+    let provider = ProviderBuilder::new().connect(&provider_url).await?;
+    wallet.set_chain_id(provider.get_chain_id().await?);
+
+    println!(
+        "{} Scanning burn addresses (gap limit {})...",
+        "🔎".bright_cyan(),
+        gap_limit
+    );
+    let report = wallet
+        .recover_coins(&provider, network.contract_address, gap_limit, encrypted)
+        .await?;
+
+    println!();
+    println!("{}", "📋 Recovered Coins".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    for coin in &report.coins {
+        println!(
+            "{:>4}. {} {} {} {}",
+            coin.index,
+            coin.address.to_string().bright_red(),
+            Amount::from_wei(coin.amount),
+            if coin.minted {
+                "minted".bright_green()
+            } else {
+                "not minted".bright_yellow()
+            },
+            if coin.added { "(new)" } else { "" }
+        );
+    }
+    println!();
+    println!(
+        "{} Scanned {} burn addresses, found {} funded, added {} coins",
+        "✅".bright_green(),
+        report.scanned,
+        report.coins.len(),
+        report.coins.iter().filter(|coin| coin.added).count()
+    );
+
+    Ok(())
+}
+
+async fn export_watch_only(
+    count: u64,
+    out: &str,
+    provider_url: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut wallet = open_wallet()?;
+    if wallet.derivation_scheme() == DerivationScheme::Hierarchical {
+        let provider_url = NetworkProfile::selected()?.rpc_url(provider_url).await?;
+        let provider = ProviderBuilder::new().connect(&provider_url).await?;
+        wallet.set_chain_id(provider.get_chain_id().await?);
+    }
+
+    let watch_only = wallet.export_watch_only(count)?;
+    watch_only.save(Path::new(out))?;
+
+    println!(
+        "{} Exported {} burn addresses to '{}'",
+        "✅".bright_green(),
+        watch_only.addresses().len(),
+        out.bright_cyan()
+    );
+    println!(
+        "{} {}",
+        "Viewing public key:".bright_white(),
+        hex::encode(watch_only.viewing_key()?.public_key()).bright_cyan()
+    );
+    println!(
+        "{} The file can follow deposits and receive sealed notes but cannot mint",
+        "💡".bright_yellow()
+    );
+    println!(
+        "{} It still reveals which addresses are yours - keep it private",
+        "⚠️".bright_yellow()
+    );
+
+    Ok(())
+}
+
+async fn watch(file: &str, provider_url: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let watch_only = WatchOnlyWallet::load(Path::new(file))?;
+    let provider_url = NetworkProfile::selected()?.rpc_url(provider_url).await?;
+    let provider = ProviderBuilder::new().connect(&provider_url).await?;
+
+    println!(
+        "{} Checking {} burn addresses...",
+        "📡".bright_cyan(),
+        watch_only.addresses().len()
+    );
+    let deposits = watch_only.scan_deposits(&provider).await?;
+
+    println!();
+    println!("{}", "📋 Deposits".bright_green().bold());
+    println!("{}", "─".repeat(30).bright_black());
+    for deposit in &deposits {
+        println!(
+            "{:>4}. {} {}",
+            deposit.index,
+            deposit.address.to_string().bright_red(),
+            Amount::from_wei(deposit.balance)
+        );
+    }
+    if deposits.is_empty() {
+        println!("No deposits found");
+    }
+
+    if !watch_only.coins().is_empty() {
+        println!();
+        println!("{}", "📥 Received Coins".bright_green().bold());
+        println!("{}", "─".repeat(30).bright_black());
+        for (i, coin) in watch_only.coins().iter().enumerate() {
+            let address = coin
+                .burn_address
+                .map(|address| address.to_string())
+                .unwrap_or_else(|| "unknown burn address".to_string());
+            println!(
+                "{:>4}. {} {} {}",
+                i,
+                coin.state.to_string().bright_yellow(),
+                address.bright_red(),
+                Amount::from_wei(coin.amount)
+            );
+        }
+    }
+
+    Ok(())
+}
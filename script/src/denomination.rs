@@ -0,0 +1,289 @@
+//! Standard denominations and amount uniqueness
+//!
+//! A burn of an unusual amount followed by a mint of the same amount links the
+//! two. Burns can be split into standard denominations, each sent to its own
+//! fresh burn address, and amounts are checked against an index of the past
+//! `TokenMinted` events of the DarkMint contract to warn when few other mints
+//! share them.
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::Filter,
+    sol,
+    sol_types::SolEvent,
+};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::amount::Amount;
+
+sol! {
+    event TokenMinted(address indexed recipient, uint256 amount, uint256 nullifier);
+}
+
+/// Exponents of the standard denominations in wei, 100 ETH down to 0.001 ETH
+const DENOMINATION_EXPONENTS: [u8; 6] = [20, 19, 18, 17, 16, 15];
+
+/// Most burns an amount may be split into
+pub const MAX_SPLIT_BURNS: usize = 20;
+
+/// Past mints of the same amount below which the amount is reported as unique
+pub const MIN_ANONYMITY_SET: u64 = 10;
+
+/// Blocks queried per `eth_getLogs` request while indexing mints
+const LOG_CHUNK_BLOCKS: u64 = 10_000;
+
+/// Recent blocks indexed when the deployment block of the contract is unknown
+pub const RECENT_MINT_BLOCKS: u64 = 200_000;
+
+/// Get the standard denominations in wei, largest first
+pub fn standard_denominations() -> Vec<U256> {
+    DENOMINATION_EXPONENTS
+        .iter()
+        .map(|&exp| U256::from(10).pow(U256::from(exp)))
+        .collect()
+}
+
+/// Get the part of an amount below the smallest denomination
+///
+/// # Arguments
+/// * `amount` - Amount to split in wei
+///
+/// # Returns
+/// * The remainder in wei, zero for multiples of the smallest denomination
+pub fn split_remainder(amount: U256) -> U256 {
+    let denominations = standard_denominations();
+    amount % denominations[denominations.len() - 1]
+}
+
+/// Split an amount into standard denominations, largest first
+///
+/// The remainder below the smallest denomination, if any, is burned on its own
+/// as the last part. Its amount is unusual, see `split_remainder`.
+///
+/// # Arguments
+/// * `amount` - Amount to split in wei
+///
+/// # Returns
+/// * Result containing the amount of each burn, or an error when the amount
+///   is zero or needs too many burns
+pub fn split_amount(amount: U256) -> Result<Vec<U256>, String> {
+    if amount.is_zero() {
+        return Err("A zero amount cannot be split".to_string());
+    }
+
+    let mut parts = Vec::new();
+    let mut rest = amount;
+    for denomination in standard_denominations() {
+        while rest >= denomination {
+            parts.push(denomination);
+            rest -= denomination;
+        }
+    }
+    if !rest.is_zero() {
+        parts.push(rest);
+    }
+    if parts.len() > MAX_SPLIT_BURNS {
+        return Err(format!(
+            "{} splits into {} burns, at most {} are allowed",
+            Amount::from_wei(amount),
+            parts.len(),
+            MAX_SPLIT_BURNS
+        ));
+    }
+    Ok(parts)
+}
+
+/// Number of past mints of each amount
+#[derive(Debug, Clone, Default)]
+pub struct MintIndex {
+    /// Mints per amount in wei
+    counts: HashMap<U256, u64>,
+    /// Number of mints indexed
+    pub total: u64,
+}
+
+impl MintIndex {
+    /// Index the `TokenMinted` events of the DarkMint contract
+    ///
+    /// # Arguments
+    /// * `provider` - Provider of the DarkMint chain
+    /// * `contract` - DarkMint contract address
+    /// * `from_block` - First block to scan, the deployment block of the contract
+    ///
+    /// # Returns
+    /// * Result containing the index or an error
+    pub async fn fetch<P: Provider>(
+        provider: &P,
+        contract: Address,
+        from_block: u64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let latest = provider.get_block_number().await?;
+        let mut index = MintIndex::default();
+        let mut start = from_block;
+        while start <= latest {
+            let end = latest.min(start + LOG_CHUNK_BLOCKS - 1);
+            let filter = Filter::new()
+                .address(contract)
+                .event_signature(TokenMinted::SIGNATURE_HASH)
+                .from_block(start)
+                .to_block(end);
+            for log in provider.get_logs(&filter).await? {
+                index.record(log.log_decode::<TokenMinted>()?.inner.data.amount);
+            }
+            start = end + 1;
+        }
+        Ok(index)
+    }
+
+    /// Record a mint of an amount
+    pub fn record(&mut self, amount: U256) {
+        *self.counts.entry(amount).or_default() += 1;
+        self.total += 1;
+    }
+
+    /// Get the number of past mints of an amount
+    pub fn count(&self, amount: U256) -> u64 {
+        self.counts.get(&amount).copied().unwrap_or_default()
+    }
+
+    /// Get the amounts shared by fewer than `MIN_ANONYMITY_SET` past mints
+    ///
+    /// # Arguments
+    /// * `amounts` - Amounts about to be burned and minted
+    ///
+    /// # Returns
+    /// * The distinct unique amounts with their number of past mints
+    pub fn unique_amounts(&self, amounts: &[U256]) -> Vec<(U256, u64)> {
+        amounts
+            .iter()
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|amount| (amount, self.count(amount)))
+            .filter(|&(_, count)| count < MIN_ANONYMITY_SET)
+            .collect()
+    }
+}
+
+/// Warn about burn amounts that few past mints share
+///
+/// # Arguments
+/// * `provider_url` - RPC provider of the DarkMint chain
+/// * `contract` - DarkMint contract address
+/// * `deployment_block` - Block the contract was deployed in, when known
+/// * `amounts` - Amounts about to be burned
+///
+/// # Returns
+/// * Result containing whether any amount is unique, or an error
+pub async fn warn_unique_amounts(
+    provider_url: &str,
+    contract: Address,
+    deployment_block: Option<u64>,
+    amounts: &[U256],
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+    let from_block = match deployment_block {
+        Some(block) => {
+            println!("Indexing past mints from block {}...", block);
+            block
+        }
+        // Without a deployment block only recent mints are counted
+        None => {
+            let block = provider
+                .get_block_number()
+                .await?
+                .saturating_sub(RECENT_MINT_BLOCKS);
+            println!(
+                "Indexing past mints of the last {} blocks, from block {}...",
+                RECENT_MINT_BLOCKS, block
+            );
+            block
+        }
+    };
+    let index = MintIndex::fetch(&provider, contract, from_block).await?;
+    println!("Indexed {} past mints", index.total);
+
+    let unique = index.unique_amounts(amounts);
+    for (amount, count) in &unique {
+        println!(
+            "⚠️ Only {} past mints of {}: minting it can link it to this burn",
+            count,
+            Amount::from_wei(*amount)
+        );
+    }
+    if !unique.is_empty() {
+        println!("  Split the burn into standard denominations with --split to blend in");
+    }
+    Ok(!unique.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eth(amount: &str) -> U256 {
+        amount.parse::<Amount>().unwrap().wei()
+    }
+
+    #[test]
+    fn test_split_amount_into_denominations() {
+        assert_eq!(
+            split_amount(eth("12.301")).unwrap(),
+            vec![
+                eth("10"),
+                eth("1"),
+                eth("1"),
+                eth("0.1"),
+                eth("0.1"),
+                eth("0.1"),
+                eth("0.001"),
+            ]
+        );
+        assert_eq!(split_amount(eth("0.1")).unwrap(), vec![eth("0.1")]);
+        assert_eq!(split_amount(eth("300")).unwrap(), vec![eth("100"); 3]);
+    }
+
+    #[test]
+    fn test_split_amount_burns_remainder_last() {
+        assert_eq!(
+            split_amount(eth("0.123456")).unwrap(),
+            vec![
+                eth("0.1"),
+                eth("0.01"),
+                eth("0.01"),
+                eth("0.001"),
+                eth("0.001"),
+                eth("0.001"),
+                eth("0.000456"),
+            ]
+        );
+        assert_eq!(split_remainder(eth("0.123456")), eth("0.000456"));
+        assert_eq!(split_remainder(eth("12.301")), U256::ZERO);
+        assert_eq!(split_amount(eth("0.0005")).unwrap(), vec![eth("0.0005")]);
+    }
+
+    #[test]
+    fn test_split_amount_rejects_zero_and_long_splits() {
+        assert!(split_amount(U256::ZERO).is_err());
+        // 9.999 ETH needs 9 + 9 + 9 + 9 burns
+        assert!(split_amount(eth("9.999")).is_err());
+    }
+
+    #[test]
+    fn test_unique_amounts_of_mint_index() {
+        let mut index = MintIndex::default();
+        for _ in 0..MIN_ANONYMITY_SET {
+            index.record(eth("0.1"));
+        }
+        index.record(eth("0.123456"));
+
+        assert_eq!(index.total, MIN_ANONYMITY_SET + 1);
+        assert!(index.unique_amounts(&[eth("0.1"), eth("0.1")]).is_empty());
+        assert_eq!(
+            index.unique_amounts(&[eth("0.123456"), eth("0.1"), eth("0.123456")]),
+            vec![(eth("0.123456"), 1)]
+        );
+        assert_eq!(index.unique_amounts(&[eth("1")]), vec![(eth("1"), 0)]);
+    }
+}
@@ -0,0 +1,253 @@
+//! Burn key derivation
+//!
+//! Two schemes derive burn address preimages from wallet entropy:
+//!
+//! - `Legacy`: `SHA256(entropy || index_le)`, the original flat scheme. Kept so
+//!   that existing wallets keep deriving the same burn addresses.
+//! - `Hierarchical`: a hardened tree `m / purpose / chain / account / index`.
+//!   Every step is `HMAC-SHA256(parent, label || value_be)` with a distinct
+//!   label per level, so keys for different purposes, chains or accounts can
+//!   never coincide and a child key reveals nothing about its siblings.
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Key of the HMAC computing the master node from entropy
+const MASTER_KEY: &[u8] = b"darkmint/hd/v1";
+
+/// Purpose of burn address preimages
+pub const PURPOSE_BURN: u32 = 1;
+
+/// Purpose of coin salts
+pub const PURPOSE_COIN_SALT: u32 = 2;
+
+/// Purpose of viewing keys
+pub const PURPOSE_VIEWING: u32 = 3;
+
+/// Account used by the CLI
+pub const DEFAULT_ACCOUNT: u32 = 0;
+
+/// Scheme used by a wallet to derive its burn keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DerivationScheme {
+    /// Flat `SHA256(entropy || index)` derivation of wallets created before
+    /// hierarchical derivation
+    #[default]
+    Legacy,
+    /// Hardened purpose/chain/account/index tree
+    Hierarchical,
+}
+
+impl fmt::Display for DerivationScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DerivationScheme::Legacy => write!(f, "legacy"),
+            DerivationScheme::Hierarchical => write!(f, "hierarchical"),
+        }
+    }
+}
+
+/// Position of a key in the hierarchical derivation tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DerivationPath {
+    /// What the key is used for (`PURPOSE_BURN`, `PURPOSE_COIN_SALT`, `PURPOSE_VIEWING`)
+    pub purpose: u32,
+    /// EIP-155 chain id the key is used on
+    pub chain_id: u64,
+    /// Account number
+    pub account: u32,
+    /// Key index within the account
+    pub index: u64,
+}
+
+impl DerivationPath {
+    /// Path of a burn address preimage
+    ///
+    /// # Arguments
+    /// * `chain_id` - Chain the burn address is used on
+    /// * `account` - Account number
+    /// * `index` - Burn address index
+    ///
+    /// # Returns
+    /// * Derivation path under `PURPOSE_BURN`
+    pub fn burn(chain_id: u64, account: u32, index: u64) -> Self {
+        Self {
+            purpose: PURPOSE_BURN,
+            chain_id,
+            account,
+            index,
+        }
+    }
+
+    /// Path of the salt of the coin minted from a burn address
+    ///
+    /// # Arguments
+    /// * `chain_id` - Chain the coin is minted on
+    /// * `account` - Account number
+    /// * `index` - Burn address index
+    ///
+    /// # Returns
+    /// * Derivation path under `PURPOSE_COIN_SALT`
+    pub fn coin_salt(chain_id: u64, account: u32, index: u64) -> Self {
+        Self {
+            purpose: PURPOSE_COIN_SALT,
+            chain_id,
+            account,
+            index,
+        }
+    }
+
+    /// Path of the viewing key of an account
+    ///
+    /// Viewing keys are not bound to a chain, the chain level is always 0.
+    ///
+    /// # Arguments
+    /// * `account` - Account number
+    ///
+    /// # Returns
+    /// * Derivation path under `PURPOSE_VIEWING`
+    pub fn viewing(account: u32) -> Self {
+        Self {
+            purpose: PURPOSE_VIEWING,
+            chain_id: 0,
+            account,
+            index: 0,
+        }
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "m/{}'/{}'/{}'/{}'",
+            self.purpose, self.chain_id, self.account, self.index
+        )
+    }
+}
+
+/// One HMAC-SHA256 step of the derivation tree
+fn hmac_step(key: &[u8], label: &[u8], value: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(label);
+    mac.update(value);
+    Zeroizing::new(mac.finalize().into_bytes().into())
+}
+
+/// Derive the node at `path` from wallet entropy
+///
+/// # Arguments
+/// * `entropy` - Wallet entropy
+/// * `path` - Position in the derivation tree
+///
+/// # Returns
+/// * 32-byte node key
+pub fn derive_node(entropy: &[u8], path: &DerivationPath) -> Zeroizing<[u8; 32]> {
+    let master = hmac_step(MASTER_KEY, b"", entropy);
+    let purpose = hmac_step(master.as_ref(), b"purpose", &path.purpose.to_be_bytes());
+    let chain = hmac_step(purpose.as_ref(), b"chain", &path.chain_id.to_be_bytes());
+    let account = hmac_step(chain.as_ref(), b"account", &path.account.to_be_bytes());
+    hmac_step(account.as_ref(), b"index", &path.index.to_be_bytes())
+}
+
+/// Reduce a 32-byte key to a field element preimage
+///
+/// Only the first 31 bytes are used to stay within the field size.
+fn key_to_preimage(key: &[u8]) -> Fr {
+    Fr::from_le_bytes_mod_order(&key[..31])
+}
+
+/// Compute a burn address preimage with the legacy flat scheme
+///
+/// # Arguments
+/// * `entropy` - Wallet entropy
+/// * `index` - Burn address index
+///
+/// # Returns
+/// * Field element preimage
+pub fn legacy_preimage(entropy: &[u8], index: u64) -> Fr {
+    let mut hasher = Sha256::new();
+    hasher.update(entropy);
+    hasher.update(index.to_le_bytes());
+    key_to_preimage(&hasher.finalize())
+}
+
+/// Compute a burn address preimage with the hierarchical scheme
+///
+/// # Arguments
+/// * `entropy` - Wallet entropy
+/// * `path` - Derivation path (normally built with `DerivationPath::burn`)
+///
+/// # Returns
+/// * Field element preimage
+pub fn hierarchical_preimage(entropy: &[u8], path: &DerivationPath) -> Fr {
+    key_to_preimage(derive_node(entropy, path).as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTROPY: [u8; 32] = [0x11; 32];
+
+    #[test]
+    fn test_derive_node_vectors() {
+        assert_eq!(
+            hex::encode(derive_node(&ENTROPY, &DerivationPath::burn(1, 0, 0)).as_ref()),
+            "dc9d8cf566d5a983fcdd068970a1fb635b37512e065df4df6abd840e93473ee8"
+        );
+        assert_eq!(
+            hex::encode(derive_node(&ENTROPY, &DerivationPath::burn(113, 0, 7)).as_ref()),
+            "f5a06c8da2f339bad5f477e30e43931a504c8514211ad675c6bef74ec7e50c49"
+        );
+        assert_eq!(
+            hex::encode(derive_node(&ENTROPY, &DerivationPath::coin_salt(1, 0, 0)).as_ref()),
+            "b6fc769de2420a5ca266ccc66c6e7b89c01a88d09a283cb6717810f966047edd"
+        );
+    }
+
+    #[test]
+    fn test_legacy_preimage_matches_flat_scheme() {
+        let digest = Sha256::digest([&ENTROPY[..], &3u64.to_le_bytes()].concat());
+        assert_eq!(
+            legacy_preimage(&ENTROPY, 3),
+            Fr::from_le_bytes_mod_order(&digest[..31])
+        );
+    }
+
+    #[test]
+    fn test_paths_do_not_overlap() {
+        let base = DerivationPath::burn(1, 0, 0);
+        let variants = [
+            DerivationPath::burn(2, 0, 0),
+            DerivationPath::burn(1, 1, 0),
+            DerivationPath::burn(1, 0, 1),
+            DerivationPath::coin_salt(1, 0, 0),
+        ];
+
+        let base_preimage = hierarchical_preimage(&ENTROPY, &base);
+        for path in variants {
+            assert_ne!(
+                hierarchical_preimage(&ENTROPY, &path),
+                base_preimage,
+                "{}",
+                path
+            );
+        }
+        assert_ne!(base_preimage, legacy_preimage(&ENTROPY, 0));
+    }
+
+    #[test]
+    fn test_path_display() {
+        assert_eq!(
+            DerivationPath::burn(113, 0, 5).to_string(),
+            "m/1'/113'/0'/5'"
+        );
+    }
+}
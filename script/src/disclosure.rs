@@ -0,0 +1,381 @@
+//! Selective disclosure of encrypted coins
+//!
+//! An encrypted coin only exposes the Poseidon commitment of its amount and salt.
+//! A disclosure reveals the opening of that commitment (amount and salt) together
+//! with the burn address and block it was proven at, so that an auditor can check
+//! the coin without ever getting access to the wallet entropy.
+//!
+//! The disclosure is bound to the coin's mint through its nullifier and mint
+//! transaction: the auditor checks on chain that this transaction minted the
+//! disclosed amount under that nullifier, from a proof of the disclosed burn
+//! address that committed the disclosed commitment.
+
+use alloy::{
+    consensus::Transaction,
+    primitives::{Address, B256, U256},
+    providers::{Provider, ProviderBuilder},
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::mint::{decode_mint_call, DecodedMint};
+use crate::recovery::IDarkMint;
+use crate::Coin;
+
+/// Current version of the disclosure file format
+pub const DISCLOSURE_VERSION: u32 = 2;
+
+/// Opening of an encrypted coin handed to a third party
+///
+/// The disclosure contains everything needed to recompute the coin commitment,
+/// to find the coin's mint on chain and to cross-check the amount against the
+/// burn address balance. It does not contain the burn preimage, so it cannot
+/// be used to mint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Disclosure {
+    /// Disclosure format version
+    pub version: u32,
+    /// Burn address the coin was minted from
+    pub burn_address: Address,
+    /// Block number the coin was proven at
+    pub block_number: u64,
+    /// Disclosed coin amount in wei
+    pub amount: U256,
+    /// Salt used in the coin commitment
+    pub salt: U256,
+    /// Commitment the opening refers to
+    pub commitment: U256,
+    /// Nullifier the coin was minted under
+    pub nullifier: U256,
+    /// Hash of the mint transaction
+    pub mint_tx_hash: B256,
+}
+
+impl Disclosure {
+    /// Create a disclosure for a minted encrypted coin
+    ///
+    /// Burn address, proof block, nullifier and mint transaction are taken
+    /// from the coin's lifecycle record.
+    ///
+    /// # Arguments
+    /// * `coin` - Encrypted coin to disclose
+    ///
+    /// # Returns
+    /// * Result containing the disclosure, or an error if the coin is not
+    ///   encrypted or not minted
+    pub fn new(coin: &Coin) -> Result<Self, Box<dyn std::error::Error>> {
+        if !coin.is_encrypted() {
+            return Err("Only encrypted coins have a commitment to disclose".into());
+        }
+        if !coin.is_minted() {
+            return Err(format!(
+                "Only minted coins can be disclosed, the coin is {}",
+                coin.state
+            )
+            .into());
+        }
+        let missing = |field: &str| format!("The coin has no recorded {}", field);
+
+        Ok(Self {
+            version: DISCLOSURE_VERSION,
+            burn_address: coin.burn_address.ok_or_else(|| missing("burn address"))?,
+            block_number: coin.proof_block.ok_or_else(|| missing("proof block"))?,
+            amount: coin.amount,
+            salt: coin.salt,
+            commitment: coin.get_effective_value(),
+            nullifier: coin.nullifier.ok_or_else(|| missing("nullifier"))?,
+            mint_tx_hash: coin
+                .mint_tx_hash
+                .ok_or_else(|| missing("mint transaction"))?,
+        })
+    }
+
+    /// Verify that the disclosed amount and salt open the commitment
+    ///
+    /// # Returns
+    /// * Result indicating success or a description of the mismatch
+    pub fn verify_opening(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.version != DISCLOSURE_VERSION {
+            return Err(format!("Unsupported disclosure version {}", self.version).into());
+        }
+
+        let recomputed = Coin::new(self.amount, self.salt, true).get_effective_value();
+        if recomputed != self.commitment {
+            return Err(format!(
+                "Commitment mismatch: disclosure claims {:#x}, opening gives {:#x}",
+                self.commitment, recomputed
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Verify that a mint call minted the disclosed coin
+    ///
+    /// # Arguments
+    /// * `mint` - Mint call of the disclosed mint transaction
+    ///
+    /// # Returns
+    /// * Result indicating success or a description of the mismatch
+    pub fn verify_mint(&self, mint: &DecodedMint) -> Result<(), Box<dyn std::error::Error>> {
+        if mint.nullifier != self.nullifier || mint.proven_nullifier != self.nullifier {
+            return Err(format!(
+                "Nullifier mismatch: disclosure claims {}, the mint used {} from a proof of {}",
+                self.nullifier, mint.nullifier, mint.proven_nullifier
+            )
+            .into());
+        }
+        if mint.burn_address != self.burn_address {
+            return Err(format!(
+                "Burn address mismatch: disclosure claims {}, the mint proved {}",
+                self.burn_address, mint.burn_address
+            )
+            .into());
+        }
+        if mint.amount != self.amount {
+            return Err(format!(
+                "Amount mismatch: disclosed {} wei, the mint minted {} wei",
+                self.amount, mint.amount
+            )
+            .into());
+        }
+        if !mint.encrypted {
+            return Err("The mint proved a plaintext balance, not an encrypted coin".into());
+        }
+        // The proof commits the low 32 bits of the commitment
+        let committed = (self.commitment & U256::from(u32::MAX)).to::<u32>();
+        if mint.encrypted_balance != committed {
+            return Err(format!(
+                "Commitment mismatch: disclosure claims {:#x}, the proof committed {:#x}",
+                committed, mint.encrypted_balance
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Verify the disclosure against the chain
+    ///
+    /// Checks that the disclosed transaction is a successful mint on the
+    /// DarkMint contract that minted the disclosed amount under the disclosed
+    /// nullifier from a proof of the burn address, that the contract recorded
+    /// the nullifier, and that the burn address held the amount at the proven
+    /// block.
+    ///
+    /// # Arguments
+    /// * `provider_url` - RPC provider URL (must serve historical state for the block)
+    /// * `contract_address` - Address of the DarkMint contract
+    ///
+    /// # Returns
+    /// * Result indicating success or a description of the mismatch
+    pub async fn verify_on_chain(
+        &self,
+        provider_url: &str,
+        contract_address: Address,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let provider = ProviderBuilder::new().connect(provider_url).await?;
+
+        let receipt = provider
+            .get_transaction_receipt(self.mint_tx_hash)
+            .await?
+            .ok_or_else(|| format!("Mint transaction {} not found", self.mint_tx_hash))?;
+        if !receipt.status() {
+            return Err(format!("Mint transaction {} reverted", self.mint_tx_hash).into());
+        }
+        if receipt.to != Some(contract_address) {
+            return Err(format!(
+                "Transaction {} was not sent to the DarkMint contract {}",
+                self.mint_tx_hash, contract_address
+            )
+            .into());
+        }
+        let tx = provider
+            .get_transaction_by_hash(self.mint_tx_hash)
+            .await?
+            .ok_or_else(|| format!("Mint transaction {} not found", self.mint_tx_hash))?;
+        self.verify_mint(&decode_mint_call(tx.input())?)?;
+
+        let contract = IDarkMint::new(contract_address, &provider);
+        if !contract.nullifiers(self.nullifier).call().await? {
+            return Err(
+                format!("The contract has no record of nullifier {}", self.nullifier).into(),
+            );
+        }
+
+        let balance = provider
+            .get_balance(self.burn_address)
+            .block_id(self.block_number.into())
+            .await?;
+
+        if balance != self.amount {
+            return Err(format!(
+                "Balance mismatch at block {}: disclosed {} wei, chain has {} wei",
+                self.block_number, self.amount, balance
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Save the disclosure as pretty JSON
+    ///
+    /// # Arguments
+    /// * `path` - Output file path
+    ///
+    /// # Returns
+    /// * Result indicating success or error
+    pub fn save_to_file(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Load a disclosure from a JSON file
+    ///
+    /// # Arguments
+    /// * `path` - Disclosure file path
+    ///
+    /// # Returns
+    /// * Result containing the disclosure or an error
+    pub fn load_from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mint::mint_calldata;
+    use crate::{BurnAddress, CoinState};
+    use alloy::sol_types::SolType;
+    use ark_bn254::Fr;
+    use ark_ff::{BigInteger, PrimeField};
+    use fibonacci_lib::PublicValuesStruct;
+
+    fn burn_address() -> BurnAddress {
+        BurnAddress::from_preimage(Fr::from(1234u64))
+    }
+
+    fn encrypted_coin() -> Coin {
+        let mut coin = Coin::new(U256::from(30_000_000_000_000u64), U256::from(42u64), true);
+        coin.state = CoinState::Minted;
+        coin.burn_address = Some(burn_address().address);
+        coin.proof_block = Some(100);
+        coin.nullifier = Some(burn_address().nullifier());
+        coin.mint_tx_hash = Some(B256::repeat_byte(9));
+        coin
+    }
+
+    fn mint_of(coin: &Coin, preimage: Fr, nullifier: U256) -> DecodedMint {
+        let committed = (coin.get_effective_value() & U256::from(u32::MAX)).to::<u32>();
+        mint_committing(coin, preimage, nullifier, committed)
+    }
+
+    fn mint_committing(
+        coin: &Coin,
+        preimage: Fr,
+        nullifier: U256,
+        encrypted_balance: u32,
+    ) -> DecodedMint {
+        let public_values = PublicValuesStruct::abi_encode(&PublicValuesStruct {
+            burn_preimage: preimage.into_bigint().to_bytes_be().into(),
+            commit_upper: 1,
+            encrypted_balance,
+            nullifier: nullifier.to::<u32>(),
+            encrypted: true,
+        });
+        let (input, _) =
+            mint_calldata(Address::repeat_byte(3), coin.amount, &public_values).unwrap();
+        decode_mint_call(&input).unwrap()
+    }
+
+    #[test]
+    fn test_disclosure_opens_commitment() {
+        let coin = encrypted_coin();
+        let disclosure = Disclosure::new(&coin).unwrap();
+
+        assert_eq!(disclosure.commitment, coin.get_effective_value());
+        assert!(disclosure.verify_opening().is_ok());
+    }
+
+    #[test]
+    fn test_disclosure_rejects_wrong_amount() {
+        let coin = encrypted_coin();
+        let mut disclosure = Disclosure::new(&coin).unwrap();
+        disclosure.amount += U256::from(1u64);
+
+        assert!(disclosure.verify_opening().is_err());
+    }
+
+    #[test]
+    fn test_disclosure_requires_minted_encrypted_coin() {
+        let mut coin = encrypted_coin();
+        coin.encrypted = false;
+        assert!(Disclosure::new(&coin).is_err());
+
+        let mut coin = encrypted_coin();
+        coin.state = CoinState::Aggregated;
+        assert!(Disclosure::new(&coin).is_err());
+    }
+
+    #[test]
+    fn test_disclosure_is_bound_to_its_mint() {
+        let coin = encrypted_coin();
+        let disclosure = Disclosure::new(&coin).unwrap();
+        let nullifier = burn_address().nullifier();
+
+        assert!(disclosure
+            .verify_mint(&mint_of(&coin, Fr::from(1234u64), nullifier))
+            .is_ok());
+
+        // A mint of another burn address, or under another nullifier, is not this coin's
+        assert!(disclosure
+            .verify_mint(&mint_of(&coin, Fr::from(99u64), nullifier))
+            .is_err());
+        assert!(disclosure
+            .verify_mint(&mint_of(
+                &coin,
+                Fr::from(1234u64),
+                nullifier + U256::from(1)
+            ))
+            .is_err());
+
+        let mut other_amount = coin.clone();
+        other_amount.amount += U256::from(1u64);
+        assert!(disclosure
+            .verify_mint(&mint_of(&other_amount, Fr::from(1234u64), nullifier))
+            .is_err());
+    }
+
+    #[test]
+    fn test_disclosure_rejects_commitment_the_proof_did_not_commit() {
+        let coin = encrypted_coin();
+        let disclosure = Disclosure::new(&coin).unwrap();
+        let nullifier = burn_address().nullifier();
+        let committed = (coin.get_effective_value() & U256::from(u32::MAX)).to::<u32>();
+
+        let other = mint_committing(&coin, Fr::from(1234u64), nullifier, committed ^ 1);
+        assert!(disclosure.verify_mint(&other).is_err());
+
+        // Another salt opens another commitment than the one the proof committed
+        let mut resalted = disclosure.clone();
+        resalted.salt += U256::from(1u64);
+        resalted.commitment = Coin::new(resalted.amount, resalted.salt, true).get_effective_value();
+        assert!(resalted.verify_opening().is_ok());
+        assert!(resalted
+            .verify_mint(&mint_of(&coin, Fr::from(1234u64), nullifier))
+            .is_err());
+    }
+
+    #[test]
+    fn test_disclosure_json_roundtrip() {
+        let disclosure = Disclosure::new(&encrypted_coin()).unwrap();
+        let json = serde_json::to_string(&disclosure).unwrap();
+        let decoded: Disclosure = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, disclosure);
+    }
+}
@@ -3,27 +3,48 @@
 //! This library provides wallet management, coin handling, and cryptographic utilities
 //! for the DarkMint privacy system. It includes functionality for:
 //!
-//! - Wallet creation and management
-//! - Burn address derivation
+//! - Wallet creation and management, with named wallets in the data directory
+//! - Password-encrypted wallet storage
+//! - Mnemonic backup and restore of wallet entropy
+//! - Legacy and hierarchical burn address derivation
 //! - Coin creation and encryption
+//! - Recovery of coins from the chain after a restore
+//! - Selective disclosure of encrypted coins
+//! - Viewing keys, sealed notes and watch-only wallets
 //! - Poseidon hash computations
 
-use alloy::primitives::{Address, U256};
+use alloy::primitives::{Address, B256, U256};
 use ark_bn254::Fr;
 use ark_ff::{BigInteger, PrimeField};
 use light_poseidon::{Poseidon, PoseidonBytesHasher};
 use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::fs;
-use std::io::Write;
+use std::fmt;
+use std::path::Path;
+use zeroize::Zeroize;
+
+use burn_index::{AddressIndex, BurnAddressRecord};
+use derivation::{DerivationPath, DerivationScheme, DEFAULT_ACCOUNT};
+use pending_burn::PendingBurn;
+use store::{FileStore, MemoryStore, WalletStore};
+use wallet_file::WalletKey;
 
 /// Size of cryptographic notes and entropy in bytes
 pub const NOTE_SIZE: usize = 32;
 
-/// Wallet file name for persistent storage
+/// File name of legacy wallets stored in the current directory
+///
+/// New wallets are stored in the data directory, see `wallet_location`.
 pub const WALLET_FILENAME: &str = "burnth.priv";
 
+/// Address of the DarkMint contract on the Horizen testnet, see `network`
+pub const DARKMINT_CONTRACT_ADDRESS: Address =
+    alloy::primitives::address!("0x157E135Fe3B6d853fb263f9E07DAda1C31361076");
+
+/// Domain separation prefix for deterministic coin salts
+const COIN_SALT_DOMAIN: &[u8] = b"darkmint/coin-salt/v1";
+
 /// Compute Poseidon hash of two field elements
 ///
 /// This is the primary hash function used in DarkMint for privacy-preserving
@@ -45,10 +66,89 @@ pub fn compute_poseidon_hash(left: Fr, right: Fr) -> Fr {
     Fr::from_le_bytes_mod_order(&hash_bytes)
 }
 
+/// Lifecycle state of a coin, from the burn transaction to the mint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoinState {
+    /// ETH was sent to the burn address
+    #[default]
+    Burned,
+    /// A mint proof is being generated
+    Proving,
+    /// The proof was generated and saved
+    Proven,
+    /// The proof was submitted to zkVerify
+    Submitted,
+    /// The proof was included in a zkVerify aggregation
+    Aggregated,
+    /// The mint transaction succeeded
+    Minted,
+    /// A step failed, see the coin's `error`
+    Failed,
+}
+
+impl CoinState {
+    /// Check whether a coin may move from this state to `next`
+    ///
+    /// States only move forward. Any unfinished coin can fail or restart
+    /// proving; minted coins are final.
+    ///
+    /// # Arguments
+    /// * `next` - Target state
+    ///
+    /// # Returns
+    /// * true if the transition is allowed
+    pub fn can_transition_to(self, next: CoinState) -> bool {
+        use CoinState::*;
+        match (self, next) {
+            (Minted, _) => false,
+            (_, Failed) | (_, Proving) => true,
+            (Proving, Proven) | (Proven, Submitted) | (Submitted, Aggregated) => true,
+            (Aggregated, Minted) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for CoinState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CoinState::Burned => "burned",
+            CoinState::Proving => "proving",
+            CoinState::Proven => "proven",
+            CoinState::Submitted => "submitted",
+            CoinState::Aggregated => "aggregated",
+            CoinState::Minted => "minted",
+            CoinState::Failed => "failed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for CoinState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "burned" => Ok(CoinState::Burned),
+            "proving" => Ok(CoinState::Proving),
+            "proven" => Ok(CoinState::Proven),
+            "submitted" => Ok(CoinState::Submitted),
+            "aggregated" => Ok(CoinState::Aggregated),
+            "minted" => Ok(CoinState::Minted),
+            "failed" => Ok(CoinState::Failed),
+            _ => Err(format!("Unknown coin state '{}'", s)),
+        }
+    }
+}
+
 /// Represents a privacy-preserving coin in the DarkMint system
 ///
 /// Coins can be either encrypted (for privacy) or plaintext (for transparency).
 /// The value computation differs based on the encryption flag.
+///
+/// Besides its opening (amount and salt) a coin records where it is in its
+/// lifecycle and the on-chain artifacts of every step.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Coin {
     /// The coin amount in wei
@@ -57,6 +157,43 @@ pub struct Coin {
     pub salt: U256,
     /// Whether the coin value should be encrypted
     pub encrypted: bool,
+    /// Index of the burn address the coin was minted from, if known
+    #[serde(default)]
+    pub burn_index: Option<u64>,
+    /// Lifecycle state
+    #[serde(default)]
+    pub state: CoinState,
+    /// Burn address the ETH was sent to
+    #[serde(default)]
+    pub burn_address: Option<Address>,
+    /// Hash of the burn transaction
+    #[serde(default)]
+    pub burn_tx_hash: Option<B256>,
+    /// Block whose state root the mint proof was generated against
+    #[serde(default)]
+    pub proof_block: Option<u64>,
+    /// Nullifier recorded by the DarkMint contract on mint
+    #[serde(default)]
+    pub nullifier: Option<U256>,
+    /// Path of the saved proof artifact
+    #[serde(default)]
+    pub proof_path: Option<String>,
+    /// zkVerify job id of the submitted proof
+    #[serde(default)]
+    pub zkverify_job_id: Option<String>,
+    /// Hash of the mint transaction
+    #[serde(default)]
+    pub mint_tx_hash: Option<B256>,
+    /// Reason of the last failure, set in the `Failed` state
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Burn address preimage received in a note, for coins burned by another
+    /// wallet (coins of this wallet derive it from `burn_index`)
+    #[serde(default)]
+    pub burn_preimage: Option<U256>,
+    /// Chain the coin was burned on, for coins received in a note
+    #[serde(default)]
+    pub chain_id: Option<u64>,
 }
 
 impl Coin {
@@ -74,7 +211,58 @@ impl Coin {
             amount,
             salt,
             encrypted,
+            burn_index: None,
+            state: CoinState::default(),
+            burn_address: None,
+            burn_tx_hash: None,
+            proof_block: None,
+            nullifier: None,
+            proof_path: None,
+            zkverify_job_id: None,
+            mint_tx_hash: None,
+            error: None,
+            burn_preimage: None,
+            chain_id: None,
+        }
+    }
+
+    /// Move the coin to a new lifecycle state
+    ///
+    /// # Arguments
+    /// * `next` - Target state
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error for a transition that is not allowed
+    pub fn transition(&mut self, next: CoinState) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.state.can_transition_to(next) {
+            return Err(format!("Coin cannot move from {} to {}", self.state, next).into());
+        }
+        self.state = next;
+        if next != CoinState::Failed {
+            self.error = None;
         }
+        Ok(())
+    }
+
+    /// Mark the coin as failed with a reason
+    ///
+    /// # Arguments
+    /// * `error` - What went wrong
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error if the coin is already minted
+    pub fn fail(&mut self, error: impl Into<String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.transition(CoinState::Failed)?;
+        self.error = Some(error.into());
+        Ok(())
+    }
+
+    /// Check if the coin has been minted
+    ///
+    /// # Returns
+    /// * true if the mint transaction succeeded
+    pub fn is_minted(&self) -> bool {
+        self.state == CoinState::Minted
     }
 
     /// Get the effective value of the coin
@@ -115,17 +303,98 @@ impl Coin {
 }
 
 
-/// Represents a DarkMint wallet containing entropy and coins
-///
-/// The wallet manages user's privacy-preserving coins and derives
-/// burn addresses deterministically from entropy.
-/// 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Wallet {
+/// Persistent content of a wallet, as saved by a `WalletStore`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletData {
+    /// Layout version of the wallet JSON, see `migration`
+    version: u32,
     /// Hex-encoded entropy for address derivation
     entropy: String,
     /// Collection of privacy-preserving coins
     coins: Vec<Coin>,
+    /// Burn key derivation scheme, `legacy` for wallets that predate it
+    #[serde(default)]
+    derivation: DerivationScheme,
+    /// First burn address index that was never handed out
+    #[serde(default)]
+    next_burn_index: u64,
+    /// Burn address indexes that were reserved or funded
+    #[serde(default)]
+    burn_addresses: Vec<BurnAddressRecord>,
+    /// Burns that were broadcast but are not confirmed yet
+    #[serde(default)]
+    pending_burns: Vec<PendingBurn>,
+}
+
+impl WalletData {
+    /// Create the content of a new wallet without coins
+    fn new(entropy: &[u8; NOTE_SIZE], derivation: DerivationScheme) -> Self {
+        Self {
+            version: migration::WALLET_SCHEMA_VERSION,
+            entropy: hex::encode(entropy),
+            coins: vec![],
+            derivation,
+            next_burn_index: 0,
+            burn_addresses: vec![],
+            pending_burns: vec![],
+        }
+    }
+
+    /// Content of a wallet that is still locked
+    fn locked() -> Self {
+        Self {
+            version: migration::WALLET_SCHEMA_VERSION,
+            entropy: String::new(),
+            coins: vec![],
+            derivation: DerivationScheme::default(),
+            next_burn_index: 0,
+            burn_addresses: vec![],
+            pending_burns: vec![],
+        }
+    }
+
+    /// Get the coins of the wallet
+    ///
+    /// # Returns
+    /// * Slice of coins
+    pub fn coins(&self) -> &[Coin] {
+        &self.coins
+    }
+
+    /// Get the burn key derivation scheme of the wallet
+    ///
+    /// # Returns
+    /// * Derivation scheme
+    pub fn derivation(&self) -> DerivationScheme {
+        self.derivation
+    }
+}
+
+/// Represents a DarkMint wallet containing entropy and coins
+///
+/// The wallet manages user's privacy-preserving coins and derives
+/// burn addresses deterministically from entropy. Every change is saved to
+/// the wallet's store, the encrypted wallet file by default.
+///
+/// A wallet protected by a password starts locked when it is opened without
+/// one. Entropy and coins are only available after `unlock`.
+///
+/// A wallet file stays locked until the wallet is dropped, so a second process
+/// opening the same wallet fails instead of overwriting it.
+#[derive(Debug)]
+pub struct Wallet<S: WalletStore = FileStore> {
+    /// Entropy and coins
+    data: WalletData,
+    /// Chain id used by hierarchical derivation, set from the connected provider
+    chain_id: Option<u64>,
+    /// Key derived from the wallet password, if the wallet is encrypted
+    key: Option<WalletKey>,
+    /// Whether the wallet is encrypted but its content is not loaded
+    locked: bool,
+    /// Where the wallet is saved
+    store: S,
+    /// Reverse index of derived burn addresses, not persisted
+    address_index: AddressIndex,
 }
 
 /// Represents a burn address with its preimage
@@ -152,54 +421,426 @@ impl BurnAddress {
     pub fn new(preimage: Fr, address: Address) -> Self {
         Self { preimage, address }
     }
+
+    /// Compute the burn address of a preimage
+    ///
+    /// # Arguments
+    /// * `preimage` - Field element preimage
+    ///
+    /// # Returns
+    /// * Burn address with its preimage
+    pub fn from_preimage(preimage: Fr) -> Self {
+        let hashed = compute_poseidon_hash(preimage, preimage);
+        let hashed_bytes = hashed.into_bigint().to_bytes_be();
+
+        let mut address_bytes = [0u8; 20];
+        for (i, byte) in address_bytes.iter_mut().enumerate() {
+            *byte = hashed_bytes.get(i).copied().unwrap_or(0);
+        }
+
+        Self::new(preimage, Address::from_slice(&address_bytes))
+    }
+
+    /// Compute the nullifier recorded by the DarkMint contract for this address
+    ///
+    /// The proving program commits the Poseidon nullifier truncated to its
+    /// lowest 32 bits, and that value is what `mint` stores in `nullifiers`.
+    ///
+    /// # Returns
+    /// * Nullifier as U256
+    pub fn nullifier(&self) -> U256 {
+        let nullifier = fibonacci_lib::crypto::generate_nullifier(self.preimage);
+        let bytes = nullifier.into_bigint().to_bytes_be();
+        U256::from_be_slice(&bytes[bytes.len() - 4..])
+    }
 }
 
 impl Wallet {
-    /// Open existing wallet or create a new one
+    /// Open the selected wallet
+    ///
+    /// The wallet is picked by `wallet_location::resolve_wallet_path` and the
+    /// password is taken from the `DARKMINT_WALLET_PASSWORD` environment
+    /// variable when it is set.
+    ///
+    /// # Returns
+    /// * Result containing the wallet or an error
+    pub fn open_selected() -> Result<Self, Box<dyn std::error::Error>> {
+        let password = std::env::var(wallet_file::WALLET_PASSWORD_ENV).ok();
+        Self::open_selected_with_password(password.as_deref())
+    }
+
+    /// Open the selected wallet, using an optional password
     ///
-    /// If the wallet file doesn't exist, creates a new wallet with random entropy.
-    /// Otherwise, loads the existing wallet from the file.
+    /// Wallets are only created by `wallet create`, so a mistyped wallet name
+    /// fails instead of silently creating an empty wallet. Encrypted wallets
+    /// are unlocked with the password (or stay locked without one) and
+    /// plaintext wallets are migrated to the encrypted format when a password
+    /// is given.
+    ///
+    /// # Arguments
+    /// * `password` - Optional wallet password
     ///
     /// # Returns
     /// * Result containing the wallet or an error
-    pub fn open_or_create() -> Result<Self, Box<dyn std::error::Error>> {
-        if !std::path::Path::new(WALLET_FILENAME).exists() {
-            Self::create_new_wallet()
+    pub fn open_selected_with_password(
+        password: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = wallet_location::resolve_existing_wallet_path()?;
+        Self::open(&path, password)
+    }
+
+    /// Create a new wallet with random entropy at `path`
+    ///
+    /// Missing parent directories are created. Refuses to overwrite an
+    /// existing wallet file.
+    ///
+    /// # Arguments
+    /// * `path` - Location of the new wallet file
+    /// * `password` - Optional password used to encrypt the wallet file
+    ///
+    /// # Returns
+    /// * Result containing the new wallet or an error
+    pub fn create(path: &Path, password: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::create_with_store(FileStore::open(path)?, password)
+    }
+
+    /// Open an existing wallet file
+    ///
+    /// Encrypted wallets are unlocked with the password, or stay locked without
+    /// one. Plaintext wallets are migrated to the encrypted format when a
+    /// password is given.
+    ///
+    /// # Arguments
+    /// * `path` - Location of the wallet file
+    /// * `password` - Optional wallet password
+    ///
+    /// # Returns
+    /// * Result containing the loaded wallet or an error
+    pub fn open(path: &Path, password: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Err(format!("No wallet found at '{}'", path.display()).into());
+        }
+        Self::open_with_store(FileStore::open(path)?, password)
+    }
+
+    /// Get the location of the wallet file
+    ///
+    /// # Returns
+    /// * Path of the wallet file
+    pub fn path(&self) -> &Path {
+        self.store.path()
+    }
+}
+
+impl Wallet<MemoryStore> {
+    /// Read the selected wallet into memory, for commands that must not write it
+    ///
+    /// Layout upgrades only apply to the copy in memory. Encrypted wallets are
+    /// unlocked with the password from `DARKMINT_WALLET_PASSWORD` when it is
+    /// set; plaintext wallets are not encrypted.
+    ///
+    /// # Returns
+    /// * Result containing the wallet or an error
+    pub fn open_selected_in_memory() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = wallet_location::resolve_existing_wallet_path()?;
+        let content = zeroize::Zeroizing::new(std::fs::read_to_string(path)?);
+        let password = if wallet_file::is_encrypted(&content) {
+            std::env::var(wallet_file::WALLET_PASSWORD_ENV).ok()
         } else {
-            Self::load_existing_wallet()
+            None
+        };
+        Self::open_with_store(MemoryStore::with_content(&content), password.as_deref())
+    }
+
+    /// Build an in-memory wallet from entropy without touching the disk
+    ///
+    /// # Arguments
+    /// * `entropy` - Wallet entropy
+    /// * `derivation` - Burn key derivation scheme
+    ///
+    /// # Returns
+    /// * New unlocked wallet without coins
+    pub fn from_entropy(entropy: &[u8; NOTE_SIZE], derivation: DerivationScheme) -> Self {
+        Self::with_data(MemoryStore::new(), WalletData::new(entropy, derivation))
+    }
+}
+
+impl<S: WalletStore> Wallet<S> {
+    /// Wrap loaded or new wallet content
+    fn with_data(store: S, data: WalletData) -> Self {
+        Wallet {
+            data,
+            chain_id: None,
+            key: None,
+            locked: false,
+            store,
+            address_index: AddressIndex::default(),
         }
     }
 
-    /// Create a new wallet with random entropy
+    /// Create a new wallet with random entropy in a store
+    ///
+    /// # Arguments
+    /// * `store` - Empty wallet store
+    /// * `password` - Optional password used to encrypt the wallet
     ///
     /// # Returns
-    /// * Result containing the new wallet or an error
-    fn create_new_wallet() -> Result<Self, Box<dyn std::error::Error>> {
+    /// * Result containing the new wallet, or an error if the store holds a wallet
+    pub fn create_with_store(
+        store: S,
+        password: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let mut entropy = [0u8; NOTE_SIZE];
         OsRng.fill_bytes(&mut entropy);
 
-        let wallet = Wallet {
-            entropy: hex::encode(entropy),
-            coins: vec![],
-        };
+        let wallet =
+            Self::create_from_entropy(store, &entropy, DerivationScheme::Hierarchical, password);
+        entropy.zeroize();
+        wallet
+    }
 
-        wallet.save_to_file()?;
+    /// Create a wallet from existing entropy and save it to a store
+    ///
+    /// # Arguments
+    /// * `store` - Empty wallet store
+    /// * `entropy` - Wallet entropy
+    /// * `derivation` - Burn key derivation scheme
+    /// * `password` - Optional password used to encrypt the wallet
+    ///
+    /// # Returns
+    /// * Result containing the new wallet or an error
+    fn create_from_entropy(
+        store: S,
+        entropy: &[u8; NOTE_SIZE],
+        derivation: DerivationScheme,
+        password: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if store.exists()? {
+            return Err(format!("Wallet '{}' already exists", store.location()).into());
+        }
+
+        let mut wallet = Self::with_data(store, WalletData::new(entropy, derivation));
+        match password {
+            Some(password) => wallet.set_password(password)?,
+            None => wallet.save()?,
+        }
         Ok(wallet)
     }
 
-    /// Load existing wallet from file
+    /// Open the wallet held by a store
+    ///
+    /// Encrypted wallets are unlocked with the password, or stay locked without
+    /// one. Plaintext wallets are migrated to the encrypted format when a
+    /// password is given. Wallets saved with an older layout are upgraded.
+    ///
+    /// # Arguments
+    /// * `store` - Wallet store
+    /// * `password` - Optional wallet password
     ///
     /// # Returns
     /// * Result containing the loaded wallet or an error
-    fn load_existing_wallet() -> Result<Self, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(WALLET_FILENAME)?;
-        Ok(serde_json::from_str(&content)?)
+    pub fn open_with_store(
+        store: S,
+        password: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if !store.exists()? {
+            return Err(format!("No wallet found at '{}'", store.location()).into());
+        }
+
+        if store.is_encrypted()? {
+            let mut wallet = Self::with_data(store, WalletData::locked());
+            wallet.locked = true;
+            if let Some(password) = password {
+                wallet.unlock(password)?;
+            }
+            return Ok(wallet);
+        }
+
+        let stored = store.load(None)?;
+        let (data, migrated) = migration::load_wallet_json(&stored.json)?;
+        let mut wallet = Self::with_data(store, data);
+        if let Some(password) = password {
+            // Migrate legacy plaintext wallets to the encrypted format
+            wallet.set_password(password)?;
+            println!("Wallet migrated to the encrypted format");
+        } else if migrated {
+            wallet.save()?;
+        }
+        Ok(wallet)
+    }
+
+    /// Get the store the wallet is saved to
+    ///
+    /// # Returns
+    /// * Wallet store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Get the persistent content of the wallet
+    ///
+    /// # Returns
+    /// * Wallet data, empty while the wallet is locked
+    pub fn data(&self) -> &WalletData {
+        &self.data
+    }
+
+    /// Check whether the wallet is password protected
+    ///
+    /// # Returns
+    /// * true if the wallet is stored encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.locked || self.key.is_some()
+    }
+
+    /// Check whether the wallet is locked
+    ///
+    /// # Returns
+    /// * true if entropy and coins are not loaded
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Unlock an encrypted wallet with its password
+    ///
+    /// # Arguments
+    /// * `password` - Wallet password
+    ///
+    /// # Returns
+    /// * Result indicating success or error (e.g. wrong password)
+    pub fn unlock(&mut self, password: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.locked {
+            return Ok(());
+        }
+
+        let stored = self.store.load(Some(password))?;
+        let (data, migrated) = migration::load_wallet_json(&stored.json)?;
+
+        self.data = data;
+        self.key = stored.key;
+        self.locked = false;
+        if migrated {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Lock the wallet, wiping entropy, coins and key from memory
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error for plaintext wallets
+    pub fn lock(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.key.is_none() && !self.locked {
+            return Err("Wallet has no password set and cannot be locked".into());
+        }
+
+        self.data.entropy.zeroize();
+        self.data.coins.clear();
+        self.data.burn_addresses.clear();
+        self.address_index.clear();
+        self.key = None;
+        self.locked = true;
+        Ok(())
+    }
+
+    /// Protect the wallet with a password and re-save it encrypted
+    ///
+    /// Used for new wallets, for migrating plaintext wallets and for password
+    /// changes. Backups of the previous version are removed, since they are
+    /// plaintext or still open with the old password.
+    ///
+    /// # Arguments
+    /// * `password` - New wallet password
+    ///
+    /// # Returns
+    /// * Result indicating success or error
+    pub fn set_password(&mut self, password: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        if password.is_empty() {
+            return Err("Wallet password must not be empty".into());
+        }
+
+        let key = wallet_file::KdfParams::new_random().derive_key(password)?;
+        self.store.save_rekeyed(&self.data, &key)?;
+        self.key = Some(key);
+        Ok(())
+    }
+
+    /// Change the password of an encrypted wallet
+    ///
+    /// The old password is checked against the stored wallet, then the wallet is
+    /// re-encrypted under a key derived from the new password with a fresh salt.
+    ///
+    /// # Arguments
+    /// * `old_password` - Current wallet password
+    /// * `new_password` - New wallet password
+    ///
+    /// # Returns
+    /// * Result indicating success or error
+    pub fn change_password(
+        &mut self,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.is_encrypted() {
+            return Err("Wallet has no password set".into());
+        }
+
+        if self.locked {
+            self.unlock(old_password)?;
+        } else {
+            self.store.load(Some(old_password))?;
+        }
+        self.set_password(new_password)
+    }
+
+    /// Return an error if the wallet is locked
+    fn ensure_unlocked(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.locked {
+            return Err("Wallet is locked, unlock it with the wallet password first".into());
+        }
+        Ok(())
+    }
+
+    /// Get the burn key derivation scheme of the wallet
+    ///
+    /// # Returns
+    /// * Derivation scheme
+    pub fn derivation_scheme(&self) -> DerivationScheme {
+        self.data.derivation
+    }
+
+    /// Set the chain id used by hierarchical derivation
+    ///
+    /// Callers set it from the connected provider so that burn addresses are
+    /// bound to the chain they are used on. Legacy wallets ignore it.
+    ///
+    /// # Arguments
+    /// * `chain_id` - EIP-155 chain id
+    pub fn set_chain_id(&mut self, chain_id: u64) {
+        if self.chain_id != Some(chain_id) {
+            self.address_index.clear();
+        }
+        self.chain_id = Some(chain_id);
+    }
+
+    /// Build the hierarchical derivation path for a purpose-specific key
+    fn derivation_path(
+        &self,
+        index: u64,
+        path: fn(u64, u32, u64) -> DerivationPath,
+    ) -> Result<DerivationPath, Box<dyn std::error::Error>> {
+        let chain_id = self
+            .chain_id
+            .ok_or("Chain id not set, hierarchical derivation needs the target chain")?;
+        Ok(path(chain_id, DEFAULT_ACCOUNT, index))
     }
 
     /// Derive a burn address from wallet entropy and index
     ///
-    /// Uses SHA-256 to hash entropy + index, then Poseidon to derive the address.
-    /// This provides deterministic address generation while maintaining privacy.
+    /// Legacy wallets hash entropy + index with SHA-256; hierarchical wallets
+    /// derive the preimage at `m/purpose'/chain'/account'/index'`. Poseidon then
+    /// turns the preimage into the address.
     ///
     /// # Arguments
     /// * `index` - Index for address derivation (allows multiple addresses per wallet)
@@ -210,32 +851,21 @@ impl Wallet {
         &self,
         index: u64,
     ) -> Result<BurnAddress, Box<dyn std::error::Error>> {
-        // Decode entropy and create hash input
-        let entropy_bytes = hex::decode(&self.entropy)?;
-        let preimage = self.compute_address_preimage(&entropy_bytes, index);
+        self.ensure_unlocked()?;
+
+        let entropy_bytes = zeroize::Zeroizing::new(hex::decode(&self.data.entropy)?);
+        let preimage = match self.data.derivation {
+            DerivationScheme::Legacy => derivation::legacy_preimage(&entropy_bytes, index),
+            DerivationScheme::Hierarchical => derivation::hierarchical_preimage(
+                &entropy_bytes,
+                &self.derivation_path(index, DerivationPath::burn)?,
+            ),
+        };
         let address = self.derive_ethereum_address_from_preimage(preimage);
 
         Ok(BurnAddress::new(preimage, address))
     }
 
-    /// Compute the preimage for address derivation
-    ///
-    /// # Arguments
-    /// * `entropy_bytes` - Decoded entropy bytes
-    /// * `index` - Address index
-    ///
-    /// # Returns
-    /// * Field element preimage
-    fn compute_address_preimage(&self, entropy_bytes: &[u8], index: u64) -> Fr {
-        let mut hasher = Sha256::new();
-        hasher.update(entropy_bytes);
-        hasher.update(index.to_le_bytes());
-        let result = hasher.finalize();
-
-        // Take first 31 bytes to ensure we stay within field size
-        Fr::from_le_bytes_mod_order(&result[..31])
-    }
-
     /// Derive Ethereum address from preimage using Poseidon hash
     ///
     /// # Arguments
@@ -244,15 +874,7 @@ impl Wallet {
     /// # Returns
     /// * Derived Ethereum address
     fn derive_ethereum_address_from_preimage(&self, preimage: Fr) -> Address {
-        let hashed = compute_poseidon_hash(preimage, preimage);
-        let hashed_bytes = hashed.into_bigint().to_bytes_be();
-
-        let mut address_bytes = [0u8; 20];
-        for (i, byte) in address_bytes.iter_mut().enumerate() {
-            *byte = hashed_bytes.get(i).copied().unwrap_or(0);
-        }
-
-        Address::from_slice(&address_bytes)
+        BurnAddress::from_preimage(preimage).address
     }
 
     /// Create a new coin from a field element amount
@@ -271,7 +893,58 @@ impl Wallet {
         Coin::new(amount_u256, salt, encrypted)
     }
 
-    /// Add a coin to the wallet and persist to disk
+    /// Derive the deterministic salt of the coin minted from a burn address
+    ///
+    /// Salts are derived from wallet entropy so that coins can be rebuilt
+    /// from the entropy alone after a restore. Hierarchical wallets take the
+    /// salt from the coin salt branch of the derivation tree.
+    ///
+    /// # Arguments
+    /// * `index` - Index of the burn address the coin is minted from
+    ///
+    /// # Returns
+    /// * Result containing the salt or an error
+    pub fn derive_coin_salt(&self, index: u64) -> Result<U256, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+
+        let entropy_bytes = zeroize::Zeroizing::new(hex::decode(&self.data.entropy)?);
+        match self.data.derivation {
+            DerivationScheme::Legacy => {
+                let mut hasher = Sha256::new();
+                hasher.update(COIN_SALT_DOMAIN);
+                hasher.update(entropy_bytes.as_slice());
+                hasher.update(index.to_le_bytes());
+                Ok(U256::from_be_slice(&hasher.finalize()))
+            }
+            DerivationScheme::Hierarchical => {
+                let path = self.derivation_path(index, DerivationPath::coin_salt)?;
+                let node = derivation::derive_node(&entropy_bytes, &path);
+                Ok(U256::from_be_slice(node.as_ref()))
+            }
+        }
+    }
+
+    /// Create the coin minted from the burn address at `index`
+    ///
+    /// # Arguments
+    /// * `index` - Index of the burn address
+    /// * `amount` - Coin amount in wei
+    /// * `encrypted` - Whether the coin should be encrypted
+    ///
+    /// # Returns
+    /// * Result containing the coin with its deterministic salt or an error
+    pub fn create_coin_for_burn(
+        &self,
+        index: u64,
+        amount: U256,
+        encrypted: bool,
+    ) -> Result<Coin, Box<dyn std::error::Error>> {
+        let mut coin = Coin::new(amount, self.derive_coin_salt(index)?, encrypted);
+        coin.burn_index = Some(index);
+        Ok(coin)
+    }
+
+    /// Add a coin to the wallet and save it
     ///
     /// # Arguments
     /// * `coin` - Coin to add to the wallet
@@ -279,11 +952,100 @@ impl Wallet {
     /// # Returns
     /// * Result indicating success or error
     pub fn add_coin(&mut self, coin: Coin) -> Result<(), Box<dyn std::error::Error>> {
-        self.coins.push(coin);
-        self.save_to_file()?;
+        self.ensure_unlocked()?;
+        self.data.coins.push(coin);
+        self.save()?;
         Ok(())
     }
 
+    /// Get the coin minted from a burn address
+    ///
+    /// # Arguments
+    /// * `burn_index` - Index of the burn address
+    ///
+    /// # Returns
+    /// * The coin, if the wallet has one for this burn address
+    pub fn coin_for_burn(&self, burn_index: u64) -> Option<&Coin> {
+        self.data.coins
+            .iter()
+            .find(|coin| coin.burn_index == Some(burn_index))
+    }
+
+    /// Update the coin minted from a burn address and save it
+    ///
+    /// Nothing is saved when `update` returns an error.
+    ///
+    /// # Arguments
+    /// * `burn_index` - Index of the burn address the coin was minted from
+    /// * `update` - Changes to apply to the coin
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error if no such coin exists
+    pub fn update_coin<F>(&mut self, burn_index: u64, update: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnOnce(&mut Coin) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        self.ensure_unlocked()?;
+        let coin = self
+            .data
+            .coins
+            .iter_mut()
+            .find(|coin| coin.burn_index == Some(burn_index))
+            .ok_or_else(|| format!("No coin for burn address #{}", burn_index))?;
+
+        let mut updated = coin.clone();
+        update(&mut updated)?;
+        *coin = updated;
+        self.save()
+    }
+
+    /// Get the coin burned to a burn address
+    ///
+    /// # Arguments
+    /// * `address` - Burn address
+    ///
+    /// # Returns
+    /// * The coin, if the wallet has one for this burn address
+    pub fn coin_for_burn_address(&self, address: Address) -> Option<&Coin> {
+        self.data.coins
+            .iter()
+            .find(|coin| coin.burn_address == Some(address))
+    }
+
+    /// Update the coin burned to a burn address and save it
+    ///
+    /// Unlike `update_coin` this also reaches coins imported from notes, which
+    /// have no burn address index in this wallet. Nothing is saved when
+    /// `update` returns an error.
+    ///
+    /// # Arguments
+    /// * `address` - Burn address the coin was burned to
+    /// * `update` - Changes to apply to the coin
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error if no such coin exists
+    pub fn update_coin_for_burn_address<F>(
+        &mut self,
+        address: Address,
+        update: F,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnOnce(&mut Coin) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        self.ensure_unlocked()?;
+        let coin = self
+            .data
+            .coins
+            .iter_mut()
+            .find(|coin| coin.burn_address == Some(address))
+            .ok_or_else(|| format!("No coin for burn address {}", address))?;
+
+        let mut updated = coin.clone();
+        update(&mut updated)?;
+        *coin = updated;
+        self.save()
+    }
+
     /// Remove a coin from the wallet by index
     ///
     /// # Arguments
@@ -292,8 +1054,8 @@ impl Wallet {
     /// # Panics
     /// * If index is out of bounds
     pub fn remove_coin(&mut self, index: usize) {
-        if index < self.coins.len() {
-            self.coins.remove(index);
+        if index < self.data.coins.len() {
+            self.data.coins.remove(index);
         }
     }
 
@@ -302,7 +1064,7 @@ impl Wallet {
     /// # Returns
     /// * Slice of coins
     pub fn get_coins(&self) -> &[Coin] {
-        &self.coins
+        &self.data.coins
     }
 
     /// Get the number of coins in the wallet
@@ -310,26 +1072,77 @@ impl Wallet {
     /// # Returns
     /// * Number of coins
     pub fn coin_count(&self) -> usize {
-        self.coins.len()
+        self.data.coins.len()
     }
 
-    /// Save wallet to file
+    /// Save the wallet to its store
+    ///
+    /// Encrypted wallets are sealed with the in-memory key, legacy wallets
+    /// are saved as plaintext JSON.
     ///
     /// # Returns
     /// * Result indicating success or error
-    fn save_to_file(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut file = fs::File::create(WALLET_FILENAME)?;
-        let json_data = serde_json::to_string_pretty(self)?;
-        file.write_all(json_data.as_bytes())?;
-        Ok(())
+    fn save(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        self.store.save(&self.data, self.key.as_ref())
     }
 }
 
 // Re-export modules for external use
+pub mod amount;
 pub mod burn;
+pub mod burn_index;
 pub mod commands;
+pub mod denomination;
+pub mod derivation;
+pub mod disclosure;
+pub mod linkability;
+pub mod migration;
 pub mod mint;
+pub mod mnemonic;
+pub mod network;
+pub mod note;
+pub mod offline;
+pub mod pending_burn;
+pub mod recovery;
+pub mod shamir;
+pub mod signer;
+pub mod simulate;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod store;
+pub mod viewing_key;
+pub mod wallet_file;
+pub mod wallet_location;
 
 // Re-export commonly used functions and types
-pub use burn::burn_cmd;
+pub use burn::{burn_cmd, pending_burns_cmd, replace_burn_cmd, BurnContext, ReplaceBurnContext};
 pub use mint::{mint_cmd, MintContext};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coin_state_transitions() {
+        let mut coin = Coin::new(U256::from(1), U256::from(2), false);
+        assert_eq!(coin.state, CoinState::Burned);
+        assert!(coin.transition(CoinState::Minted).is_err());
+
+        for state in [
+            CoinState::Proving,
+            CoinState::Proven,
+            CoinState::Submitted,
+            CoinState::Aggregated,
+        ] {
+            coin.transition(state).unwrap();
+        }
+        coin.fail("relayer timeout").unwrap();
+        assert_eq!(coin.state, CoinState::Failed);
+        assert_eq!(coin.error.as_deref(), Some("relayer timeout"));
+
+        // A failed coin is proven again from the start
+        coin.transition(CoinState::Proving).unwrap();
+        assert!(coin.error.is_none());
+    }
+}
@@ -0,0 +1,684 @@
+//! Linkability analysis of burns and mints
+//!
+//! A burn and its mint are unlinkable only when nothing but the proof ties
+//! them together. Funding the burn and paying the mint gas from the same
+//! account, minting right after burning, minting to the funding account or
+//! burning an unusual amount all link them again. The parts of a split burn
+//! are sent from one account with consecutive nonces, so minting them to the
+//! same account puts the amount back together. Planned operations are
+//! checked against the history of the wallet before anything is broadcast,
+//! and the result is a privacy score out of 100 with concrete warnings. In
+//! strict mode any warning is a hard failure.
+
+use alloy::{
+    consensus::Transaction,
+    eips::BlockNumberOrTag,
+    network::TransactionResponse,
+    primitives::{Address, U256},
+    providers::Provider,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::amount::Amount;
+use crate::denomination::standard_denominations;
+use crate::mint::decode_mint_call;
+use crate::store::WalletStore;
+use crate::Wallet;
+
+/// Score of an operation without warnings
+pub const MAX_PRIVACY_SCORE: u32 = 100;
+
+/// Time a burn should age before it is minted, in seconds
+pub const MIN_BURN_AGE_SECS: u64 = 60 * 60;
+
+/// Something that links a burn to its mint or to other coins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkWarning {
+    /// The mint gas is paid by the account that funded the burn
+    FeePayerFundedBurn,
+    /// The mint pays out to the account that funded the burn
+    RecipientFundedBurn,
+    /// The mint gas is paid by an account that funded other burns of the wallet
+    FeePayerFundedOtherBurns,
+    /// The mint account already minted other coins of the wallet
+    MintAccountReused,
+    /// The burn is minted soon after it was mined
+    MintRightAfterBurn {
+        /// Seconds since the burn was mined
+        age_secs: u64,
+    },
+    /// The burn is funded by an account that received mints of the wallet
+    SourceReceivedMints,
+    /// The burn is funded by an account that funded other burns of the wallet
+    SourceFundedOtherBurns,
+    /// The burn is funded by an account that paid the gas of mints of the wallet
+    SourcePaidMints,
+    /// The parts of a split burn share their source and consecutive nonces
+    SplitPartsLinked {
+        /// Number of parts
+        parts: usize,
+    },
+    /// The mint account already minted another part of the same split burn
+    SplitPartsMintedTogether,
+    /// An amount is not a standard denomination
+    NonStandardAmount(U256),
+}
+
+impl LinkWarning {
+    /// Get the points the warning takes off the privacy score
+    pub fn penalty(&self) -> u32 {
+        match self {
+            LinkWarning::FeePayerFundedBurn | LinkWarning::RecipientFundedBurn => 40,
+            LinkWarning::SourceReceivedMints | LinkWarning::SplitPartsMintedTogether => 30,
+            LinkWarning::FeePayerFundedOtherBurns | LinkWarning::MintRightAfterBurn { .. } => 25,
+            LinkWarning::SourcePaidMints => 20,
+            LinkWarning::MintAccountReused | LinkWarning::NonStandardAmount(_) => 15,
+            LinkWarning::SourceFundedOtherBurns | LinkWarning::SplitPartsLinked { .. } => 10,
+        }
+    }
+}
+
+impl fmt::Display for LinkWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkWarning::FeePayerFundedBurn => {
+                write!(f, "The mint fee payer is the account that funded the burn")
+            }
+            LinkWarning::RecipientFundedBurn => {
+                write!(f, "The mint recipient is the account that funded the burn")
+            }
+            LinkWarning::FeePayerFundedOtherBurns => {
+                write!(f, "The mint fee payer funded other burns of this wallet")
+            }
+            LinkWarning::MintAccountReused => write!(
+                f,
+                "The mint account already minted other coins of this wallet"
+            ),
+            LinkWarning::MintRightAfterBurn { age_secs } => write!(
+                f,
+                "The burn was mined {} minutes ago, wait at least {} minutes before minting it",
+                age_secs / 60,
+                MIN_BURN_AGE_SECS / 60
+            ),
+            LinkWarning::SourceReceivedMints => {
+                write!(f, "The burn source received mints of this wallet")
+            }
+            LinkWarning::SourceFundedOtherBurns => {
+                write!(f, "The burn source funded other burns of this wallet")
+            }
+            LinkWarning::SourcePaidMints => {
+                write!(f, "The burn source paid the gas of mints of this wallet")
+            }
+            LinkWarning::SplitPartsLinked { parts } => write!(
+                f,
+                "The {} parts are sent from one account with consecutive nonces, mint them to different accounts",
+                parts
+            ),
+            LinkWarning::SplitPartsMintedTogether => write!(
+                f,
+                "The mint account already minted another part of this split burn"
+            ),
+            LinkWarning::NonStandardAmount(amount) => write!(
+                f,
+                "{} is not a standard denomination (split it with --split)",
+                Amount::from_wei(*amount)
+            ),
+        }
+    }
+}
+
+/// Privacy score of a planned operation with its warnings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkabilityReport {
+    /// Warnings, most severe first
+    pub warnings: Vec<LinkWarning>,
+}
+
+impl LinkabilityReport {
+    fn new(mut warnings: Vec<LinkWarning>) -> Self {
+        warnings.sort_by_key(|warning| std::cmp::Reverse(warning.penalty()));
+        LinkabilityReport { warnings }
+    }
+
+    /// Get the privacy score, 100 without warnings
+    pub fn score(&self) -> u32 {
+        let penalty: u32 = self.warnings.iter().map(LinkWarning::penalty).sum();
+        MAX_PRIVACY_SCORE.saturating_sub(penalty)
+    }
+
+    /// Print the privacy score and the warnings
+    pub fn print(&self) {
+        println!("Privacy score: {}/{}", self.score(), MAX_PRIVACY_SCORE);
+        for warning in &self.warnings {
+            println!("  ⚠️ {} (-{})", warning, warning.penalty());
+        }
+    }
+
+    /// Fail in strict mode when anything links the operation
+    ///
+    /// # Arguments
+    /// * `strict` - Whether warnings are errors
+    ///
+    /// # Returns
+    /// * Result indicating whether the operation may go ahead
+    pub fn enforce(&self, strict: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if strict && !self.warnings.is_empty() {
+            return Err(format!(
+                "Privacy score {}/{} with {} warnings, refused in strict mode",
+                self.score(),
+                MAX_PRIVACY_SCORE,
+                self.warnings.len()
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Burn of the wallet as seen on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BurnRecord {
+    /// Account that funded the burn
+    pub source: Address,
+    /// Nonce of the burn transaction in its source account
+    pub nonce: u64,
+    /// Block the burn was mined in, `None` while it is pending
+    pub block: Option<u64>,
+}
+
+/// Mint of the wallet as seen on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MintRecord {
+    /// Account that paid the mint gas
+    pub fee_payer: Address,
+    /// Account the tokens were minted to
+    pub recipient: Address,
+}
+
+/// Accounts the wallet already used, looked up from its burn and mint transactions
+#[derive(Debug, Clone, Default)]
+pub struct WalletHistory {
+    /// Burns by burn address
+    pub burns: HashMap<Address, BurnRecord>,
+    /// Mints by the burn address of the minted coin
+    pub mints: HashMap<Address, MintRecord>,
+}
+
+impl WalletHistory {
+    /// Look up the burn and mint transactions of the coins and pending burns
+    ///
+    /// # Arguments
+    /// * `wallet` - Wallet whose history is analyzed
+    /// * `provider` - Provider of the DarkMint chain
+    ///
+    /// # Returns
+    /// * Result containing the history or an error
+    pub async fn fetch<S: WalletStore, P: Provider>(
+        wallet: &Wallet<S>,
+        provider: &P,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut history = WalletHistory::default();
+        for pending in wallet.pending_burns() {
+            history.burns.insert(
+                pending.burn_address,
+                BurnRecord {
+                    source: pending.from,
+                    nonce: pending.nonce,
+                    block: None,
+                },
+            );
+        }
+        for coin in wallet.data.coins() {
+            if let (Some(burn_address), Some(tx_hash)) = (coin.burn_address, coin.burn_tx_hash) {
+                if let Some(tx) = provider.get_transaction_by_hash(tx_hash).await? {
+                    history.burns.insert(
+                        burn_address,
+                        BurnRecord {
+                            source: tx.from(),
+                            nonce: tx.nonce(),
+                            block: tx.block_number,
+                        },
+                    );
+                }
+            }
+            if let Some(tx_hash) = coin.mint_tx_hash {
+                if let Some(tx) = provider.get_transaction_by_hash(tx_hash).await? {
+                    // The recipient is an argument of the call, not the sender
+                    let mint = decode_mint_call(tx.input())?;
+                    history.mints.insert(
+                        mint.burn_address,
+                        MintRecord {
+                            fee_payer: tx.from(),
+                            recipient: mint.recipient,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(history)
+    }
+
+    /// Check whether an account funded a burn other than `burn_address`
+    ///
+    /// Other parts of the same split burn are not counted, their source is
+    /// shared by design.
+    fn funded_other_burns(&self, account: Address, burn_address: Option<Address>) -> bool {
+        let siblings = burn_address
+            .map(|address| self.split_siblings(address))
+            .unwrap_or_default();
+        self.burns.iter().any(|(&address, burn)| {
+            burn.source == account && Some(address) != burn_address && !siblings.contains(&address)
+        })
+    }
+
+    /// Check whether an account paid the gas of a mint
+    pub fn paid_mints(&self, account: Address) -> bool {
+        self.mints.values().any(|mint| mint.fee_payer == account)
+    }
+
+    /// Check whether an account received a mint
+    pub fn received_mints(&self, account: Address) -> bool {
+        self.mints.values().any(|mint| mint.recipient == account)
+    }
+
+    /// Get the other parts of the split burn a burn belongs to
+    ///
+    /// Parts of a split burn are sent back to back, so they are the burns of
+    /// the same source whose nonces run on from the burn without a gap.
+    ///
+    /// # Arguments
+    /// * `burn_address` - Burn address of one part
+    ///
+    /// # Returns
+    /// * The burn addresses of the other parts
+    pub fn split_siblings(&self, burn_address: Address) -> BTreeSet<Address> {
+        let Some(burn) = self.burns.get(&burn_address) else {
+            return BTreeSet::new();
+        };
+        let by_nonce: HashMap<u64, Address> = self
+            .burns
+            .iter()
+            .filter(|(_, other)| other.source == burn.source)
+            .map(|(&address, other)| (other.nonce, address))
+            .collect();
+
+        let mut siblings = BTreeSet::new();
+        let mut nonce = burn.nonce;
+        while let Some(&address) = nonce.checked_sub(1).and_then(|n| by_nonce.get(&n)) {
+            siblings.insert(address);
+            nonce -= 1;
+        }
+        let mut nonce = burn.nonce;
+        while let Some(&address) = nonce.checked_add(1).and_then(|n| by_nonce.get(&n)) {
+            siblings.insert(address);
+            nonce += 1;
+        }
+        siblings
+    }
+}
+
+/// Mint about to be sent
+#[derive(Debug, Clone, Copy)]
+pub struct MintPlan {
+    /// Account paying the mint gas
+    pub fee_payer: Address,
+    /// Account receiving the minted tokens
+    pub recipient: Address,
+    /// Burn address the coin was burned to
+    pub burn_address: Address,
+    /// Amount minted in wei
+    pub amount: U256,
+    /// Seconds since the burn was mined, when known
+    pub burn_age_secs: Option<u64>,
+}
+
+/// Find what links a planned burn to the other coins of the wallet
+///
+/// # Arguments
+/// * `source` - Account funding the burn
+/// * `amounts` - Amount of each burn transaction
+/// * `history` - History of the wallet
+///
+/// # Returns
+/// * The privacy report of the burn
+pub fn analyze_burn(
+    source: Address,
+    amounts: &[U256],
+    history: &WalletHistory,
+) -> LinkabilityReport {
+    let mut warnings = Vec::new();
+    if history.received_mints(source) {
+        warnings.push(LinkWarning::SourceReceivedMints);
+    } else if history.paid_mints(source) {
+        warnings.push(LinkWarning::SourcePaidMints);
+    }
+    if history.funded_other_burns(source, None) {
+        warnings.push(LinkWarning::SourceFundedOtherBurns);
+    }
+    if amounts.len() > 1 {
+        warnings.push(LinkWarning::SplitPartsLinked {
+            parts: amounts.len(),
+        });
+    }
+    warnings.extend(non_standard_amounts(amounts));
+    LinkabilityReport::new(warnings)
+}
+
+/// Find what links a planned mint to its burn or to the other coins of the wallet
+///
+/// # Arguments
+/// * `plan` - Planned mint
+/// * `history` - History of the wallet
+///
+/// # Returns
+/// * The privacy report of the mint
+pub fn analyze_mint(plan: &MintPlan, history: &WalletHistory) -> LinkabilityReport {
+    let mut warnings = Vec::new();
+    let source = history
+        .burns
+        .get(&plan.burn_address)
+        .map(|burn| burn.source);
+    if source == Some(plan.fee_payer) {
+        warnings.push(LinkWarning::FeePayerFundedBurn);
+    } else if history.funded_other_burns(plan.fee_payer, Some(plan.burn_address)) {
+        warnings.push(LinkWarning::FeePayerFundedOtherBurns);
+    }
+    if plan.recipient != plan.fee_payer && source == Some(plan.recipient) {
+        warnings.push(LinkWarning::RecipientFundedBurn);
+    }
+    let accounts = [plan.fee_payer, plan.recipient];
+    let minted_by_accounts = |mint: &MintRecord| {
+        accounts.contains(&mint.fee_payer) || accounts.contains(&mint.recipient)
+    };
+    let siblings = history.split_siblings(plan.burn_address);
+    if siblings
+        .iter()
+        .filter_map(|address| history.mints.get(address))
+        .any(minted_by_accounts)
+    {
+        warnings.push(LinkWarning::SplitPartsMintedTogether);
+    } else if history.mints.values().any(minted_by_accounts) {
+        warnings.push(LinkWarning::MintAccountReused);
+    }
+    if let Some(age_secs) = plan.burn_age_secs.filter(|&age| age < MIN_BURN_AGE_SECS) {
+        warnings.push(LinkWarning::MintRightAfterBurn { age_secs });
+    }
+    warnings.extend(non_standard_amounts(&[plan.amount]));
+    LinkabilityReport::new(warnings)
+}
+
+/// Warn once about the amounts that are not standard denominations
+fn non_standard_amounts(amounts: &[U256]) -> Option<LinkWarning> {
+    let denominations = standard_denominations();
+    amounts
+        .iter()
+        .find(|amount| !denominations.contains(amount))
+        .map(|&amount| LinkWarning::NonStandardAmount(amount))
+}
+
+/// Analyze a planned burn against the wallet history and print its score
+///
+/// # Arguments
+/// * `wallet` - Wallet the burn is recorded in
+/// * `provider` - Provider of the DarkMint chain
+/// * `source` - Account funding the burn
+/// * `amounts` - Amount of each burn transaction
+/// * `strict` - Whether any warning fails the burn
+///
+/// # Returns
+/// * Result indicating whether the burn may be broadcast
+pub async fn check_burn_linkability<S: WalletStore, P: Provider>(
+    wallet: &Wallet<S>,
+    provider: &P,
+    source: Address,
+    amounts: &[U256],
+    strict: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let history = WalletHistory::fetch(wallet, provider).await?;
+    let report = analyze_burn(source, amounts, &history);
+    report.print();
+    report.enforce(strict)
+}
+
+/// Analyze a planned mint against the wallet history and print its score
+///
+/// # Arguments
+/// * `wallet` - Wallet the coin is recorded in
+/// * `provider` - Provider of the DarkMint chain
+/// * `fee_payer` - Account paying the mint gas
+/// * `recipient` - Account receiving the minted tokens
+/// * `burn_address` - Burn address the coin was burned to
+/// * `amount` - Amount minted in wei
+/// * `strict` - Whether any warning fails the mint
+///
+/// # Returns
+/// * Result indicating whether the mint may be broadcast
+pub async fn check_mint_linkability<S: WalletStore, P: Provider>(
+    wallet: &Wallet<S>,
+    provider: &P,
+    fee_payer: Address,
+    recipient: Address,
+    burn_address: Address,
+    amount: U256,
+    strict: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let history = WalletHistory::fetch(wallet, provider).await?;
+    check_mint_against_history(
+        &history,
+        provider,
+        fee_payer,
+        recipient,
+        burn_address,
+        amount,
+        strict,
+    )
+    .await
+}
+
+/// Analyze a planned mint against a known history and print its score
+///
+/// Used where the wallet is not at hand, e.g. on the online side of the
+/// offline workflow, with whatever history is known there.
+///
+/// # Arguments
+/// * `history` - History the mint is checked against
+/// * `provider` - Provider of the DarkMint chain
+/// * `fee_payer` - Account paying the mint gas
+/// * `recipient` - Account receiving the minted tokens
+/// * `burn_address` - Burn address the coin was burned to
+/// * `amount` - Amount minted in wei
+/// * `strict` - Whether any warning fails the mint
+///
+/// # Returns
+/// * Result indicating whether the mint may be broadcast
+pub async fn check_mint_against_history<P: Provider>(
+    history: &WalletHistory,
+    provider: &P,
+    fee_payer: Address,
+    recipient: Address,
+    burn_address: Address,
+    amount: U256,
+    strict: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let burn_block = history.burns.get(&burn_address).and_then(|burn| burn.block);
+    let burn_age_secs = match burn_block {
+        Some(block) => Some(block_age_secs(provider, block).await?),
+        None => None,
+    };
+    let plan = MintPlan {
+        fee_payer,
+        recipient,
+        burn_address,
+        amount,
+        burn_age_secs,
+    };
+    let report = analyze_mint(&plan, history);
+    report.print();
+    report.enforce(strict)
+}
+
+/// Get the seconds between a block and the latest block
+async fn block_age_secs<P: Provider>(
+    provider: &P,
+    block: u64,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let mined = provider
+        .get_block_by_number(BlockNumberOrTag::Number(block))
+        .await?
+        .ok_or_else(|| format!("The node has no block {}", block))?;
+    let latest = provider
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .await?
+        .ok_or("The node has no latest block")?;
+    Ok(latest
+        .header
+        .timestamp
+        .saturating_sub(mined.header.timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: Address = Address::repeat_byte(1);
+    const FEE_PAYER: Address = Address::repeat_byte(2);
+    const RECIPIENT: Address = Address::repeat_byte(3);
+    const BURN: Address = Address::repeat_byte(4);
+    const OTHER_BURN: Address = Address::repeat_byte(5);
+    const THIRD_BURN: Address = Address::repeat_byte(6);
+    const FRESH: Address = Address::repeat_byte(7);
+
+    fn eth(amount: &str) -> U256 {
+        amount.parse::<Amount>().unwrap().wei()
+    }
+
+    fn history(burns: &[(Address, Address)]) -> WalletHistory {
+        let mut history = WalletHistory::default();
+        for (nonce, &(burn_address, source)) in burns.iter().enumerate() {
+            // Spaced nonces, so that no two burns look like parts of a split
+            history.burns.insert(
+                burn_address,
+                BurnRecord {
+                    source,
+                    nonce: nonce as u64 * 10,
+                    block: Some(1),
+                },
+            );
+        }
+        history
+    }
+
+    fn plan(fee_payer: Address, recipient: Address) -> MintPlan {
+        MintPlan {
+            fee_payer,
+            recipient,
+            burn_address: BURN,
+            amount: eth("1"),
+            burn_age_secs: Some(MIN_BURN_AGE_SECS),
+        }
+    }
+
+    #[test]
+    fn test_separate_accounts_score_full_marks() {
+        let history = history(&[(BURN, SOURCE)]);
+        let report = analyze_mint(&plan(FEE_PAYER, RECIPIENT), &history);
+        assert!(report.warnings.is_empty());
+        assert_eq!(report.score(), MAX_PRIVACY_SCORE);
+        assert!(report.enforce(true).is_ok());
+    }
+
+    #[test]
+    fn test_mint_from_the_funding_account_is_linked() {
+        let history = history(&[(BURN, SOURCE), (OTHER_BURN, FEE_PAYER)]);
+        let mut linked = plan(SOURCE, SOURCE);
+        linked.amount = eth("0.123456");
+        linked.burn_age_secs = Some(120);
+
+        let report = analyze_mint(&linked, &history);
+        assert_eq!(
+            report.warnings,
+            vec![
+                LinkWarning::FeePayerFundedBurn,
+                LinkWarning::MintRightAfterBurn { age_secs: 120 },
+                LinkWarning::NonStandardAmount(eth("0.123456")),
+            ]
+        );
+        assert_eq!(report.score(), 20);
+        assert!(report.enforce(false).is_ok());
+        assert!(report.enforce(true).is_err());
+
+        // Paying gas from the account that funded another burn links the coins
+        let report = analyze_mint(&plan(FEE_PAYER, RECIPIENT), &history);
+        assert_eq!(report.warnings, vec![LinkWarning::FeePayerFundedOtherBurns]);
+
+        let report = analyze_mint(&plan(FEE_PAYER, SOURCE), &history);
+        assert!(report.warnings.contains(&LinkWarning::RecipientFundedBurn));
+    }
+
+    #[test]
+    fn test_burn_from_a_mint_account_is_linked() {
+        let mut history = history(&[(OTHER_BURN, SOURCE)]);
+        history.mints.insert(
+            OTHER_BURN,
+            MintRecord {
+                fee_payer: FEE_PAYER,
+                recipient: RECIPIENT,
+            },
+        );
+
+        let report = analyze_burn(RECIPIENT, &[eth("1")], &history);
+        assert_eq!(report.warnings, vec![LinkWarning::SourceReceivedMints]);
+        let report = analyze_burn(FEE_PAYER, &[eth("1")], &history);
+        assert_eq!(report.warnings, vec![LinkWarning::SourcePaidMints]);
+        let report = analyze_burn(FRESH, &[eth("1"), eth("0.1")], &history);
+        assert_eq!(
+            report.warnings,
+            vec![LinkWarning::SplitPartsLinked { parts: 2 }]
+        );
+
+        let report = analyze_burn(SOURCE, &[eth("0.5")], &history);
+        assert_eq!(
+            report.warnings,
+            vec![
+                LinkWarning::NonStandardAmount(eth("0.5")),
+                LinkWarning::SourceFundedOtherBurns,
+            ]
+        );
+        assert_eq!(report.score(), 75);
+    }
+
+    #[test]
+    fn test_parts_of_a_split_burn_are_siblings() {
+        let mut history = WalletHistory::default();
+        for (burn_address, nonce) in [(OTHER_BURN, 4), (BURN, 5), (THIRD_BURN, 7)] {
+            history.burns.insert(
+                burn_address,
+                BurnRecord {
+                    source: SOURCE,
+                    nonce,
+                    block: Some(1),
+                },
+            );
+        }
+        assert_eq!(history.split_siblings(BURN), BTreeSet::from([OTHER_BURN]));
+        assert!(history.split_siblings(THIRD_BURN).is_empty());
+
+        // Minting another part to the same account puts the split back together
+        history.mints.insert(
+            OTHER_BURN,
+            MintRecord {
+                fee_payer: FRESH,
+                recipient: RECIPIENT,
+            },
+        );
+        let report = analyze_mint(&plan(FEE_PAYER, RECIPIENT), &history);
+        assert_eq!(report.warnings, vec![LinkWarning::SplitPartsMintedTogether]);
+
+        let report = analyze_mint(&plan(FEE_PAYER, FRESH), &history);
+        assert_eq!(report.warnings, vec![LinkWarning::SplitPartsMintedTogether]);
+        assert!(analyze_mint(&plan(FEE_PAYER, FEE_PAYER), &history)
+            .warnings
+            .is_empty());
+    }
+}
@@ -0,0 +1,413 @@
+//! Wallet schema versioning and migrations
+//!
+//! The wallet JSON (the plaintext file, or the content sealed in an encrypted
+//! container) carries a `version` field. Files written before the field
+//! existed are version 0. When a wallet is loaded, the migrations registered
+//! in `MIGRATIONS` are applied one after the other on the raw JSON until it
+//! reaches `WALLET_SCHEMA_VERSION`, and only then is it deserialized.
+//!
+//! The schema version is independent of the encrypted container version in
+//! `wallet_file`, which only describes the encryption envelope.
+//!
+//! To change the layout of `Wallet` or `Coin`, bump `WALLET_SCHEMA_VERSION`,
+//! append a migration from the previous version and add a fixture of the old
+//! layout to the tests.
+
+use serde_json::{json, Map, Value};
+
+use crate::WalletData;
+
+/// Current version of the wallet JSON layout
+pub const WALLET_SCHEMA_VERSION: u32 = 5;
+
+/// Function rewriting a wallet JSON object in place
+pub type MigrationFn = fn(&mut Map<String, Value>) -> Result<(), Box<dyn std::error::Error>>;
+
+/// One upgrade step of the wallet JSON layout
+pub struct Migration {
+    /// Version the migration upgrades from, it produces `from + 1`
+    pub from: u32,
+    /// What the migration changes
+    pub description: &'static str,
+    /// Rewrite the wallet JSON object in place
+    pub apply: MigrationFn,
+}
+
+/// Registered migrations, ordered by `from`
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "record the legacy derivation scheme of unversioned wallets",
+        apply: pin_derivation_scheme,
+    },
+    Migration {
+        from: 1,
+        description: "replace the coin `minted` flag with the lifecycle state",
+        apply: minted_flag_to_state,
+    },
+    Migration {
+        from: 2,
+        description: "record the burn address indexes used by coins",
+        apply: index_burn_addresses,
+    },
+    Migration {
+        from: 3,
+        description: "allow coins to carry the burn preimage of an imported note",
+        apply: allow_imported_preimages,
+    },
+    Migration {
+        from: 4,
+        description: "track burn transactions that are not confirmed yet",
+        apply: track_pending_burns,
+    },
+];
+
+/// Version 0 -> 1
+///
+/// Wallets written before hierarchical derivation have no `derivation` field
+/// and rely on the serde default. Writing it out keeps them on the legacy
+/// scheme even if the default ever changes.
+fn pin_derivation_scheme(
+    wallet: &mut Map<String, Value>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    wallet
+        .entry("derivation")
+        .or_insert_with(|| json!("legacy"));
+    Ok(())
+}
+
+/// Version 1 -> 2
+///
+/// Coins recorded whether their nullifier was spent in a `minted` flag before
+/// coins tracked their lifecycle. Minted coins become `minted`, the others
+/// keep the default `burned` state.
+fn minted_flag_to_state(wallet: &mut Map<String, Value>) -> Result<(), Box<dyn std::error::Error>> {
+    let coins = match wallet.get_mut("coins") {
+        Some(Value::Array(coins)) => coins,
+        Some(_) => return Err("Wallet field 'coins' is not a list".into()),
+        None => return Ok(()),
+    };
+
+    for coin in coins {
+        let coin = coin.as_object_mut().ok_or("Wallet coin is not an object")?;
+        let minted = match coin.remove("minted") {
+            Some(Value::Bool(minted)) => minted,
+            Some(_) => return Err("Coin field 'minted' is not a boolean".into()),
+            None => false,
+        };
+        if minted && !coin.contains_key("state") {
+            coin.insert("state".to_string(), json!("minted"));
+        }
+    }
+    Ok(())
+}
+
+/// Version 2 -> 3
+///
+/// Wallets used to find burn addresses by scanning a fixed range of indexes.
+/// The indexes of existing coins are recorded as used and the next burn
+/// address index starts after the highest of them.
+fn index_burn_addresses(wallet: &mut Map<String, Value>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut indexes = vec![];
+    if let Some(coins) = wallet.get("coins") {
+        let coins = coins
+            .as_array()
+            .ok_or("Wallet field 'coins' is not a list")?;
+        for coin in coins {
+            match coin.get("burn_index") {
+                None | Some(Value::Null) => {}
+                Some(index) => indexes.push(
+                    index
+                        .as_u64()
+                        .ok_or("Coin field 'burn_index' is not an index")?,
+                ),
+            }
+        }
+    }
+    indexes.sort_unstable();
+    indexes.dedup();
+
+    let next = indexes.last().map_or(0, |index| index + 1);
+    let records: Vec<Value> = indexes
+        .into_iter()
+        .map(|index| json!({ "index": index, "status": "used" }))
+        .collect();
+    wallet.insert("next_burn_index".to_string(), json!(next));
+    wallet.insert("burn_addresses".to_string(), Value::Array(records));
+    Ok(())
+}
+
+/// Version 3 -> 4
+///
+/// Coins imported from notes keep their burn preimage. Existing coins have
+/// none, so nothing is rewritten: the version only stops older DarkMint
+/// releases from loading the wallet and dropping imported preimages on save.
+fn allow_imported_preimages(
+    _wallet: &mut Map<String, Value>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Ok(())
+}
+
+/// Version 4 -> 5
+///
+/// Burns are tracked from the moment they are broadcast. Existing wallets have
+/// no pending burns, so nothing is rewritten: the version only stops older
+/// DarkMint releases from loading the wallet and dropping pending burns on
+/// save, which would leave their burn addresses reserved without a coin.
+fn track_pending_burns(_wallet: &mut Map<String, Value>) -> Result<(), Box<dyn std::error::Error>> {
+    Ok(())
+}
+
+/// Upgrade wallet JSON to the current schema version
+///
+/// # Arguments
+/// * `value` - Wallet JSON of any known version
+///
+/// # Returns
+/// * Result containing the version the wallet was stored with, or an error
+///   for malformed wallets and wallets written by a newer DarkMint
+pub fn migrate(value: &mut Value) -> Result<u32, Box<dyn std::error::Error>> {
+    let wallet = value
+        .as_object_mut()
+        .ok_or("Wallet content is not a JSON object")?;
+
+    let stored = match wallet.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or("Invalid wallet schema version")?,
+    };
+    if stored > WALLET_SCHEMA_VERSION {
+        return Err(format!(
+            "Wallet schema version {} is newer than this DarkMint supports ({}), upgrade DarkMint",
+            stored, WALLET_SCHEMA_VERSION
+        )
+        .into());
+    }
+
+    let mut version = stored;
+    while version < WALLET_SCHEMA_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or(format!(
+                "No migration from wallet schema version {}",
+                version
+            ))?;
+        (migration.apply)(wallet).map_err(|e| {
+            format!(
+                "Wallet migration from version {} ({}) failed: {}",
+                version, migration.description, e
+            )
+        })?;
+        version += 1;
+    }
+    wallet.insert("version".to_string(), json!(WALLET_SCHEMA_VERSION));
+
+    Ok(stored)
+}
+
+/// Parse wallet JSON of any known version
+///
+/// # Arguments
+/// * `content` - Wallet JSON
+///
+/// # Returns
+/// * Result containing the wallet data and whether it was migrated, or an error
+pub fn load_wallet_json(content: &[u8]) -> Result<(WalletData, bool), Box<dyn std::error::Error>> {
+    let mut value: Value = serde_json::from_slice(content)?;
+    let stored = migrate(&mut value)?;
+    let data: WalletData = serde_json::from_value(value)?;
+    Ok((data, stored < WALLET_SCHEMA_VERSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::burn_index::BurnAddressStatus;
+    use crate::derivation::DerivationScheme;
+    use crate::store::MemoryStore;
+    use crate::{wallet_file, CoinState, Wallet};
+    use alloy::primitives::U256;
+
+    /// Wallet as written by the first release: entropy and bare coins
+    const V0_BASELINE: &str = include_str!("../fixtures/wallets/v0_baseline.json");
+    /// Unversioned wallet with burn indexes and the `minted` flag
+    const V0_RECOVERY: &str = include_str!("../fixtures/wallets/v0_recovery.json");
+    /// Unversioned wallet with hierarchical derivation
+    const V0_HIERARCHICAL: &str = include_str!("../fixtures/wallets/v0_hierarchical.json");
+    /// Unversioned wallet with coin lifecycle states
+    const V0_LIFECYCLE: &str = include_str!("../fixtures/wallets/v0_lifecycle.json");
+    /// Wallet with coin lifecycle states, before burn address indexes
+    const V2: &str = include_str!("../fixtures/wallets/v2.json");
+    /// Wallet with burn address indexes, before imported notes
+    const V3: &str = include_str!("../fixtures/wallets/v3.json");
+    /// Wallet with imported notes, before pending burns
+    const V4: &str = include_str!("../fixtures/wallets/v4.json");
+    /// Current layout
+    const V5: &str = include_str!("../fixtures/wallets/v5.json");
+
+    fn load(content: &str) -> (WalletData, bool) {
+        load_wallet_json(content.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_migrations_cover_every_version() {
+        for (position, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from, position as u32);
+        }
+        assert_eq!(MIGRATIONS.len() as u32, WALLET_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_baseline() {
+        let (data, migrated) = load(V0_BASELINE);
+        assert!(migrated);
+        assert_eq!(data.derivation(), DerivationScheme::Legacy);
+
+        let coins = data.coins();
+        assert_eq!(coins.len(), 2);
+        assert_eq!(coins[0].amount, U256::from(1_000_000_000_000_000u64));
+        assert!(coins[1].encrypted);
+        assert!(coins.iter().all(|coin| coin.state == CoinState::Burned));
+        assert!(coins.iter().all(|coin| coin.burn_index.is_none()));
+
+        let mut wallet =
+            Wallet::open_with_store(MemoryStore::with_content(V0_BASELINE), None).unwrap();
+        wallet.set_chain_id(1);
+        assert!(wallet.derive_burn_address(0).is_ok());
+        // The upgraded wallet is saved back to the store
+        let saved: Value = serde_json::from_str(wallet.store().content().unwrap()).unwrap();
+        assert_eq!(saved["version"], json!(WALLET_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_migrate_minted_flag() {
+        let (data, migrated) = load(V0_RECOVERY);
+        assert!(migrated);
+        assert_eq!(data.derivation(), DerivationScheme::Legacy);
+
+        let coins = data.coins();
+        assert_eq!(coins[0].burn_index, Some(0));
+        assert_eq!(coins[0].state, CoinState::Minted);
+        assert_eq!(coins[1].burn_index, Some(1));
+        assert_eq!(coins[1].state, CoinState::Burned);
+    }
+
+    #[test]
+    fn test_migrate_keeps_hierarchical_scheme() {
+        let (data, migrated) = load(V0_HIERARCHICAL);
+        assert!(migrated);
+        assert_eq!(data.derivation(), DerivationScheme::Hierarchical);
+        assert_eq!(data.coins()[0].state, CoinState::Minted);
+    }
+
+    #[test]
+    fn test_migrate_lifecycle() {
+        let (data, migrated) = load(V0_LIFECYCLE);
+        assert!(migrated);
+
+        let coin = &data.coins()[0];
+        assert_eq!(coin.state, CoinState::Submitted);
+        assert_eq!(coin.proof_block, Some(1234));
+        assert_eq!(coin.zkverify_job_id.as_deref(), Some("job-42"));
+    }
+
+    #[test]
+    fn test_migrate_burn_indexes() {
+        let (data, migrated) = load(V2);
+        assert!(migrated);
+        assert_eq!(data.coins()[0].state, CoinState::Aggregated);
+        assert_eq!(data.next_burn_index, 3);
+        assert_eq!(data.burn_addresses.len(), 1);
+        assert_eq!(data.burn_addresses[0].index, 2);
+        assert_eq!(data.burn_addresses[0].status, BurnAddressStatus::Used);
+
+        // Coins without an index do not reserve any
+        let (data, _) = load(V0_BASELINE);
+        assert_eq!(data.next_burn_index, 0);
+        assert!(data.burn_addresses.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_burn_address_records() {
+        let (data, migrated) = load(V3);
+        assert!(migrated);
+        assert_eq!(data.coins()[0].state, CoinState::Aggregated);
+        assert!(data.coins()[0].burn_preimage.is_none());
+        assert_eq!(data.next_burn_index, 4);
+        assert_eq!(data.burn_addresses[1].label.as_deref(), Some("donation"));
+    }
+
+    #[test]
+    fn test_migrate_imported_preimages() {
+        let (data, migrated) = load(V4);
+        assert!(migrated);
+        assert_eq!(data.coins()[1].burn_preimage, Some(U256::from(0x1234)));
+        assert!(data.pending_burns.is_empty());
+    }
+
+    #[test]
+    fn test_current_version_is_not_migrated() {
+        let (data, migrated) = load(V5);
+        assert!(!migrated);
+        assert_eq!(data.coins()[0].state, CoinState::Aggregated);
+        assert_eq!(data.coins()[1].burn_preimage, Some(U256::from(0x1234)));
+        let pending = &data.pending_burns[0];
+        assert_eq!(pending.burn_index, 3);
+        assert_eq!(pending.max_fee_per_gas, 30_000_000_000);
+        assert_eq!(pending.max_priority_fee_per_gas, Some(1_000_000_000));
+        assert_eq!(pending.tx_hashes.len(), 2);
+
+        // Saving writes the current version
+        let saved = serde_json::to_value(&data).unwrap();
+        assert_eq!(saved["version"], json!(WALLET_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let mut value: Value = serde_json::from_str(V5).unwrap();
+        value["version"] = json!(WALLET_SCHEMA_VERSION + 1);
+        assert!(load_wallet_json(value.to_string().as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_open_migrates_wallet_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let plain = dir.path().join("plain.priv");
+        std::fs::write(&plain, V0_RECOVERY).unwrap();
+        let wallet = Wallet::open(&plain, None).unwrap();
+        assert_eq!(wallet.get_coins()[0].state, CoinState::Minted);
+        drop(wallet);
+        let saved: Value = serde_json::from_slice(&std::fs::read(&plain).unwrap()).unwrap();
+        assert_eq!(saved["version"], json!(WALLET_SCHEMA_VERSION));
+
+        // Encrypting the wallet leaves no plaintext backup behind
+        let wallet = Wallet::open(&plain, Some("pw")).unwrap();
+        assert!(wallet.is_encrypted());
+        drop(wallet);
+        assert!(wallet_file::is_encrypted(
+            &std::fs::read_to_string(&plain).unwrap()
+        ));
+        for n in 1..=wallet_file::WALLET_BACKUP_COUNT {
+            assert!(!wallet_file::backup_path(&plain, n).exists());
+        }
+
+        let sealed = dir.path().join("sealed.priv");
+        let key = wallet_file::KdfParams::with_costs(1024, 1, 1)
+            .derive_key("pw")
+            .unwrap();
+        std::fs::write(
+            &sealed,
+            wallet_file::encrypt(V0_RECOVERY.as_bytes(), &key).unwrap(),
+        )
+        .unwrap();
+        let wallet = Wallet::open(&sealed, Some("pw")).unwrap();
+        assert_eq!(wallet.get_coins()[0].state, CoinState::Minted);
+        drop(wallet);
+        let reopened = Wallet::open(&sealed, Some("pw")).unwrap();
+        assert_eq!(reopened.get_coins()[0].state, CoinState::Minted);
+    }
+}
@@ -1,37 +1,68 @@
-use ark_bn254::Fr;
-use ark_ff::PrimeField;
-
 use alloy::{
     primitives::{keccak256, Address, Bytes, B256, U256},
+    network::{EthereumWallet, TxSigner},
+    primitives::Signature,
+    eips::{BlockId, BlockNumberOrTag},
     providers::{Provider, ProviderBuilder},
     rpc::types::{Block, EIP1186AccountProofResponse},
-    signers::local::PrivateKeySigner,
+    sol,
+    sol_types::{SolCall, SolType},
 };
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use fibonacci_lib::PublicValuesStruct;
 
 use rlp::RlpStream;
 
-use crate::{Coin, Wallet, BurnAddress};
+use crate::network::ProofBlock;
+use crate::store::WalletStore;
+use crate::{BurnAddress, Coin, CoinState, Wallet};
+
+sol! {
+    interface IDarkMintMinter {
+        function mint(
+            address recipient,
+            uint256 amount,
+            uint256 nullifier,
+            bytes32[] memory publicInputHashes
+        ) external;
+    }
+}
+
+/// Gas limit of mint transactions
+pub const MINT_GAS_LIMIT: u64 = 500_000;
+
+/// Number of times a proof is regenerated when its block is reorged out
+pub const MAX_REPROVE_ATTEMPTS: u32 = 3;
 
 /// Context for minting operations
 /// 
 /// Contains all necessary parameters for converting burned ETH
 /// into privacy-preserving coins.
 #[derive(Debug)]
-pub struct MintContext {
+pub struct MintContext<T> {
     /// Source burn address to mint from
     pub src_burn_addr: Address,
     /// Destination address for the minting transaction
     pub dst_addr: Address,
     /// Whether the resulting coin should be encrypted
     pub encrypted: bool,
-    /// Private key signer for paying transaction fees
-    pub priv_fee_payer: PrivateKeySigner,
+    /// Signer of the account paying transaction fees
+    pub fee_payer: T,
+    /// Number of burn address indexes searched past the next unused one
+    pub gap_limit: u64,
+    /// Block the proof is generated against
+    pub proof_block: ProofBlock,
 }
 
-// Placeholder for block splitting logic
-fn get_block_splited_information(
-    block: &Block,
-) -> Result<(Bytes, B256, Bytes), Box<dyn std::error::Error>> {
+/// RLP encode a block header and check that it hashes to the block hash
+///
+/// # Arguments
+/// * `block` - Block whose header is encoded
+///
+/// # Returns
+/// * Result containing the header RLP, or an error if it does not hash to the block hash
+pub(crate) fn verified_header_rlp(block: &Block) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     // Count fields dynamically
     let mut field_count = 15;
     if block.header.base_fee_per_gas.is_some() {
@@ -133,24 +164,25 @@ fn get_block_splited_information(
         stream.append(&requests_hash.as_slice());
     }
 
-    let header_rlp = stream.out();
+    let header_rlp = stream.out().to_vec();
 
     // CORRECT: Direct keccak256 hash of RLP
     let computed_hash = keccak256(&header_rlp);
-    let block_hash = block.header.hash;
-
-    
-    if computed_hash.as_slice() != block_hash.as_slice() {
-        println!(
-            "Hash mismatch! Expected: {:?}, Got: {:?}",
-            block_hash.as_slice(),
-            computed_hash.as_slice()
-        );
-        return Err("Block header hash verification failed".into());
-    } else {
-        println!("Hash matches!");
+    if computed_hash != block.header.hash {
+        return Err(format!(
+            "Block header hash verification failed: block {} has hash {}, its header hashes to {}",
+            block.header.number, block.header.hash, computed_hash
+        )
+        .into());
     }
+    Ok(header_rlp)
+}
 
+// Placeholder for block splitting logic
+pub(crate) fn get_block_splited_information(
+    block: &Block,
+) -> Result<(Bytes, B256, Bytes), Box<dyn std::error::Error>> {
+    let header_rlp = verified_header_rlp(block)?;
 
     // Find state root position in RLP
     let state_root_bytes = block.header.state_root.as_slice();
@@ -169,14 +201,349 @@ fn get_block_splited_information(
     Ok((prefix, commit_top, postfix))
 }
 
+/// Encode the mint call of a proven coin
+///
+/// # Arguments
+/// * `recipient` - Address receiving the minted tokens
+/// * `amount` - Amount to mint in wei, the token has 18 decimals like ETH
+/// * `public_values` - Public values committed by the proof
+///
+/// # Returns
+/// * Result containing the calldata and the nullifier committed by the proof
+pub fn mint_calldata(
+    recipient: Address,
+    amount: U256,
+    public_values: &[u8],
+) -> Result<(Bytes, U256), Box<dyn std::error::Error>> {
+    // The nullifier is the value committed by the program, which is also
+    // what `wallet recover` looks up in the contract's `nullifiers` mapping
+    let decoded = <PublicValuesStruct as SolType>::abi_decode(public_values)?;
+    let nullifier = U256::from(decoded.nullifier);
+
+    let call = IDarkMintMinter::mintCall {
+        recipient,
+        amount,
+        nullifier,
+        publicInputHashes: public_values
+            .chunks(32)
+            .map(|chunk| {
+                let mut hash_32 = [0u8; 32];
+                hash_32[..chunk.len()].copy_from_slice(chunk);
+                B256::from(hash_32)
+            })
+            .collect(),
+    };
+    Ok((call.abi_encode().into(), nullifier))
+}
+
+/// Mint call of a mint transaction, with the public values it carries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedMint {
+    /// Address the tokens were minted to
+    pub recipient: Address,
+    /// Minted amount in wei
+    pub amount: U256,
+    /// Nullifier passed to the contract
+    pub nullifier: U256,
+    /// Burn address of the burn preimage committed by the proof
+    pub burn_address: Address,
+    /// Nullifier committed by the proof
+    pub proven_nullifier: U256,
+    /// Balance committed by the proof, the low 32 bits of the commitment when encrypted
+    pub encrypted_balance: u32,
+    /// Whether the proof committed an encrypted balance
+    pub encrypted: bool,
+}
+
+/// Decode the input of a mint transaction
+///
+/// The public values of the proof travel in `publicInputHashes` as 32-byte
+/// chunks, so they are reassembled and decoded as well.
+///
+/// # Arguments
+/// * `input` - Transaction input
+///
+/// # Returns
+/// * Result containing the decoded mint, or an error if the input is not a mint call
+pub fn decode_mint_call(input: &[u8]) -> Result<DecodedMint, Box<dyn std::error::Error>> {
+    let call = IDarkMintMinter::mintCall::abi_decode(input)
+        .map_err(|e| format!("Not a mint call: {}", e))?;
+    let public_values: Vec<u8> = call
+        .publicInputHashes
+        .iter()
+        .flat_map(|chunk| chunk.0)
+        .collect();
+    let decoded = <PublicValuesStruct as SolType>::abi_decode(&public_values)
+        .map_err(|e| format!("Mint call carries invalid public values: {}", e))?;
+
+    Ok(DecodedMint {
+        recipient: call.recipient,
+        amount: call.amount,
+        nullifier: call.nullifier,
+        burn_address: proven_burn_address(&decoded),
+        proven_nullifier: U256::from(decoded.nullifier),
+        encrypted_balance: decoded.encrypted_balance,
+        encrypted: decoded.encrypted,
+    })
+}
+
+/// Get the burn address of the burn preimage committed by a proof
+///
+/// # Arguments
+/// * `public_values` - Decoded public values of the proof
+///
+/// # Returns
+/// * The burn address the proof is for
+pub fn proven_burn_address(public_values: &PublicValuesStruct) -> Address {
+    let preimage = Fr::from_be_bytes_mod_order(&public_values.burn_preimage);
+    BurnAddress::from_preimage(preimage).address
+}
+
+/// Get the block a proof is generated against
+///
+/// # Arguments
+/// * `provider` - Provider of the burn's chain
+/// * `policy` - How deep the block must be
+///
+/// # Returns
+/// * Result containing the block or an error
+pub async fn select_proof_block<P: Provider>(
+    provider: &P,
+    policy: ProofBlock,
+) -> Result<Block, Box<dyn std::error::Error>> {
+    let tag = match policy {
+        ProofBlock::Confirmations(confirmations) => {
+            let head = provider.get_block_number().await?;
+            BlockNumberOrTag::Number(head.saturating_sub(confirmations.saturating_sub(1)))
+        }
+        ProofBlock::Safe => BlockNumberOrTag::Safe,
+        ProofBlock::Finalized => BlockNumberOrTag::Finalized,
+    };
+    provider
+        .get_block_by_number(tag)
+        .await?
+        .ok_or_else(|| format!("The node has no {} block", policy).into())
+}
+
+/// Select the proof block and fetch the account proof of a burn address in it
+///
+/// # Arguments
+/// * `provider` - Provider of the burn's chain
+/// * `address` - Burn address
+/// * `policy` - How deep the proof block must be
+///
+/// # Returns
+/// * Result containing the block and the account proof, or an error
+pub async fn fetch_proof_inputs<P: Provider>(
+    provider: &P,
+    address: Address,
+    policy: ProofBlock,
+) -> Result<(Block, EIP1186AccountProofResponse), Box<dyn std::error::Error>> {
+    let block = select_proof_block(provider, policy).await?;
+    // Pinned to the block hash, so the proof cannot come from a block that replaced it
+    let proof = provider
+        .get_proof(address, vec![])
+        .block_id(BlockId::hash(block.header.hash))
+        .await?;
+    if proof.balance.is_zero() {
+        return Err(format!(
+            "Burn address {} has no balance at block {} ({} proof block), wait until the burn reaches it",
+            address, block.header.number, policy
+        )
+        .into());
+    }
+    Ok((block, proof))
+}
+
+/// Check that a block is still part of the canonical chain
+///
+/// # Arguments
+/// * `provider` - Provider of the block's chain
+/// * `block` - Block a proof was generated against
+///
+/// # Returns
+/// * Result containing whether the chain still has the block at its height
+pub async fn is_canonical<P: Provider>(
+    provider: &P,
+    block: &Block,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let canonical = provider
+        .get_block_by_number(block.header.number.into())
+        .await?;
+    Ok(canonical.is_some_and(|canonical| canonical.header.hash == block.header.hash))
+}
+
+/// Move a coin being proven to a new proof block, after its block was reorged out
+///
+/// # Arguments
+/// * `wallet` - Wallet the coin belongs to
+/// * `provider` - Provider of the burn's chain
+/// * `burn_addr` - Burn address of the coin
+/// * `policy` - How deep the new proof block must be
+///
+/// # Returns
+/// * Result containing the new block, account proof and coin, or an error
+pub async fn refetch_proof_inputs<S: WalletStore, P: Provider>(
+    wallet: &mut Wallet<S>,
+    provider: &P,
+    burn_addr: &BurnAddress,
+    policy: ProofBlock,
+) -> Result<(Block, EIP1186AccountProofResponse, Coin), Box<dyn std::error::Error>> {
+    let (block, proof) = fetch_proof_inputs(provider, burn_addr.address, policy).await?;
+    get_block_splited_information(&block)?;
+    wallet.update_coin_for_burn_address(burn_addr.address, |coin| {
+        coin.amount = proof.balance;
+        coin.proof_block = Some(block.header.number);
+        Ok(())
+    })?;
+    let coin = wallet
+        .coin_for_burn_address(burn_addr.address)
+        .cloned()
+        .ok_or("Coin not found in wallet")?;
+    Ok((block, proof, coin))
+}
+
 // Helper function to remove leading zeros
 fn remove_leading_zeros(bytes: &[u8]) -> Vec<u8> {
     bytes.iter().skip_while(|&&x| x == 0).copied().collect()
 }
 
-pub async fn mint_cmd(
+/// Find a burn address of the wallet, or of a coin imported from a note
+///
+/// # Arguments
+/// * `wallet` - Wallet to search, with its chain id set
+/// * `address` - Burn address to find
+/// * `gap_limit` - Number of indexes searched past the next unused one
+///
+/// # Returns
+/// * Result containing the burn address index (`None` for imported coins) and
+///   the burn address, or an error if the wallet does not know it
+pub(crate) fn locate_burn_address<S: WalletStore>(
+    wallet: &mut Wallet<S>,
+    address: Address,
+    gap_limit: u64,
+) -> Result<(Option<u64>, BurnAddress), Box<dyn std::error::Error>> {
+    // Burn addresses of this wallet first, then coins imported from notes
+    match wallet.find_burn_address(address, gap_limit)? {
+        Some((index, burn_addr)) => Ok((Some(index), burn_addr)),
+        None => Ok((
+            None,
+            wallet.imported_burn_address(address)?.ok_or(format!(
+                "Burn address not found within {} addresses of the next unused index, try a larger gap limit",
+                gap_limit
+            ))?,
+        )),
+    }
+}
+
+/// Record that the coin of a burn address is being proven
+///
+/// # Arguments
+/// * `wallet` - Wallet the coin belongs to
+/// * `burn_index` - Index of the burn address, `None` for imported coins
+/// * `burn_addr` - Burn address
+/// * `amount` - Balance of the burn address at the proof block
+/// * `encrypted` - Whether the coin is minted in encrypted mode
+/// * `block_number` - Block the account proof is taken at
+///
+/// # Returns
+/// * Result containing the updated coin or an error
+pub(crate) fn start_proving<S: WalletStore>(
+    wallet: &mut Wallet<S>,
+    burn_index: Option<u64>,
+    burn_addr: &BurnAddress,
+    amount: U256,
+    encrypted: bool,
+    block_number: u64,
+) -> Result<Coin, Box<dyn std::error::Error>> {
+    if let Some(burn_index) = burn_index {
+        if wallet.settle_funded_burn(burn_addr.address)? {
+            // The burn was still pending, the funds show it confirmed
+        } else if wallet.coin_for_burn_address(burn_addr.address).is_some() {
+            // Already tracked, possibly imported from a note of another device
+        } else if wallet.coin_for_burn(burn_index).is_some() {
+            // Recorded before coins kept their burn address
+            wallet.update_coin(burn_index, |coin| {
+                coin.burn_address = Some(burn_addr.address);
+                Ok(())
+            })?;
+        } else {
+            // Burned outside this wallet: record the coin with its deterministic salt
+            let mut coin = wallet.create_coin_for_burn(burn_index, amount, encrypted)?;
+            coin.burn_address = Some(burn_addr.address);
+            wallet.add_coin(coin)?;
+            wallet.mark_burn_address_used(burn_index)?;
+        }
+    }
+    let nullifier = burn_addr.nullifier();
+    wallet.update_coin_for_burn_address(burn_addr.address, |coin| {
+        coin.transition(CoinState::Proving)?;
+        coin.amount = amount;
+        coin.encrypted = encrypted;
+        coin.proof_block = Some(block_number);
+        coin.nullifier = Some(nullifier);
+        Ok(())
+    })?;
+    wallet
+        .coin_for_burn_address(burn_addr.address)
+        .cloned()
+        .ok_or_else(|| "Coin not found in wallet".into())
+}
+
+/// Fetch everything proving a burn address needs, without recording anything
+///
+/// Read-only counterpart of `mint_cmd` for dry runs: the coin is the one the
+/// wallet would prove, but its state and the burn address are left as they are.
+///
+/// # Arguments
+/// * `wallet` - Wallet the burn address belongs to
+/// * `provider_url` - RPC provider URL
+/// * `src_burn_addr` - Burn address to prove
+/// * `encrypted` - Whether the coin would be minted in encrypted mode
+/// * `gap_limit` - Number of indexes searched past the next unused one
+/// * `proof_block` - Block the proof would be generated against
+///
+/// # Returns
+/// * Result containing the burn address, the proof block, the account proof and the coin
+pub async fn preview_mint<S: WalletStore>(
+    wallet: &mut Wallet<S>,
+    provider_url: &str,
+    src_burn_addr: Address,
+    encrypted: bool,
+    gap_limit: u64,
+    proof_block: ProofBlock,
+) -> Result<(BurnAddress, Block, EIP1186AccountProofResponse, Coin), Box<dyn std::error::Error>> {
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+
+    wallet.set_chain_id(provider.get_chain_id().await?);
+    let (burn_index, burn_addr) = locate_burn_address(wallet, src_burn_addr, gap_limit)?;
+
+    let (block, proof) = fetch_proof_inputs(&provider, burn_addr.address, proof_block).await?;
+    get_block_splited_information(&block)?;
+
+    // The coin as start_proving would record it
+    let known = wallet
+        .coin_for_burn_address(burn_addr.address)
+        .or_else(|| burn_index.and_then(|index| wallet.coin_for_burn(index)))
+        .cloned();
+    let mut coin = match known {
+        Some(coin) => coin,
+        None => wallet.create_coin_for_burn(
+            burn_index.ok_or("Coin not found in wallet")?,
+            proof.balance,
+            encrypted,
+        )?,
+    };
+    coin.amount = proof.balance;
+    coin.encrypted = encrypted;
+
+    Ok((burn_addr, block, proof, coin))
+}
+
+pub async fn mint_cmd<S, T>(
+    wallet: &mut Wallet<S>,
     provider_url: &str,
-    context: MintContext,
+    context: MintContext<T>,
 ) -> Result<
     (
         BurnAddress,
@@ -188,39 +555,31 @@ pub async fn mint_cmd(
         Bytes,
     ),
     Box<dyn std::error::Error>,
-> {
+>
+where
+    S: WalletStore,
+    T: TxSigner<Signature> + Send + Sync + 'static,
+{
     let provider = ProviderBuilder::new()
-        .wallet(context.priv_fee_payer.clone())
+        .wallet(EthereumWallet::from(context.fee_payer))
         .connect(provider_url)
         .await?;
 
-    let mut wallet = Wallet::open_or_create()?;
-    let mut burn_addr: Option<BurnAddress> = None;
-    let mut amount = U256::ZERO;
-
-    for i in 0..10 {
-        let b_addr = wallet.derive_burn_address(i)?;
-        if context.src_burn_addr == b_addr.address {
-            amount = provider.get_balance(b_addr.address).await?;
-            burn_addr = Some(b_addr);
-            break;
-        }
-    }
-
-    let burn_addr = burn_addr.ok_or("Burn address not found!")?;
-
-    let block_number = provider.get_block_number().await?;
-    let amount_fr = Fr::from_le_bytes_mod_order(&amount.to_be_bytes::<32>());
-    let coin = wallet.create_coin(amount_fr, context.encrypted);
-    wallet.add_coin(coin.clone())?;
-    let block = provider
-        .get_block_by_number(block_number.into())
-        .await?
-        .ok_or("Block not found")?;
-    let proof = provider
-        .get_proof(burn_addr.address, vec![])
-        .block_id(block_number.into())
-        .await?;
+    wallet.set_chain_id(provider.get_chain_id().await?);
+    let (burn_index, burn_addr) =
+        locate_burn_address(wallet, context.src_burn_addr, context.gap_limit)?;
+
+    // The amount is the balance in the proven state, not at the head
+    let (block, proof) =
+        fetch_proof_inputs(&provider, burn_addr.address, context.proof_block).await?;
+    let coin = start_proving(
+        wallet,
+        burn_index,
+        &burn_addr,
+        proof.balance,
+        context.encrypted,
+        block.header.number,
+    )?;
 
     let (prefix, state_root, postfix) = get_block_splited_information(&block)?;
 
@@ -0,0 +1,225 @@
+//! BIP-39 mnemonic backup of wallet entropy
+//!
+//! The 32 bytes of wallet entropy are encoded as a 24-word BIP-39 mnemonic.
+//! An optional passphrase masks the entropy before encoding: the mnemonic holds
+//! `entropy XOR mask(passphrase)`, so the same words restore a different wallet
+//! when the passphrase is wrong. Without a passphrase the mnemonic is the plain
+//! BIP-39 encoding of the entropy.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use bip39::Mnemonic;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::derivation::DerivationScheme;
+use crate::store::WalletStore;
+use crate::{Wallet, NOTE_SIZE};
+
+/// Number of words in a wallet mnemonic (256 bits of entropy)
+pub const MNEMONIC_WORDS: usize = 24;
+
+/// Domain separation salt for the passphrase mask
+const PASSPHRASE_SALT: &[u8] = b"darkmint/mnemonic-passphrase/v1";
+
+/// Derive the mask applied to the entropy for a passphrase
+///
+/// # Arguments
+/// * `passphrase` - Mnemonic passphrase (empty for no passphrase)
+///
+/// # Returns
+/// * Result containing the mask (all zeros for an empty passphrase) or an error
+fn passphrase_mask(
+    passphrase: &str,
+) -> Result<Zeroizing<[u8; NOTE_SIZE]>, Box<dyn std::error::Error>> {
+    let mut mask = Zeroizing::new([0u8; NOTE_SIZE]);
+    if passphrase.is_empty() {
+        return Ok(mask);
+    }
+
+    let params = Params::new(64 * 1024, 3, 1, Some(NOTE_SIZE))
+        .map_err(|e| format!("Invalid KDF parameters: {}", e))?;
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), PASSPHRASE_SALT, mask.as_mut())
+        .map_err(|e| format!("Passphrase derivation failed: {}", e))?;
+    Ok(mask)
+}
+
+/// Encode wallet entropy as a 24-word mnemonic
+///
+/// # Arguments
+/// * `entropy` - Wallet entropy
+/// * `passphrase` - Optional passphrase (empty for none)
+///
+/// # Returns
+/// * Result containing the space-separated mnemonic or an error
+pub fn entropy_to_mnemonic(
+    entropy: &[u8; NOTE_SIZE],
+    passphrase: &str,
+) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    // Masked in place, so the masked entropy is wiped on every return path
+    let mut masked = passphrase_mask(passphrase)?;
+    for (byte, entropy_byte) in masked.iter_mut().zip(entropy.iter()) {
+        *byte ^= entropy_byte;
+    }
+
+    let mnemonic = Mnemonic::from_entropy(masked.as_ref())?;
+    Ok(Zeroizing::new(mnemonic.to_string()))
+}
+
+/// Decode a 24-word mnemonic back into wallet entropy
+///
+/// # Arguments
+/// * `phrase` - Space-separated mnemonic words
+/// * `passphrase` - Passphrase used at backup time (empty for none)
+///
+/// # Returns
+/// * Result containing the wallet entropy or an error
+pub fn mnemonic_to_entropy(
+    phrase: &str,
+    passphrase: &str,
+) -> Result<Zeroizing<[u8; NOTE_SIZE]>, Box<dyn std::error::Error>> {
+    let mnemonic = Mnemonic::parse(phrase.trim().to_lowercase())?;
+    if mnemonic.word_count() != MNEMONIC_WORDS {
+        return Err(format!(
+            "Expected a {}-word mnemonic, got {} words",
+            MNEMONIC_WORDS,
+            mnemonic.word_count()
+        )
+        .into());
+    }
+
+    let mut decoded = Zeroizing::new(mnemonic.to_entropy());
+    let mut entropy = passphrase_mask(passphrase)?;
+    for (byte, decoded_byte) in entropy.iter_mut().zip(decoded.iter()) {
+        *byte ^= decoded_byte;
+    }
+    decoded.zeroize();
+    Ok(entropy)
+}
+
+impl<S: WalletStore> Wallet<S> {
+    /// Export the wallet entropy as a 24-word mnemonic
+    ///
+    /// # Arguments
+    /// * `passphrase` - Optional passphrase (empty for none)
+    ///
+    /// # Returns
+    /// * Result containing the mnemonic or an error
+    pub fn export_mnemonic(
+        &self,
+        passphrase: &str,
+    ) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+
+        let mut entropy = Zeroizing::new([0u8; NOTE_SIZE]);
+        hex::decode_to_slice(&self.data.entropy, entropy.as_mut())?;
+        entropy_to_mnemonic(&entropy, passphrase)
+    }
+
+    /// Restore a wallet from a mnemonic and save it to `store`
+    ///
+    /// Refuses to overwrite an existing wallet. The restored wallet has no
+    /// coins; they can be rediscovered from the chain. The mnemonic does not
+    /// record the derivation scheme, so wallets created before hierarchical
+    /// derivation have to be restored with `DerivationScheme::Legacy`.
+    ///
+    /// # Arguments
+    /// * `store` - Empty store for the restored wallet
+    /// * `phrase` - Space-separated mnemonic words
+    /// * `passphrase` - Passphrase used at backup time (empty for none)
+    /// * `derivation` - Burn key derivation scheme of the original wallet
+    /// * `password` - Optional password used to encrypt the restored wallet
+    ///
+    /// # Returns
+    /// * Result containing the restored wallet or an error
+    pub fn restore_from_mnemonic(
+        store: S,
+        phrase: &str,
+        passphrase: &str,
+        derivation: DerivationScheme,
+        password: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if store.exists()? {
+            return Err(format!(
+                "Wallet '{}' already exists, move it away before restoring",
+                store.location()
+            )
+            .into());
+        }
+
+        let entropy = mnemonic_to_entropy(phrase, passphrase)?;
+        Self::create_from_entropy(store, &entropy, derivation, password)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[test]
+    fn test_mnemonic_roundtrip_without_passphrase() {
+        let entropy = [7u8; NOTE_SIZE];
+        let phrase = entropy_to_mnemonic(&entropy, "").unwrap();
+
+        assert_eq!(phrase.split_whitespace().count(), MNEMONIC_WORDS);
+        assert_eq!(*mnemonic_to_entropy(&phrase, "").unwrap(), entropy);
+    }
+
+    #[test]
+    fn test_mnemonic_matches_bip39_vector() {
+        // BIP-39 reference vector for 256 bits of 0x00
+        let phrase = entropy_to_mnemonic(&[0u8; NOTE_SIZE], "").unwrap();
+        assert_eq!(
+            phrase.as_str(),
+            "abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon abandon abandon abandon abandon abandon abandon art"
+        );
+    }
+
+    #[test]
+    fn test_mnemonic_roundtrip_with_passphrase() {
+        let entropy = [42u8; NOTE_SIZE];
+        let phrase = entropy_to_mnemonic(&entropy, "treasury").unwrap();
+
+        assert_eq!(*mnemonic_to_entropy(&phrase, "treasury").unwrap(), entropy);
+        assert_ne!(*mnemonic_to_entropy(&phrase, "").unwrap(), entropy);
+        assert_ne!(*mnemonic_to_entropy(&phrase, "other").unwrap(), entropy);
+    }
+
+    #[test]
+    fn test_restored_wallet_derives_same_addresses() {
+        let mut original = Wallet::from_entropy(&[9u8; NOTE_SIZE], DerivationScheme::Hierarchical);
+        original.set_chain_id(1);
+        let phrase = original.export_mnemonic("pass").unwrap();
+        let mut restored = Wallet::restore_from_mnemonic(
+            MemoryStore::new(),
+            &phrase,
+            "pass",
+            DerivationScheme::Hierarchical,
+            None,
+        )
+        .unwrap();
+        restored.set_chain_id(1);
+
+        for index in 0..3 {
+            assert_eq!(
+                original.derive_burn_address(index).unwrap().address,
+                restored.derive_burn_address(index).unwrap().address
+            );
+        }
+    }
+
+    #[test]
+    fn test_mnemonic_rejects_short_phrase() {
+        let short = "abandon abandon abandon abandon abandon abandon \
+                     abandon abandon abandon abandon abandon about";
+        assert!(mnemonic_to_entropy(short, "").is_err());
+    }
+
+    #[test]
+    fn test_mnemonic_rejects_bad_checksum() {
+        let bad = "abandon ".repeat(MNEMONIC_WORDS);
+        assert!(mnemonic_to_entropy(&bad, "").is_err());
+    }
+}
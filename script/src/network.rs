@@ -0,0 +1,390 @@
+//! Network profiles
+//!
+//! Everything that differs between deployments (RPC endpoints, chain id,
+//! DarkMint contract, zkVerify domain, relayer and confirmation depth) lives in
+//! a named network profile. Profiles are read from
+//! `<data dir>/darkmint/networks.toml`:
+//!
+//! ```toml
+//! default = "local"
+//!
+//! [networks.local]
+//! rpc_urls = ["http://127.0.0.1:8545"]
+//! chain_id = 31337
+//! contract_address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+//! domain_id = 0
+//! relayer_url = "http://localhost:3001/verify-proof"
+//! confirmations = 1
+//! proof_block = "finalized"
+//! ```
+//!
+//! `proof_block` picks the block mint proofs are generated against: `latest`,
+//! a number of confirmations, or the node's `safe` or `finalized` block. It
+//! defaults to the block `confirmations` deep.
+//!
+//! The built-in `horizen-testnet` profile is always available and can be
+//! overridden by a profile of the same name. The profile to use is picked in
+//! this order: `--network` (passed around as `$DARKMINT_NETWORK`), the
+//! `default` of the config file, then `horizen-testnet`.
+
+use alloy::primitives::Address;
+use alloy::providers::{Provider, ProviderBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::wallet_location::data_dir;
+use crate::DARKMINT_CONTRACT_ADDRESS;
+
+/// Environment variable the CLI uses to pass the selected network name around
+pub const NETWORK_ENV: &str = "DARKMINT_NETWORK";
+
+/// Name of the built-in network profile
+pub const DEFAULT_NETWORK: &str = "horizen-testnet";
+
+const NETWORKS_FILENAME: &str = "networks.toml";
+
+fn default_confirmations() -> u64 {
+    1
+}
+
+/// Block a mint proof is generated against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ProofBlock {
+    /// The block with this many confirmations, 1 being the head
+    Confirmations(u64),
+    /// The block the node considers safe from reorgs
+    Safe,
+    /// The block the node considers finalized
+    Finalized,
+}
+
+impl FromStr for ProofBlock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "latest" => Ok(ProofBlock::Confirmations(1)),
+            "safe" => Ok(ProofBlock::Safe),
+            "finalized" => Ok(ProofBlock::Finalized),
+            other => match other.parse::<u64>() {
+                Ok(0) => Err("A proof block needs at least 1 confirmation".to_string()),
+                Ok(confirmations) => Ok(ProofBlock::Confirmations(confirmations)),
+                Err(_) => Err(format!(
+                    "Invalid proof block '{}', use latest, safe, finalized or a number of confirmations",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ProofBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofBlock::Confirmations(1) => write!(f, "latest"),
+            ProofBlock::Confirmations(confirmations) => write!(f, "{}", confirmations),
+            ProofBlock::Safe => write!(f, "safe"),
+            ProofBlock::Finalized => write!(f, "finalized"),
+        }
+    }
+}
+
+impl TryFrom<String> for ProofBlock {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ProofBlock> for String {
+    fn from(value: ProofBlock) -> Self {
+        value.to_string()
+    }
+}
+
+/// Settings of one network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkProfile {
+    /// Profile name, the key of the profile in the config file
+    #[serde(skip)]
+    pub name: String,
+    /// RPC endpoints, tried in order
+    pub rpc_urls: Vec<String>,
+    /// Chain id the RPC endpoints must serve, not checked when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    /// Address of the deployed DarkMint contract
+    pub contract_address: Address,
+    /// zkVerify aggregation domain id
+    pub domain_id: u64,
+    /// Endpoint proofs are submitted to for verification and aggregation
+    pub relayer_url: String,
+    /// Number of blocks a transaction needs before it is considered final
+    #[serde(default = "default_confirmations")]
+    pub confirmations: u64,
+    /// Block mint proofs are generated against, `confirmations` deep when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_block: Option<ProofBlock>,
+    /// Block the DarkMint contract was deployed in, where past mints are indexed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment_block: Option<u64>,
+}
+
+impl NetworkProfile {
+    /// Get the built-in Horizen testnet profile
+    pub fn horizen_testnet() -> Self {
+        NetworkProfile {
+            name: DEFAULT_NETWORK.to_string(),
+            rpc_urls: vec!["https://horizen-rpc-testnet.appchain.base.org/".to_string()],
+            chain_id: None,
+            contract_address: DARKMINT_CONTRACT_ADDRESS,
+            domain_id: 113,
+            relayer_url: "http://localhost:3001/verify-proof".to_string(),
+            confirmations: default_confirmations(),
+            proof_block: None,
+            deployment_block: None,
+        }
+    }
+
+    /// Get the block mint proofs are generated against
+    ///
+    /// # Returns
+    /// * The configured policy, or the block `confirmations` deep
+    pub fn proof_block_policy(&self) -> ProofBlock {
+        self.proof_block
+            .unwrap_or(ProofBlock::Confirmations(self.confirmations))
+    }
+
+    /// Load the profile selected with `$DARKMINT_NETWORK` or the config default
+    ///
+    /// # Returns
+    /// * Result containing the profile, or an error for unknown names and bad config files
+    pub fn selected() -> Result<Self, Box<dyn std::error::Error>> {
+        let name = env::var(NETWORK_ENV).ok();
+        NetworkConfig::load()?.profile(name.as_deref())
+    }
+
+    /// Check that the profile is usable
+    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.rpc_urls.is_empty() {
+            return Err(format!("Network '{}' has no rpc_urls", self.name).into());
+        }
+        if self.confirmations == 0 {
+            return Err(format!("Network '{}' needs at least 1 confirmation", self.name).into());
+        }
+        Ok(())
+    }
+
+    /// Pick the RPC endpoint to use
+    ///
+    /// Endpoints are tried in order until one answers. When the profile has a
+    /// chain id, an endpoint serving another chain is an error rather than a
+    /// fallback.
+    ///
+    /// # Arguments
+    /// * `override_url` - Endpoint given on the command line, used instead of the profile's
+    ///
+    /// # Returns
+    /// * Result containing the endpoint URL, or an error if none is reachable
+    pub async fn rpc_url(
+        &self,
+        override_url: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let candidates: Vec<&str> = match override_url {
+            Some(url) => vec![url],
+            None => self.rpc_urls.iter().map(String::as_str).collect(),
+        };
+
+        let mut last_error = None;
+        for url in candidates {
+            let chain_id = match ProviderBuilder::new().connect(url).await {
+                Ok(provider) => provider.get_chain_id().await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match chain_id {
+                Ok(chain_id) => {
+                    if let Some(expected) = self.chain_id.filter(|&id| id != chain_id) {
+                        return Err(format!(
+                            "{} serves chain {}, but network '{}' is chain {}",
+                            url, chain_id, self.name, expected
+                        )
+                        .into());
+                    }
+                    return Ok(url.to_string());
+                }
+                Err(e) => last_error = Some(format!("{}: {}", url, e)),
+            }
+        }
+        Err(format!(
+            "No RPC endpoint of network '{}' is reachable ({})",
+            self.name,
+            last_error.unwrap_or_default()
+        )
+        .into())
+    }
+}
+
+/// Network profiles from the config file and the built-in ones
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Profile used when none is selected
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Profiles by name
+    #[serde(default)]
+    pub networks: BTreeMap<String, NetworkProfile>,
+}
+
+impl NetworkConfig {
+    /// Get the path of the network config file
+    pub fn path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(data_dir()?.join(NETWORKS_FILENAME))
+    }
+
+    /// Load the config file, or only the built-in profiles when there is none
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Self::from_toml("");
+        }
+        let content = fs::read_to_string(&path)?;
+        Self::from_toml(&content).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Parse a network config and add the built-in profiles
+    ///
+    /// # Arguments
+    /// * `content` - TOML config
+    ///
+    /// # Returns
+    /// * Result containing the config, or an error for invalid TOML or profiles
+    pub fn from_toml(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config: NetworkConfig = toml::from_str(content)?;
+        config
+            .networks
+            .entry(DEFAULT_NETWORK.to_string())
+            .or_insert_with(NetworkProfile::horizen_testnet);
+        for (name, profile) in config.networks.iter_mut() {
+            profile.name = name.clone();
+            profile.validate()?;
+        }
+        Ok(config)
+    }
+
+    /// Get a profile by name
+    ///
+    /// # Arguments
+    /// * `name` - Profile name, falls back to the config default and then `horizen-testnet`
+    ///
+    /// # Returns
+    /// * Result containing the profile, or an error for unknown names
+    pub fn profile(
+        &self,
+        name: Option<&str>,
+    ) -> Result<NetworkProfile, Box<dyn std::error::Error>> {
+        let name = name.or(self.default.as_deref()).unwrap_or(DEFAULT_NETWORK);
+        self.networks.get(name).cloned().ok_or_else(|| {
+            let known: Vec<&str> = self.networks.keys().map(String::as_str).collect();
+            format!("Unknown network '{}' (known: {})", name, known.join(", ")).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default = "local"
+
+[networks.local]
+rpc_urls = ["http://127.0.0.1:8545", "http://127.0.0.1:8546"]
+chain_id = 31337
+contract_address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+domain_id = 0
+relayer_url = "http://localhost:3001/verify-proof"
+confirmations = 3
+proof_block = "finalized"
+"#;
+
+    #[test]
+    fn test_profiles_are_selected_by_name_and_default() {
+        let config = NetworkConfig::from_toml(CONFIG).unwrap();
+
+        let local = config.profile(None).unwrap();
+        assert_eq!(local.name, "local");
+        assert_eq!(local.chain_id, Some(31337));
+        assert_eq!(local.rpc_urls.len(), 2);
+        assert_eq!(local.confirmations, 3);
+        assert_eq!(local.proof_block_policy(), ProofBlock::Finalized);
+
+        let builtin = config.profile(Some(DEFAULT_NETWORK)).unwrap();
+        assert_eq!(builtin, NetworkProfile::horizen_testnet());
+        assert_eq!(builtin.contract_address, DARKMINT_CONTRACT_ADDRESS);
+
+        let error = config.profile(Some("mainnet")).unwrap_err().to_string();
+        assert!(error.contains("horizen-testnet, local"));
+    }
+
+    #[test]
+    fn test_empty_config_uses_builtin_profile() {
+        let config = NetworkConfig::from_toml("").unwrap();
+        assert_eq!(config.profile(None).unwrap().domain_id, 113);
+    }
+
+    #[test]
+    fn test_invalid_profiles_are_rejected() {
+        let override_builtin = r#"
+[networks.horizen-testnet]
+rpc_urls = ["http://10.0.0.1:8545"]
+contract_address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+domain_id = 7
+relayer_url = "http://relayer"
+"#;
+        let config = NetworkConfig::from_toml(override_builtin).unwrap();
+        let profile = config.profile(None).unwrap();
+        assert_eq!(profile.domain_id, 7);
+        assert_eq!(profile.confirmations, 1);
+        assert_eq!(profile.proof_block_policy(), ProofBlock::Confirmations(1));
+
+        let no_urls = override_builtin.replace(r#"["http://10.0.0.1:8545"]"#, "[]");
+        assert!(NetworkConfig::from_toml(&no_urls).is_err());
+
+        let missing_domain = override_builtin.replace("domain_id = 7\n", "");
+        assert!(NetworkConfig::from_toml(&missing_domain).is_err());
+
+        let typo = format!("{}confirmation = 2\n", override_builtin);
+        assert!(NetworkConfig::from_toml(&typo).is_err());
+
+        let bad_policy = format!("{}proof_block = \"pending\"\n", override_builtin);
+        assert!(NetworkConfig::from_toml(&bad_policy).is_err());
+    }
+
+    #[test]
+    fn test_proof_block_parsing() {
+        assert_eq!("latest".parse(), Ok(ProofBlock::Confirmations(1)));
+        assert_eq!("12".parse(), Ok(ProofBlock::Confirmations(12)));
+        assert_eq!("Safe".parse(), Ok(ProofBlock::Safe));
+        assert_eq!("finalized".parse(), Ok(ProofBlock::Finalized));
+        assert!("0".parse::<ProofBlock>().is_err());
+        assert!("pending".parse::<ProofBlock>().is_err());
+
+        for policy in [
+            ProofBlock::Confirmations(1),
+            ProofBlock::Confirmations(12),
+            ProofBlock::Safe,
+            ProofBlock::Finalized,
+        ] {
+            assert_eq!(policy.to_string().parse(), Ok(policy));
+        }
+    }
+}
@@ -0,0 +1,574 @@
+//! Portable coin notes
+//!
+//! A note hands a coin to another wallet or device as a single line of text:
+//! bech32m with the `dmnote` human-readable part, so typos are caught by the
+//! checksum and the prefix tells what the string is.
+//!
+//! The payload starts with a format version and a flags byte:
+//!
+//! ```text
+//! version (1) | flags (1) | chain id (8) | amount (32) | salt (32) | [burn preimage (32)] | [burn address (20)]
+//! ```
+//!
+//! The chain id is the chain the coin was burned on. Importing a note into a
+//! wallet connected to another chain is refused, and so is minting the coin
+//! of an imported note on another chain.
+//!
+//! A note for a coin that is not minted yet carries the burn address preimage,
+//! which is what a mint proof is generated from: whoever holds the note can
+//! mint the coin. A note for a minted coin only carries its opening (amount
+//! and salt).
+//!
+//! A note can be sealed to the recipient's viewing key. The plain payload is
+//! then encrypted as in `viewing_key`, and the outer payload is the version,
+//! the `SEALED` flag and the encrypted note.
+//!
+//! The uppercase form of a note is equally valid and fits the alphanumeric
+//! mode of QR codes, which makes the codes noticeably smaller.
+
+use alloy::primitives::{Address, U256};
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use bech32::{primitives::decode::CheckedHrpstring, Bech32m, Hrp};
+use qrcode::{render::unicode, QrCode};
+use std::fmt;
+use zeroize::Zeroizing;
+
+use crate::store::WalletStore;
+use crate::viewing_key::{self, EncryptedNote, ViewingKey};
+use crate::{BurnAddress, Coin, CoinState, Wallet};
+
+/// Human-readable part of encoded notes
+pub const NOTE_HRP: &str = "dmnote";
+
+/// Current version of the note payload
+pub const NOTE_FORMAT_VERSION: u8 = 2;
+
+/// HKDF info string of the key sealing a note to a viewing key
+const SEALED_NOTE_KDF_INFO: &[u8] = b"darkmint/dmnote/v1";
+
+/// The coin was minted in encrypted mode
+const FLAG_ENCRYPTED: u8 = 0x01;
+/// The payload carries the burn preimage
+const FLAG_PREIMAGE: u8 = 0x02;
+/// The payload carries the burn address
+const FLAG_ADDRESS: u8 = 0x04;
+/// The payload is sealed to a viewing key
+const FLAG_SEALED: u8 = 0x80;
+
+/// Coin handed over in a note
+#[derive(Clone, PartialEq, Eq)]
+pub struct Note {
+    /// Chain the coin was burned on
+    pub chain_id: u64,
+    /// Coin amount in wei
+    pub amount: U256,
+    /// Salt of the coin commitment
+    pub salt: U256,
+    /// Whether the coin is minted in encrypted mode
+    pub encrypted: bool,
+    /// Burn address preimage, for coins that are not minted yet
+    pub burn_preimage: Option<U256>,
+    /// Burn address the ETH was sent to
+    pub burn_address: Option<Address>,
+}
+
+impl fmt::Debug for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Note")
+            .field("chain_id", &self.chain_id)
+            .field("amount", &self.amount)
+            .field("encrypted", &self.encrypted)
+            .field("has_burn_preimage", &self.burn_preimage.is_some())
+            .field("burn_address", &self.burn_address)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What importing a note changed in the wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteImport {
+    /// The coin was new and was added
+    Added,
+    /// The wallet had the coin and learned its burn address or preimage
+    Updated,
+    /// The wallet already knew everything in the note
+    Known,
+}
+
+/// Convert a burn preimage to the field element it encodes
+fn preimage_to_field(preimage: U256) -> Result<Fr, Box<dyn std::error::Error>> {
+    let bytes = preimage.to_be_bytes::<32>();
+    let field = Fr::from_be_bytes_mod_order(&bytes);
+    if field.into_bigint().to_bytes_be() != bytes {
+        return Err("Burn preimage is not a field element".into());
+    }
+    Ok(field)
+}
+
+fn field_to_preimage(field: Fr) -> U256 {
+    U256::from_be_slice(&field.into_bigint().to_bytes_be())
+}
+
+impl Note {
+    /// Serialize the plain payload
+    fn to_payload(&self) -> Zeroizing<Vec<u8>> {
+        let mut flags = 0;
+        if self.encrypted {
+            flags |= FLAG_ENCRYPTED;
+        }
+        if self.burn_preimage.is_some() {
+            flags |= FLAG_PREIMAGE;
+        }
+        if self.burn_address.is_some() {
+            flags |= FLAG_ADDRESS;
+        }
+
+        let mut payload = Zeroizing::new(vec![NOTE_FORMAT_VERSION, flags]);
+        payload.extend_from_slice(&self.chain_id.to_be_bytes());
+        payload.extend_from_slice(&self.amount.to_be_bytes::<32>());
+        payload.extend_from_slice(&self.salt.to_be_bytes::<32>());
+        if let Some(preimage) = self.burn_preimage {
+            payload.extend_from_slice(&preimage.to_be_bytes::<32>());
+        }
+        if let Some(address) = self.burn_address {
+            payload.extend_from_slice(address.as_slice());
+        }
+        payload
+    }
+
+    /// Parse a plain payload
+    fn from_payload(payload: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let flags = payload[1];
+        if flags & !(FLAG_ENCRYPTED | FLAG_PREIMAGE | FLAG_ADDRESS) != 0 {
+            return Err("Note has unknown flags".into());
+        }
+
+        let mut expected = 2 + 8 + 32 + 32;
+        if flags & FLAG_PREIMAGE != 0 {
+            expected += 32;
+        }
+        if flags & FLAG_ADDRESS != 0 {
+            expected += 20;
+        }
+        if payload.len() != expected {
+            return Err("Malformed note".into());
+        }
+
+        let mut rest = &payload[2..];
+        let mut take = |len: usize| {
+            let (field, tail) = rest.split_at(len);
+            rest = tail;
+            field
+        };
+        let chain_id = u64::from_be_bytes(take(8).try_into()?);
+        let amount = U256::from_be_slice(take(32));
+        let salt = U256::from_be_slice(take(32));
+        let burn_preimage = (flags & FLAG_PREIMAGE != 0).then(|| U256::from_be_slice(take(32)));
+        let burn_address = (flags & FLAG_ADDRESS != 0).then(|| Address::from_slice(take(20)));
+
+        if let Some(preimage) = burn_preimage {
+            let derived = BurnAddress::from_preimage(preimage_to_field(preimage)?).address;
+            if burn_address.is_some_and(|address| address != derived) {
+                return Err("Note burn address does not match its preimage".into());
+            }
+        }
+
+        Ok(Self {
+            chain_id,
+            amount,
+            salt,
+            encrypted: flags & FLAG_ENCRYPTED != 0,
+            burn_preimage,
+            burn_address,
+        })
+    }
+
+    /// Encode the note as a `dmnote` string
+    ///
+    /// # Returns
+    /// * Result containing the lowercase bech32m note or an error
+    pub fn encode(&self) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+        Ok(Zeroizing::new(bech32::encode::<Bech32m>(
+            Hrp::parse(NOTE_HRP)?,
+            &self.to_payload(),
+        )?))
+    }
+
+    /// Encode the note sealed to a viewing key
+    ///
+    /// # Arguments
+    /// * `recipient` - Public key of the recipient's viewing key
+    ///
+    /// # Returns
+    /// * Result containing the lowercase bech32m note or an error
+    pub fn encode_sealed(
+        &self,
+        recipient: &[u8; 32],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let sealed = viewing_key::seal(&self.to_payload(), recipient, SEALED_NOTE_KDF_INFO)?;
+        let payload = [&[NOTE_FORMAT_VERSION, FLAG_SEALED][..], &sealed.to_bytes()].concat();
+        Ok(bech32::encode::<Bech32m>(Hrp::parse(NOTE_HRP)?, &payload)?)
+    }
+
+    /// Decode a `dmnote` string, lowercase or uppercase
+    ///
+    /// # Arguments
+    /// * `text` - Encoded note
+    /// * `viewing_key` - Viewing key opening sealed notes
+    ///
+    /// # Returns
+    /// * Result containing the note, or an error for malformed notes and
+    ///   sealed notes without the matching viewing key
+    pub fn decode(
+        text: &str,
+        viewing_key: Option<&ViewingKey>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let checked = CheckedHrpstring::new::<Bech32m>(text.trim())
+            .map_err(|e| format!("Invalid note: {}", e))?;
+        if checked.hrp() != Hrp::parse(NOTE_HRP)? {
+            return Err(format!("Not a note, expected the '{}' prefix", NOTE_HRP).into());
+        }
+        let payload = Zeroizing::new(checked.byte_iter().collect::<Vec<u8>>());
+
+        if payload.len() < 2 {
+            return Err("Malformed note".into());
+        }
+        if payload[0] != NOTE_FORMAT_VERSION {
+            return Err(format!("Unsupported note version {}", payload[0]).into());
+        }
+        if payload[1] != FLAG_SEALED {
+            return Self::from_payload(&payload);
+        }
+
+        let viewing_key = viewing_key.ok_or("Note is sealed, a viewing key is needed")?;
+        let plain = viewing_key.open(
+            &EncryptedNote::from_bytes(&payload[2..])?,
+            SEALED_NOTE_KDF_INFO,
+        )?;
+        if plain.len() < 2 || plain[0] != NOTE_FORMAT_VERSION || plain[1] & FLAG_SEALED != 0 {
+            return Err("Malformed sealed note".into());
+        }
+        Self::from_payload(&plain)
+    }
+}
+
+/// Uppercase form of a note, for QR codes
+///
+/// # Arguments
+/// * `note` - Encoded note
+///
+/// # Returns
+/// * Uppercase note, decoded like the lowercase one
+pub fn qr_text(note: &str) -> Zeroizing<String> {
+    Zeroizing::new(note.to_ascii_uppercase())
+}
+
+/// Render a note as a QR code for the terminal
+///
+/// # Arguments
+/// * `note` - Encoded note
+///
+/// # Returns
+/// * Result containing the QR code drawn with Unicode blocks or an error
+pub fn render_qr(note: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let code = QrCode::new(qr_text(note).as_bytes())?;
+    Ok(code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build())
+}
+
+impl<S: WalletStore> Wallet<S> {
+    /// Build the note of a coin
+    ///
+    /// Coins that are not minted yet are exported with their burn preimage.
+    /// For coins of this wallet it is derived. The note records the chain id
+    /// of the wallet, which has to be set.
+    ///
+    /// # Arguments
+    /// * `position` - Position of the coin in `get_coins`
+    ///
+    /// # Returns
+    /// * Result containing the note or an error
+    pub fn export_note(&self, position: usize) -> Result<Note, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        let coin = self
+            .data
+            .coins
+            .get(position)
+            .ok_or_else(|| format!("Coin #{} not found in wallet", position))?;
+        let chain_id = coin
+            .chain_id
+            .or(self.chain_id)
+            .ok_or("Chain id not set, notes record the chain the coin was burned on")?;
+
+        let mut note = Note {
+            chain_id,
+            amount: coin.amount,
+            salt: coin.salt,
+            encrypted: coin.encrypted,
+            burn_preimage: None,
+            burn_address: coin.burn_address,
+        };
+        if coin.is_minted() {
+            return Ok(note);
+        }
+
+        let burn_address = match (coin.burn_preimage, coin.burn_index) {
+            (Some(preimage), _) => BurnAddress::from_preimage(preimage_to_field(preimage)?),
+            (None, Some(index)) => self.derive_burn_address(index)?,
+            (None, None) => {
+                return Err(format!(
+                    "Coin #{} is not minted and its burn address is unknown",
+                    position
+                )
+                .into())
+            }
+        };
+        note.burn_preimage = Some(field_to_preimage(burn_address.preimage));
+        note.burn_address = Some(burn_address.address);
+        Ok(note)
+    }
+
+    /// Merge the coin of a note into the wallet and save it
+    ///
+    /// A note with a burn preimage becomes a `burned` coin that this wallet
+    /// can prove. A note without one describes a minted coin. Notes of
+    /// another chain than the wallet's are refused.
+    ///
+    /// # Arguments
+    /// * `note` - Decoded note
+    ///
+    /// # Returns
+    /// * Result containing what the import changed, or an error
+    pub fn import_note(&mut self, note: &Note) -> Result<NoteImport, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        let chain_id = self
+            .chain_id
+            .ok_or("Chain id not set, notes are only imported on the chain they were burned on")?;
+        if note.chain_id != chain_id {
+            return Err(format!(
+                "Note is for chain {}, the wallet is connected to chain {}",
+                note.chain_id, chain_id
+            )
+            .into());
+        }
+        let burn_address = match note.burn_preimage {
+            Some(preimage) => Some(BurnAddress::from_preimage(preimage_to_field(preimage)?)),
+            None => None,
+        };
+        let address = burn_address
+            .as_ref()
+            .map(|burn_address| burn_address.address)
+            .or(note.burn_address);
+
+        let existing = self.data.coins.iter_mut().find(|coin| {
+            (address.is_some() && coin.burn_address == address)
+                || (coin.amount == note.amount && coin.salt == note.salt)
+        });
+        let outcome = match existing {
+            Some(coin) => {
+                let mut changed = false;
+                if coin.burn_address.is_none() && address.is_some() {
+                    coin.burn_address = address;
+                    changed = true;
+                }
+                if coin.burn_index.is_none()
+                    && coin.burn_preimage.is_none()
+                    && note.burn_preimage.is_some()
+                {
+                    coin.burn_preimage = note.burn_preimage;
+                    changed = true;
+                }
+                if changed {
+                    NoteImport::Updated
+                } else {
+                    NoteImport::Known
+                }
+            }
+            None => {
+                let mut coin = Coin::new(note.amount, note.salt, note.encrypted);
+                coin.chain_id = Some(note.chain_id);
+                coin.burn_address = address;
+                match &burn_address {
+                    Some(burn_address) => {
+                        coin.burn_preimage = note.burn_preimage;
+                        coin.nullifier = Some(burn_address.nullifier());
+                    }
+                    None => coin.state = CoinState::Minted,
+                }
+                self.data.coins.push(coin);
+                NoteImport::Added
+            }
+        };
+
+        if outcome != NoteImport::Known {
+            self.save()?;
+        }
+        Ok(outcome)
+    }
+
+    /// Get the burn address of a coin imported with its preimage
+    ///
+    /// Fails when the coin was burned on another chain than the wallet's.
+    ///
+    /// # Arguments
+    /// * `address` - Burn address
+    ///
+    /// # Returns
+    /// * Result containing the burn address with its preimage, if imported
+    pub fn imported_burn_address(
+        &self,
+        address: Address,
+    ) -> Result<Option<BurnAddress>, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        let Some(coin) = self.coin_for_burn_address(address) else {
+            return Ok(None);
+        };
+        if let Some(chain_id) = coin.chain_id {
+            if self.chain_id != Some(chain_id) {
+                return Err(format!(
+                    "Coin of {} was burned on chain {}, connect to that chain to mint it",
+                    address, chain_id
+                )
+                .into());
+            }
+        }
+        match coin.burn_preimage {
+            Some(preimage) => Ok(Some(BurnAddress::from_preimage(preimage_to_field(
+                preimage,
+            )?))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derivation::DerivationScheme;
+    use crate::store::MemoryStore;
+    use crate::NOTE_SIZE;
+
+    fn test_wallet(seed: u8) -> Wallet<MemoryStore> {
+        let mut wallet = Wallet::from_entropy(&[seed; NOTE_SIZE], DerivationScheme::Hierarchical);
+        wallet.set_chain_id(1);
+        wallet
+    }
+
+    fn burned_coin(wallet: &mut Wallet<MemoryStore>) {
+        let mut coin = wallet
+            .create_coin_for_burn(0, U256::from(1000u64), true)
+            .unwrap();
+        coin.burn_address = Some(wallet.derive_burn_address(0).unwrap().address);
+        wallet.add_coin(coin).unwrap();
+    }
+
+    #[test]
+    fn test_note_roundtrip() {
+        let mut sender = test_wallet(1);
+        burned_coin(&mut sender);
+        let note = sender.export_note(0).unwrap();
+        let text = note.encode().unwrap();
+
+        assert!(text.starts_with("dmnote1"));
+        assert_eq!(Note::decode(&text, None).unwrap(), note);
+        assert_eq!(Note::decode(&qr_text(&text), None).unwrap(), note);
+        assert!(!render_qr(&text).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corrupted_note_is_rejected() {
+        let mut sender = test_wallet(1);
+        burned_coin(&mut sender);
+        let text = sender.export_note(0).unwrap().encode().unwrap();
+
+        let mut corrupted = text.to_string();
+        let last = corrupted.pop().unwrap();
+        corrupted.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(Note::decode(&corrupted, None).is_err());
+
+        // Same payload under another prefix
+        let (_, payload) = bech32::decode(&text).unwrap();
+        let other = bech32::encode::<Bech32m>(Hrp::parse("other").unwrap(), &payload).unwrap();
+        assert!(Note::decode(&other, None).is_err());
+    }
+
+    #[test]
+    fn test_sealed_note_needs_viewing_key() {
+        let mut sender = test_wallet(1);
+        burned_coin(&mut sender);
+        let recipient = test_wallet(2);
+        let note = sender.export_note(0).unwrap();
+        let text = note
+            .encode_sealed(&recipient.viewing_key().unwrap().public_key())
+            .unwrap();
+
+        assert!(Note::decode(&text, None).is_err());
+        assert!(Note::decode(&text, Some(&sender.viewing_key().unwrap())).is_err());
+        let opened = Note::decode(&text, Some(&recipient.viewing_key().unwrap())).unwrap();
+        assert_eq!(opened, note);
+    }
+
+    #[test]
+    fn test_import_makes_burn_provable() {
+        let mut sender = test_wallet(1);
+        burned_coin(&mut sender);
+        let sent = sender.derive_burn_address(0).unwrap();
+        let note = sender.export_note(0).unwrap();
+
+        let mut recipient = test_wallet(2);
+        assert_eq!(recipient.import_note(&note).unwrap(), NoteImport::Added);
+        assert_eq!(recipient.import_note(&note).unwrap(), NoteImport::Known);
+
+        let coin = recipient.coin_for_burn_address(sent.address).unwrap();
+        assert_eq!(coin.state, CoinState::Burned);
+        assert_eq!(coin.salt, sender.get_coins()[0].salt);
+        assert_eq!(coin.nullifier, Some(sent.nullifier()));
+        let imported = recipient
+            .imported_burn_address(sent.address)
+            .unwrap()
+            .unwrap();
+        assert_eq!(imported.preimage, sent.preimage);
+    }
+
+    #[test]
+    fn test_note_is_bound_to_its_chain() {
+        let mut sender = test_wallet(1);
+        burned_coin(&mut sender);
+        let sent = sender.derive_burn_address(0).unwrap();
+        let note = sender.export_note(0).unwrap();
+        assert_eq!(note.chain_id, 1);
+
+        let mut other_chain = test_wallet(2);
+        other_chain.set_chain_id(5);
+        assert!(other_chain.import_note(&note).is_err());
+
+        // An imported coin cannot be minted on another chain either
+        let mut recipient = test_wallet(2);
+        recipient.import_note(&note).unwrap();
+        recipient.set_chain_id(5);
+        assert!(recipient.imported_burn_address(sent.address).is_err());
+    }
+
+    #[test]
+    fn test_minted_coin_exports_opening_only() {
+        let mut sender = test_wallet(1);
+        burned_coin(&mut sender);
+        sender
+            .update_coin(0, |coin| {
+                coin.state = CoinState::Minted;
+                Ok(())
+            })
+            .unwrap();
+        let note = sender.export_note(0).unwrap();
+        assert!(note.burn_preimage.is_none());
+
+        let mut recipient = test_wallet(2);
+        recipient.import_note(&note).unwrap();
+        assert!(recipient.get_coins()[0].is_minted());
+        assert!(recipient.get_coins()[0].burn_preimage.is_none());
+    }
+}
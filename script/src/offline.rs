@@ -0,0 +1,726 @@
+//! Offline (air-gapped) burn and proving workflow
+//!
+//! The wallet, with the burn preimages, and the fee payer key can stay on a
+//! machine that never connects to the network. The two sides exchange JSON
+//! bundles, e.g. on a USB stick:
+//!
+//! 1. Online: `burn-request` writes an unsigned burn (sender, nonce, fees,
+//!    gas limit, chain id). The burn address is left for the offline side.
+//! 2. Offline: `sign` reserves a burn address, signs the burn and writes the
+//!    signed raw transaction. The burn is recorded as pending.
+//! 3. Online: `broadcast` sends the raw transaction.
+//! 4. Online: `proving-request` fetches the account proof of the burn address
+//!    and the block header it is rooted in.
+//! 5. Offline: `prove` runs the SP1 prover locally with the burn preimage and
+//!    writes the proof artifact (`proof.json`).
+//! 6. Online: after the proof is aggregated, `mint-request` writes an unsigned
+//!    mint; it is signed offline and broadcast like the burn.
+//!
+//! Bundles carry their kind and `OFFLINE_BUNDLE_VERSION`, and nothing in them
+//! is secret: the signed transactions and the proof are published anyway.
+
+use alloy::{
+    consensus::{SignableTransaction, TxEip1559, TxEnvelope, TxLegacy},
+    eips::eip2718::Encodable2718,
+    network::TxSigner,
+    primitives::{Address, Bytes, Signature, TxKind, B256, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::{Block, EIP1186AccountProofResponse, TransactionRequest},
+    sol_types::SolType,
+};
+use fibonacci_lib::PublicValuesStruct;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::amount::Amount;
+use crate::burn::{current_fees, max_transaction_cost, with_gas_margin};
+use crate::mint::{
+    fetch_proof_inputs, is_canonical, locate_burn_address, mint_calldata, proven_burn_address,
+    start_proving, verified_header_rlp, MINT_GAS_LIMIT,
+};
+use crate::network::ProofBlock;
+use crate::pending_burn::{BurnFees, PendingBurn, RECEIPT_TIMEOUT_SECS};
+use crate::store::WalletStore;
+use crate::{BurnAddress, Coin, Wallet};
+
+/// Version of the offline bundle format
+pub const OFFLINE_BUNDLE_VERSION: u32 = 1;
+
+/// JSON file exchanged between the online and the offline machine
+pub trait Bundle: Serialize + DeserializeOwned {
+    /// Kind recorded in the file, so one bundle is not mistaken for another
+    const KIND: &'static str;
+}
+
+/// What a transaction request is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxPurpose {
+    /// Burn to a burn address picked by the offline wallet
+    Burn,
+    /// Mint call of the DarkMint contract
+    Mint,
+}
+
+/// Unsigned transaction prepared by the online machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxRequest {
+    /// What the transaction is for
+    pub purpose: TxPurpose,
+    /// Chain the transaction is for
+    pub chain_id: u64,
+    /// Fee paying account that must sign the transaction
+    pub from: Address,
+    /// Recipient, `None` for burns: the offline wallet picks the burn address
+    pub to: Option<Address>,
+    /// Value sent in wei
+    pub value: U256,
+    /// Calldata
+    #[serde(default)]
+    pub input: Bytes,
+    /// Nonce of the fee paying account
+    pub nonce: u64,
+    /// Gas limit
+    pub gas_limit: u64,
+    /// Max fee per gas, or the gas price of legacy transactions
+    #[serde(with = "alloy::serde::quantity")]
+    pub max_fee_per_gas: u128,
+    /// Priority fee per gas, `None` for legacy transactions
+    #[serde(
+        default,
+        with = "alloy::serde::quantity::opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_priority_fee_per_gas: Option<u128>,
+}
+
+impl Bundle for TxRequest {
+    const KIND: &'static str = "tx-request";
+}
+
+/// Transaction signed by the offline machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedTx {
+    /// What the transaction is for
+    pub purpose: TxPurpose,
+    /// Chain the transaction is for
+    pub chain_id: u64,
+    /// Signing account
+    pub from: Address,
+    /// Hash of the transaction
+    pub tx_hash: B256,
+    /// EIP-2718 encoded signed transaction
+    pub raw: Bytes,
+    /// Index of the burn address, for burns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn_index: Option<u64>,
+}
+
+impl Bundle for SignedTx {
+    const KIND: &'static str = "signed-tx";
+}
+
+/// Chain data the offline machine proves a burn against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvingRequest {
+    /// Chain the burn is on
+    pub chain_id: u64,
+    /// Burn address to prove
+    pub burn_address: Address,
+    /// Block the account proof is rooted in
+    pub block: Block,
+    /// Account proof of the burn address at `block`
+    pub account_proof: EIP1186AccountProofResponse,
+}
+
+impl Bundle for ProvingRequest {
+    const KIND: &'static str = "proving-request";
+}
+
+impl ProvingRequest {
+    /// Check that the account proof and the block header belong together
+    ///
+    /// The header must hash to the block hash, and the proof must be for the
+    /// burn address. The prover checks the proof against the state root.
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error for inconsistent requests
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.account_proof.address != self.burn_address {
+            return Err(format!(
+                "Account proof is for {}, not the burn address {}",
+                self.account_proof.address, self.burn_address
+            )
+            .into());
+        }
+        if self.account_proof.account_proof.is_empty() {
+            return Err("Account proof is empty".into());
+        }
+        verified_header_rlp(&self.block)?;
+        Ok(())
+    }
+}
+
+/// Serialize a bundle with its kind and version
+pub fn bundle_to_json<T: Bundle>(bundle: &T) -> Result<String, Box<dyn std::error::Error>> {
+    let mut value = serde_json::to_value(bundle)?;
+    let object = value
+        .as_object_mut()
+        .ok_or("Bundles must serialize to JSON objects")?;
+    object.insert("bundle".to_string(), json!(T::KIND));
+    object.insert("version".to_string(), json!(OFFLINE_BUNDLE_VERSION));
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+/// Parse a bundle, checking its kind and version
+pub fn bundle_from_json<T: Bundle>(content: &str) -> Result<T, Box<dyn std::error::Error>> {
+    let mut value: Value = serde_json::from_str(content)?;
+    let object = value.as_object_mut().ok_or("Bundle is not a JSON object")?;
+    let kind = object.remove("bundle");
+    if kind.as_ref().and_then(Value::as_str) != Some(T::KIND) {
+        return Err(format!(
+            "Expected a {} bundle, got {}",
+            T::KIND,
+            kind.map_or("no bundle kind".to_string(), |kind| kind.to_string())
+        )
+        .into());
+    }
+    let version = object
+        .remove("version")
+        .and_then(|version| version.as_u64());
+    if version != Some(OFFLINE_BUNDLE_VERSION as u64) {
+        return Err(format!(
+            "Unsupported {} bundle version {:?} (this DarkMint reads version {})",
+            T::KIND,
+            version,
+            OFFLINE_BUNDLE_VERSION
+        )
+        .into());
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Write a bundle to a file
+pub fn write_bundle<T: Bundle>(path: &Path, bundle: &T) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(path, bundle_to_json(bundle)?)
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e).into())
+}
+
+/// Read a bundle from a file
+pub fn read_bundle<T: Bundle>(path: &Path) -> Result<T, Box<dyn std::error::Error>> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    bundle_from_json(&content).map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+/// Read the public values of a proof artifact (`proof.json`)
+///
+/// # Arguments
+/// * `path` - Proof artifact written by the prover
+///
+/// # Returns
+/// * Result containing the public values or an error
+pub fn read_public_values(path: &Path) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let artifact: Value = serde_json::from_str(&content)?;
+    let public_values = artifact
+        .get("pub_inputs")
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("{} has no pub_inputs", path.display()))?;
+    Ok(hex::decode(public_values.trim_start_matches("0x"))?)
+}
+
+/// Sign a transaction request
+///
+/// # Arguments
+/// * `request` - Unsigned transaction
+/// * `to` - Recipient, the burn address for burns
+/// * `signer` - Signer of the account the request is from
+///
+/// # Returns
+/// * Result containing the signed transaction, or an error if the signer is not the sender
+pub async fn sign_tx_request<T>(
+    request: &TxRequest,
+    to: Address,
+    signer: &T,
+) -> Result<SignedTx, Box<dyn std::error::Error>>
+where
+    T: TxSigner<Signature> + Send + Sync,
+{
+    if signer.address() != request.from {
+        return Err(format!(
+            "Request is from {}, but the unlocked account is {}",
+            request.from,
+            signer.address()
+        )
+        .into());
+    }
+
+    let envelope: TxEnvelope = match request.max_priority_fee_per_gas {
+        Some(max_priority_fee_per_gas) => {
+            let mut tx = TxEip1559 {
+                chain_id: request.chain_id,
+                nonce: request.nonce,
+                gas_limit: request.gas_limit,
+                max_fee_per_gas: request.max_fee_per_gas,
+                max_priority_fee_per_gas,
+                to: TxKind::Call(to),
+                value: request.value,
+                access_list: Default::default(),
+                input: request.input.clone(),
+            };
+            let signature = signer.sign_transaction(&mut tx).await?;
+            tx.into_signed(signature).into()
+        }
+        None => {
+            let mut tx = TxLegacy {
+                chain_id: Some(request.chain_id),
+                nonce: request.nonce,
+                gas_price: request.max_fee_per_gas,
+                gas_limit: request.gas_limit,
+                to: TxKind::Call(to),
+                value: request.value,
+                input: request.input.clone(),
+            };
+            let signature = signer.sign_transaction(&mut tx).await?;
+            tx.into_signed(signature).into()
+        }
+    };
+
+    Ok(SignedTx {
+        purpose: request.purpose,
+        chain_id: request.chain_id,
+        from: request.from,
+        tx_hash: *envelope.tx_hash(),
+        raw: envelope.encoded_2718().into(),
+        burn_index: None,
+    })
+}
+
+/// Sign a burn request to the next unused burn address of the wallet
+///
+/// The burn address is reserved and the burn recorded as pending, so the
+/// address is not handed out again. Without network access the wallet cannot
+/// check that the address is still empty.
+///
+/// # Arguments
+/// * `wallet` - Offline wallet
+/// * `request` - Unsigned burn
+/// * `signer` - Signer of the account the request is from
+/// * `label` - Label stored with the burn address
+///
+/// # Returns
+/// * Result containing the signed burn or an error
+pub async fn sign_burn_request<S, T>(
+    wallet: &mut Wallet<S>,
+    request: &TxRequest,
+    signer: &T,
+    label: Option<&str>,
+) -> Result<SignedTx, Box<dyn std::error::Error>>
+where
+    S: WalletStore,
+    T: TxSigner<Signature> + Send + Sync,
+{
+    if request.purpose != TxPurpose::Burn || request.to.is_some() {
+        return Err("Not a burn request".into());
+    }
+    if request.value.is_zero() {
+        return Err("Burn amount must be greater than 0".into());
+    }
+    if signer.address() != request.from {
+        return Err(format!(
+            "Request is from {}, but the unlocked account is {}",
+            request.from,
+            signer.address()
+        )
+        .into());
+    }
+
+    // Hierarchical burn addresses depend on the chain
+    wallet.set_chain_id(request.chain_id);
+    let (burn_index, burn_address) = wallet.reserve_burn_address(label)?;
+    let mut signed = sign_tx_request(request, burn_address.address, signer).await?;
+    signed.burn_index = Some(burn_index);
+
+    wallet.add_pending_burn(PendingBurn {
+        burn_index,
+        burn_address: burn_address.address,
+        from: request.from,
+        amount: request.value,
+        nonce: request.nonce,
+        gas_limit: request.gas_limit,
+        max_fee_per_gas: request.max_fee_per_gas,
+        max_priority_fee_per_gas: request.max_priority_fee_per_gas,
+        tx_hashes: vec![signed.tx_hash],
+        cancel_tx_hashes: vec![],
+    })?;
+    Ok(signed)
+}
+
+/// Record that the coin of a proving request is being proven offline
+///
+/// A pending burn to the burn address is settled, since the account proof
+/// shows it was funded.
+///
+/// # Arguments
+/// * `wallet` - Offline wallet
+/// * `request` - Proving request written by the online machine
+/// * `encrypted` - Whether the coin is minted in encrypted mode
+/// * `gap_limit` - Number of burn address indexes searched past the next unused one
+///
+/// # Returns
+/// * Result containing the burn address with its preimage and the coin, or an error
+pub fn start_offline_proof<S: WalletStore>(
+    wallet: &mut Wallet<S>,
+    request: &ProvingRequest,
+    encrypted: bool,
+    gap_limit: u64,
+) -> Result<(BurnAddress, Coin), Box<dyn std::error::Error>> {
+    request.validate()?;
+    wallet.set_chain_id(request.chain_id);
+    let (burn_index, burn_addr) = locate_burn_address(wallet, request.burn_address, gap_limit)?;
+    let coin = start_proving(
+        wallet,
+        burn_index,
+        &burn_addr,
+        request.account_proof.balance,
+        encrypted,
+        request.block.header.number,
+    )?;
+    Ok((burn_addr, coin))
+}
+
+/// Fill in the sender, nonce, fees and chain of a transaction request
+async fn prepare_tx_request<P: Provider>(
+    provider: &P,
+    purpose: TxPurpose,
+    tx: TransactionRequest,
+    legacy: bool,
+) -> Result<TxRequest, Box<dyn std::error::Error>> {
+    let from = tx.from.ok_or("Transaction request without sender")?;
+    let fees: BurnFees = current_fees(provider, legacy).await?;
+    Ok(TxRequest {
+        purpose,
+        chain_id: provider.get_chain_id().await?,
+        from,
+        to: tx.to.and_then(|to| to.to().copied()),
+        value: tx.value.unwrap_or_default(),
+        input: tx.input.into_input().unwrap_or_default(),
+        nonce: provider.get_transaction_count(from).await?,
+        gas_limit: tx.gas.ok_or("Transaction request without gas limit")?,
+        max_fee_per_gas: fees.max_fee_per_gas,
+        max_priority_fee_per_gas: fees.max_priority_fee_per_gas,
+    })
+}
+
+/// Check that an account can pay for a transaction request
+async fn check_balance<P: Provider>(
+    provider: &P,
+    request: &TxRequest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let balance = provider.get_balance(request.from).await?;
+    let max_cost = max_transaction_cost(request.value, request.gas_limit, request.max_fee_per_gas);
+    if balance < max_cost {
+        return Err(format!(
+            "Insufficient balance. Need {}, have {}",
+            Amount::from_wei(max_cost),
+            Amount::from_wei(balance)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Prepare an unsigned burn on the online machine
+///
+/// # Arguments
+/// * `provider_url` - RPC endpoint
+/// * `from` - Fee paying account the burn is sent from
+/// * `amount` - Amount to burn
+/// * `legacy` - Use a gas price instead of EIP-1559 fees
+///
+/// # Returns
+/// * Result containing the unsigned burn or an error
+pub async fn prepare_burn_request(
+    provider_url: &str,
+    from: Address,
+    amount: Amount,
+    legacy: bool,
+) -> Result<TxRequest, Box<dyn std::error::Error>> {
+    if amount.is_zero() {
+        return Err("Burn amount must be greater than 0".into());
+    }
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+
+    // The burn address is not known yet: estimate a transfer to a fresh address
+    let estimate = TransactionRequest::default()
+        .from(from)
+        .to(Address::from(rand::random::<[u8; 20]>()))
+        .value(amount.wei());
+    let gas_limit = with_gas_margin(provider.estimate_gas(estimate.clone()).await?);
+    let mut request = prepare_tx_request(
+        &provider,
+        TxPurpose::Burn,
+        estimate.gas_limit(gas_limit),
+        legacy,
+    )
+    .await?;
+    request.to = None;
+    check_balance(&provider, &request).await?;
+    Ok(request)
+}
+
+/// Fetch the chain data a burn is proven against on the online machine
+///
+/// # Arguments
+/// * `provider_url` - RPC endpoint
+/// * `burn_address` - Funded burn address
+/// * `proof_block` - Block the proof is generated against
+///
+/// # Returns
+/// * Result containing the proving request, or an error for empty burn addresses
+pub async fn prepare_proving_request(
+    provider_url: &str,
+    burn_address: Address,
+    proof_block: ProofBlock,
+) -> Result<ProvingRequest, Box<dyn std::error::Error>> {
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+    let chain_id = provider.get_chain_id().await?;
+    let (block, account_proof) = fetch_proof_inputs(&provider, burn_address, proof_block).await?;
+
+    let request = ProvingRequest {
+        chain_id,
+        burn_address,
+        block,
+        account_proof,
+    };
+    request.validate()?;
+    Ok(request)
+}
+
+/// Prepare an unsigned mint of a proven coin on the online machine
+///
+/// # Arguments
+/// * `provider_url` - RPC endpoint
+/// * `from` - Fee paying account
+/// * `recipient` - Account receiving the minted tokens
+/// * `contract_address` - DarkMint contract
+/// * `proving_request` - Proving request the proof was generated from
+/// * `public_values` - Public values of the proof
+/// * `legacy` - Use a gas price instead of EIP-1559 fees
+///
+/// # Returns
+/// * Result containing the unsigned mint and the nullifier of the coin, or an
+///   error if the proof is not of the proving request or its block was reorged out
+pub async fn prepare_mint_request(
+    provider_url: &str,
+    from: Address,
+    recipient: Address,
+    contract_address: Address,
+    proving_request: &ProvingRequest,
+    public_values: &[u8],
+    legacy: bool,
+) -> Result<(TxRequest, U256), Box<dyn std::error::Error>> {
+    check_proof_of_request(proving_request.burn_address, public_values)?;
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+    if !is_canonical(&provider, &proving_request.block).await? {
+        return Err(format!(
+            "Proof block {} ({}) was reorged out, fetch a new proving request and prove it again",
+            proving_request.block.header.number, proving_request.block.header.hash
+        )
+        .into());
+    }
+    let amount = Amount::from_wei(proving_request.account_proof.balance);
+    let (input, nullifier) = mint_calldata(recipient, amount.wei(), public_values)?;
+    let tx = TransactionRequest::default()
+        .from(from)
+        .to(contract_address)
+        .input(input.into())
+        .gas_limit(MINT_GAS_LIMIT);
+    let request = prepare_tx_request(&provider, TxPurpose::Mint, tx, legacy).await?;
+    check_balance(&provider, &request).await?;
+    Ok((request, nullifier))
+}
+
+/// Check that a proof was generated for the burn address of a proving request
+///
+/// # Arguments
+/// * `burn_address` - Burn address of the proving request
+/// * `public_values` - Public values of the proof
+///
+/// # Returns
+/// * Result indicating success, or an error if the proof is of another burn address
+fn check_proof_of_request(
+    burn_address: Address,
+    public_values: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let decoded = <PublicValuesStruct as SolType>::abi_decode(public_values)
+        .map_err(|e| format!("Proof carries invalid public values: {}", e))?;
+    let proven = proven_burn_address(&decoded);
+    if proven != burn_address {
+        return Err(format!(
+            "The proof is of burn address {}, not of the proving request's {}",
+            proven, burn_address
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Broadcast a transaction signed offline and wait for it
+///
+/// # Arguments
+/// * `provider_url` - RPC endpoint
+/// * `signed` - Signed transaction
+/// * `confirmations` - Number of blocks to wait for
+///
+/// # Returns
+/// * Result containing whether the transaction succeeded, or an error
+pub async fn broadcast_signed_tx(
+    provider_url: &str,
+    signed: &SignedTx,
+    confirmations: u64,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+    let chain_id = provider.get_chain_id().await?;
+    if chain_id != signed.chain_id {
+        return Err(format!(
+            "Transaction is for chain {}, but the provider serves chain {}",
+            signed.chain_id, chain_id
+        )
+        .into());
+    }
+
+    let pending = provider.send_raw_transaction(&signed.raw).await?;
+    println!("Pending transaction... {}", pending.tx_hash());
+    let receipt = pending
+        .with_required_confirmations(confirmations)
+        .with_timeout(Some(Duration::from_secs(RECEIPT_TIMEOUT_SECS)))
+        .get_receipt()
+        .await
+        .map_err(|e| format!("Transaction {} is not confirmed yet: {}", signed.tx_hash, e))?;
+    println!(
+        "Transaction {} mined in block {:?}",
+        receipt.transaction_hash, receipt.block_number
+    );
+    Ok(receipt.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::burn_index::BurnAddressStatus;
+    use crate::derivation::DerivationScheme;
+    use crate::NOTE_SIZE;
+    use alloy::consensus::Transaction;
+    use alloy::eips::eip2718::Decodable2718;
+    use alloy::signers::local::PrivateKeySigner;
+    use ark_bn254::Fr;
+    use ark_ff::{BigInteger, PrimeField};
+
+    fn burn_request(from: Address, legacy: bool) -> TxRequest {
+        TxRequest {
+            purpose: TxPurpose::Burn,
+            chain_id: 31337,
+            from,
+            to: None,
+            value: U256::from(1_000_000_000_000_000u64),
+            input: Bytes::new(),
+            nonce: 3,
+            gas_limit: 25_200,
+            max_fee_per_gas: 30_000_000_000,
+            max_priority_fee_per_gas: (!legacy).then_some(1_000_000_000),
+        }
+    }
+
+    #[test]
+    fn test_proof_must_be_of_the_proving_request() {
+        let preimage = Fr::from(1234u64);
+        let public_values = PublicValuesStruct::abi_encode(&PublicValuesStruct {
+            burn_preimage: preimage.into_bigint().to_bytes_be().into(),
+            commit_upper: 1,
+            encrypted_balance: 2,
+            nullifier: 3,
+            encrypted: false,
+        });
+
+        let burn_address = BurnAddress::from_preimage(preimage).address;
+        assert!(check_proof_of_request(burn_address, &public_values).is_ok());
+        assert!(check_proof_of_request(Address::repeat_byte(7), &public_values).is_err());
+        assert!(check_proof_of_request(burn_address, &[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_bundle_kind_and_version_are_checked() {
+        let request = burn_request(Address::repeat_byte(1), false);
+        let json = bundle_to_json(&request).unwrap();
+        assert_eq!(bundle_from_json::<TxRequest>(&json).unwrap(), request);
+
+        // A request is not a signed transaction
+        assert!(bundle_from_json::<SignedTx>(&json).is_err());
+
+        let mut value: Value = serde_json::from_str(&json).unwrap();
+        value["version"] = json!(OFFLINE_BUNDLE_VERSION + 1);
+        assert!(bundle_from_json::<TxRequest>(&value.to_string()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_signed_transactions_recover_the_sender() {
+        let signer = PrivateKeySigner::random();
+        for legacy in [false, true] {
+            let request = burn_request(signer.address(), legacy);
+            let to = Address::repeat_byte(0xbb);
+            let signed = sign_tx_request(&request, to, &signer).await.unwrap();
+
+            let envelope = TxEnvelope::decode_2718(&mut signed.raw.as_ref()).unwrap();
+            assert_eq!(*envelope.tx_hash(), signed.tx_hash);
+            let sender = envelope
+                .signature()
+                .recover_address_from_prehash(&envelope.signature_hash())
+                .unwrap();
+            assert_eq!(sender, signer.address());
+            assert_eq!(envelope.is_legacy(), legacy);
+        }
+
+        let other = PrivateKeySigner::random();
+        let request = burn_request(signer.address(), false);
+        assert!(sign_tx_request(&request, Address::ZERO, &other)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_offline_burn_reserves_pending_address() {
+        let mut wallet = Wallet::from_entropy(&[6u8; NOTE_SIZE], DerivationScheme::Hierarchical);
+        let signer = PrivateKeySigner::random();
+        let request = burn_request(signer.address(), false);
+
+        let signed = sign_burn_request(&mut wallet, &request, &signer, Some("cold"))
+            .await
+            .unwrap();
+        assert_eq!(signed.burn_index, Some(0));
+
+        // The burn goes to the wallet's burn address for the request's chain
+        let envelope = TxEnvelope::decode_2718(&mut signed.raw.as_ref()).unwrap();
+        let burn_address = wallet.derive_burn_address(0).unwrap().address;
+        assert_eq!(envelope.to(), Some(burn_address));
+
+        let pending = wallet.pending_burn(signed.tx_hash).unwrap();
+        assert_eq!(pending.burn_address, burn_address);
+        assert_eq!(
+            wallet.burn_addresses()[0].status,
+            BurnAddressStatus::Reserved
+        );
+
+        let mut mint = request.clone();
+        mint.purpose = TxPurpose::Mint;
+        assert!(sign_burn_request(&mut wallet, &mint, &signer, None)
+            .await
+            .is_err());
+    }
+}
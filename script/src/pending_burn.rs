@@ -0,0 +1,466 @@
+//! Pending burn tracking
+//!
+//! A burn is recorded as pending as soon as it is broadcast, with the nonce,
+//! the fees and every transaction hash sent for that nonce. Its burn address
+//! stays reserved until one of those transactions confirms, so an index is
+//! never considered used because of a burn that may still be dropped.
+//!
+//! A stuck burn is replaced by a transaction with the same nonce: the same
+//! burn with higher fees to speed it up, or a 0-value transfer to the fee
+//! payer itself to cancel it. Nodes only accept a replacement whose fees are
+//! at least `MIN_FEE_BUMP_PERCENT` higher than the transaction it replaces.
+
+use alloy::primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::store::WalletStore;
+use crate::Wallet;
+
+/// Smallest fee increase, in percent, nodes accept for a replacement transaction
+pub const MIN_FEE_BUMP_PERCENT: u64 = 10;
+
+/// Default fee increase, in percent, of `darkmint burn speedup` and `cancel`
+pub const DEFAULT_FEE_BUMP_PERCENT: u64 = 25;
+
+/// Seconds to wait for a burn receipt before leaving the burn pending
+pub const RECEIPT_TIMEOUT_SECS: u64 = 300;
+
+/// Fees of a burn transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BurnFees {
+    /// Max fee per gas, or the gas price of legacy transactions
+    pub max_fee_per_gas: u128,
+    /// Priority fee per gas, `None` for legacy transactions
+    pub max_priority_fee_per_gas: Option<u128>,
+}
+
+impl BurnFees {
+    /// Get the fees of a replacement transaction
+    ///
+    /// Each fee is raised by `bump_percent`, or to the current network fee if
+    /// that is higher.
+    ///
+    /// # Arguments
+    /// * `current` - Fees currently estimated by the node, of the same transaction type
+    /// * `bump_percent` - Fee increase in percent, at least `MIN_FEE_BUMP_PERCENT`
+    ///
+    /// # Returns
+    /// * Result containing the new fees, or an error for a too small increase
+    pub fn bumped(
+        &self,
+        current: BurnFees,
+        bump_percent: u64,
+    ) -> Result<BurnFees, Box<dyn std::error::Error>> {
+        if bump_percent < MIN_FEE_BUMP_PERCENT {
+            return Err(format!(
+                "Replacement fees must be at least {}% higher",
+                MIN_FEE_BUMP_PERCENT
+            )
+            .into());
+        }
+        let bump = |fee: u128, current: u128| {
+            let increase = fee.saturating_mul(bump_percent as u128).div_ceil(100);
+            fee.saturating_add(increase).max(current)
+        };
+
+        let max_fee_per_gas = bump(self.max_fee_per_gas, current.max_fee_per_gas);
+        let max_priority_fee_per_gas = self.max_priority_fee_per_gas.map(|fee| {
+            bump(fee, current.max_priority_fee_per_gas.unwrap_or(0)).min(max_fee_per_gas)
+        });
+        Ok(BurnFees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        })
+    }
+
+    /// Check whether these are legacy gas price fees
+    pub fn is_legacy(&self) -> bool {
+        self.max_priority_fee_per_gas.is_none()
+    }
+}
+
+/// Burn that was broadcast but is not confirmed yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingBurn {
+    /// Index of the reserved burn address
+    pub burn_index: u64,
+    /// Burn address the ETH is sent to
+    pub burn_address: Address,
+    /// Fee paying account the burn is sent from
+    pub from: Address,
+    /// Amount burned in wei
+    pub amount: U256,
+    /// Nonce shared by the burn and its replacements
+    pub nonce: u64,
+    /// Gas limit of the transactions
+    pub gas_limit: u64,
+    /// Max fee per gas of the latest transaction, or its gas price
+    #[serde(with = "alloy::serde::quantity")]
+    pub max_fee_per_gas: u128,
+    /// Priority fee per gas of the latest transaction, `None` for legacy transactions
+    #[serde(
+        default,
+        with = "alloy::serde::quantity::opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_priority_fee_per_gas: Option<u128>,
+    /// Burn transactions sent for the nonce, oldest first
+    pub tx_hashes: Vec<B256>,
+    /// Cancelling transactions sent for the nonce, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cancel_tx_hashes: Vec<B256>,
+}
+
+impl PendingBurn {
+    /// Get the fees of the latest transaction
+    pub fn fees(&self) -> BurnFees {
+        BurnFees {
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+        }
+    }
+
+    /// Get the latest transaction sent for the nonce
+    pub fn latest_tx_hash(&self) -> Option<B256> {
+        self.cancel_tx_hashes
+            .last()
+            .or(self.tx_hashes.last())
+            .copied()
+    }
+
+    /// Check whether a transaction was sent for this burn
+    pub fn has_tx(&self, tx_hash: B256) -> bool {
+        self.tx_hashes.contains(&tx_hash) || self.cancel_tx_hashes.contains(&tx_hash)
+    }
+}
+
+impl<S: WalletStore> Wallet<S> {
+    /// Get the burns that were broadcast but are not confirmed yet
+    ///
+    /// # Returns
+    /// * Pending burns, oldest first
+    pub fn pending_burns(&self) -> &[PendingBurn] {
+        &self.data.pending_burns
+    }
+
+    /// Find the pending burn a transaction belongs to
+    ///
+    /// # Arguments
+    /// * `tx_hash` - Hash of the burn or of one of its replacements
+    ///
+    /// # Returns
+    /// * The pending burn, if the transaction is one of them
+    pub fn pending_burn(&self, tx_hash: B256) -> Option<&PendingBurn> {
+        self.data
+            .pending_burns
+            .iter()
+            .find(|pending| pending.has_tx(tx_hash))
+    }
+
+    /// Record a burn that was just broadcast and save the wallet
+    ///
+    /// # Arguments
+    /// * `pending` - Burn transaction details
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error if the burn index is already pending
+    pub fn add_pending_burn(
+        &mut self,
+        pending: PendingBurn,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        if self
+            .data
+            .pending_burns
+            .iter()
+            .any(|other| other.burn_index == pending.burn_index)
+        {
+            return Err(format!(
+                "Burn address #{} already has a pending burn",
+                pending.burn_index
+            )
+            .into());
+        }
+        self.data.pending_burns.push(pending);
+        self.save()
+    }
+
+    /// Record a replacement transaction of a pending burn and save the wallet
+    ///
+    /// # Arguments
+    /// * `tx_hash` - Hash of any transaction of the pending burn
+    /// * `replacement_hash` - Hash of the replacement transaction
+    /// * `fees` - Fees of the replacement transaction
+    /// * `cancel` - Whether the replacement cancels the burn
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error if the burn is not pending
+    pub fn record_burn_replacement(
+        &mut self,
+        tx_hash: B256,
+        replacement_hash: B256,
+        fees: BurnFees,
+        cancel: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        let pending = self
+            .data
+            .pending_burns
+            .iter_mut()
+            .find(|pending| pending.has_tx(tx_hash))
+            .ok_or_else(|| format!("Transaction {} is not a pending burn", tx_hash))?;
+        if cancel {
+            pending.cancel_tx_hashes.push(replacement_hash);
+        } else {
+            pending.tx_hashes.push(replacement_hash);
+        }
+        pending.max_fee_per_gas = fees.max_fee_per_gas;
+        pending.max_priority_fee_per_gas = fees.max_priority_fee_per_gas;
+        self.save()
+    }
+
+    /// Record that a pending burn confirmed and save the wallet
+    ///
+    /// Adds the coin of the burn and marks its burn address used.
+    ///
+    /// # Arguments
+    /// * `tx_hash` - Hash of the burn transaction that confirmed
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error if the transaction is not a pending burn
+    pub fn confirm_pending_burn(
+        &mut self,
+        tx_hash: B256,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        let position = self
+            .data
+            .pending_burns
+            .iter()
+            .position(|pending| pending.tx_hashes.contains(&tx_hash))
+            .ok_or_else(|| format!("Transaction {} is not a pending burn", tx_hash))?;
+        let pending = &self.data.pending_burns[position];
+
+        // Track the coin from the burn on; the encryption mode is chosen when
+        // proving
+        let mut coin = self.create_coin_for_burn(pending.burn_index, pending.amount, false)?;
+        coin.burn_address = Some(pending.burn_address);
+        coin.burn_tx_hash = Some(tx_hash);
+        let burn_index = pending.burn_index;
+
+        self.data.pending_burns.remove(position);
+        self.data.coins.push(coin);
+        self.data.mark_burn_index_used(burn_index);
+        self.save()
+    }
+
+    /// Record that the burn address of a pending burn holds funds and save the wallet
+    ///
+    /// Used when the funds are seen without a receipt, e.g. in the account
+    /// proof of a burn broadcast from another machine. The coin only records
+    /// the burn transaction when a single one was sent.
+    ///
+    /// # Arguments
+    /// * `burn_address` - Burn address seen funded
+    ///
+    /// # Returns
+    /// * Result containing whether a pending burn was settled, or an error
+    pub fn settle_funded_burn(
+        &mut self,
+        burn_address: Address,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        let Some(position) = self
+            .data
+            .pending_burns
+            .iter()
+            .position(|pending| pending.burn_address == burn_address)
+        else {
+            return Ok(false);
+        };
+        let pending = &self.data.pending_burns[position];
+
+        let mut coin = self.create_coin_for_burn(pending.burn_index, pending.amount, false)?;
+        coin.burn_address = Some(burn_address);
+        if pending.tx_hashes.len() == 1 && pending.cancel_tx_hashes.is_empty() {
+            coin.burn_tx_hash = Some(pending.tx_hashes[0]);
+        }
+        let burn_index = pending.burn_index;
+
+        self.data.pending_burns.remove(position);
+        self.data.coins.push(coin);
+        self.data.mark_burn_index_used(burn_index);
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Forget a pending burn that will never confirm and save the wallet
+    ///
+    /// The burn address stays reserved, so it is not handed out again.
+    ///
+    /// # Arguments
+    /// * `tx_hash` - Hash of any transaction of the pending burn
+    ///
+    /// # Returns
+    /// * Result containing the removed burn, or an error if it is not pending
+    pub fn drop_pending_burn(
+        &mut self,
+        tx_hash: B256,
+    ) -> Result<PendingBurn, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        let position = self
+            .data
+            .pending_burns
+            .iter()
+            .position(|pending| pending.has_tx(tx_hash))
+            .ok_or_else(|| format!("Transaction {} is not a pending burn", tx_hash))?;
+        let pending = self.data.pending_burns.remove(position);
+        self.save()?;
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::burn_index::BurnAddressStatus;
+    use crate::derivation::DerivationScheme;
+    use crate::store::MemoryStore;
+    use crate::NOTE_SIZE;
+
+    fn test_wallet() -> Wallet<MemoryStore> {
+        let mut wallet = Wallet::from_entropy(&[4u8; NOTE_SIZE], DerivationScheme::Hierarchical);
+        wallet.set_chain_id(1);
+        wallet
+    }
+
+    fn pending_burn(wallet: &mut Wallet<MemoryStore>) -> PendingBurn {
+        let (burn_index, burn_address) = wallet.reserve_burn_address(None).unwrap();
+        PendingBurn {
+            burn_index,
+            burn_address: burn_address.address,
+            from: Address::repeat_byte(0xaa),
+            amount: U256::from(1_000_000_000_000_000u64),
+            nonce: 7,
+            gas_limit: 25_200,
+            max_fee_per_gas: 30_000_000_000,
+            max_priority_fee_per_gas: Some(1_000_000_000),
+            tx_hashes: vec![B256::repeat_byte(1)],
+            cancel_tx_hashes: vec![],
+        }
+    }
+
+    #[test]
+    fn test_fee_bump() {
+        let fees = BurnFees {
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: Some(10),
+        };
+        let quiet = BurnFees {
+            max_fee_per_gas: 50,
+            max_priority_fee_per_gas: Some(1),
+        };
+        let bumped = fees.bumped(quiet, 10).unwrap();
+        assert_eq!(bumped.max_fee_per_gas, 110);
+        assert_eq!(bumped.max_priority_fee_per_gas, Some(11));
+
+        // The network fee wins when it rose more than the bump
+        let busy = BurnFees {
+            max_fee_per_gas: 500,
+            max_priority_fee_per_gas: Some(40),
+        };
+        let bumped = fees.bumped(busy, 25).unwrap();
+        assert_eq!(bumped.max_fee_per_gas, 500);
+        assert_eq!(bumped.max_priority_fee_per_gas, Some(40));
+
+        // Rounding up keeps tiny fees strictly increasing
+        let legacy = BurnFees {
+            max_fee_per_gas: 3,
+            max_priority_fee_per_gas: None,
+        };
+        let bumped = legacy.bumped(legacy, 10).unwrap();
+        assert_eq!(bumped.max_fee_per_gas, 4);
+        assert!(bumped.is_legacy());
+
+        assert!(fees.bumped(quiet, 5).is_err());
+    }
+
+    #[test]
+    fn test_confirmed_burn_uses_index() {
+        let mut wallet = test_wallet();
+        let pending = pending_burn(&mut wallet);
+        let index = pending.burn_index;
+        wallet.add_pending_burn(pending.clone()).unwrap();
+        assert!(wallet.add_pending_burn(pending).is_err());
+
+        let speedup = B256::repeat_byte(2);
+        let fees = BurnFees {
+            max_fee_per_gas: 40_000_000_000,
+            max_priority_fee_per_gas: Some(2_000_000_000),
+        };
+        wallet
+            .record_burn_replacement(B256::repeat_byte(1), speedup, fees, false)
+            .unwrap();
+        let pending = wallet.pending_burn(speedup).unwrap();
+        assert_eq!(pending.tx_hashes.len(), 2);
+        assert_eq!(pending.fees(), fees);
+        assert_eq!(
+            wallet.burn_addresses()[0].status,
+            BurnAddressStatus::Reserved
+        );
+        assert!(wallet.coin_for_burn(index).is_none());
+
+        wallet.confirm_pending_burn(speedup).unwrap();
+        assert!(wallet.pending_burns().is_empty());
+        assert_eq!(wallet.burn_addresses()[0].status, BurnAddressStatus::Used);
+        let coin = wallet.coin_for_burn(index).unwrap();
+        assert_eq!(coin.burn_tx_hash, Some(speedup));
+        assert_eq!(coin.amount, U256::from(1_000_000_000_000_000u64));
+    }
+
+    #[test]
+    fn test_cancelled_burn_keeps_index_reserved() {
+        let mut wallet = test_wallet();
+        let pending = pending_burn(&mut wallet);
+        wallet.add_pending_burn(pending).unwrap();
+
+        let cancel = B256::repeat_byte(3);
+        let fees = BurnFees {
+            max_fee_per_gas: 40_000_000_000,
+            max_priority_fee_per_gas: Some(2_000_000_000),
+        };
+        wallet
+            .record_burn_replacement(B256::repeat_byte(1), cancel, fees, true)
+            .unwrap();
+        assert_eq!(
+            wallet.pending_burn(cancel).unwrap().latest_tx_hash(),
+            Some(cancel)
+        );
+        assert!(wallet.confirm_pending_burn(cancel).is_err());
+
+        wallet.drop_pending_burn(cancel).unwrap();
+        assert!(wallet.pending_burns().is_empty());
+        assert_eq!(wallet.coin_count(), 0);
+        assert_eq!(
+            wallet.burn_addresses()[0].status,
+            BurnAddressStatus::Reserved
+        );
+        assert_eq!(wallet.next_burn_index(), 1);
+    }
+
+    #[test]
+    fn test_funded_burn_is_settled_without_receipt() {
+        let mut wallet = test_wallet();
+        let pending = pending_burn(&mut wallet);
+        let (index, address) = (pending.burn_index, pending.burn_address);
+        wallet.add_pending_burn(pending).unwrap();
+
+        assert!(!wallet.settle_funded_burn(Address::repeat_byte(9)).unwrap());
+        assert!(wallet.settle_funded_burn(address).unwrap());
+        assert!(wallet.pending_burns().is_empty());
+        assert_eq!(wallet.burn_addresses()[0].status, BurnAddressStatus::Used);
+        let coin = wallet.coin_for_burn(index).unwrap();
+        assert_eq!(coin.burn_address, Some(address));
+        assert_eq!(coin.burn_tx_hash, Some(B256::repeat_byte(1)));
+    }
+}
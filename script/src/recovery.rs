@@ -0,0 +1,225 @@
+//! Recovery of coins from the chain
+//!
+//! After a restore the wallet knows its entropy but not its coins. Burn
+//! addresses are derived in order and their balances queried until `gap_limit`
+//! consecutive addresses are found empty. Every funded address becomes a coin
+//! with its deterministic salt, and its nullifier is checked against the
+//! DarkMint contract to tell minted coins from pending ones. Funded indexes
+//! are recorded as used, so new burns continue after them.
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+    sol,
+};
+
+use crate::store::WalletStore;
+use crate::{CoinState, Wallet};
+
+sol! {
+    #[sol(rpc)]
+    interface IDarkMint {
+        function nullifiers(uint256 nullifier) external view returns (bool);
+    }
+}
+
+/// A funded burn address found during recovery
+#[derive(Debug, Clone)]
+pub struct RecoveredCoin {
+    /// Index of the burn address
+    pub index: u64,
+    /// Burn address
+    pub address: Address,
+    /// Balance of the burn address in wei
+    pub amount: U256,
+    /// Whether the nullifier is already spent on the DarkMint contract
+    pub minted: bool,
+    /// Whether the coin was missing from the wallet and has been added
+    pub added: bool,
+}
+
+/// Result of a recovery scan
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    /// Number of burn addresses queried
+    pub scanned: u64,
+    /// Funded burn addresses in index order
+    pub coins: Vec<RecoveredCoin>,
+}
+
+impl<S: WalletStore> Wallet<S> {
+    /// Rediscover coins by scanning derived burn addresses on chain
+    ///
+    /// Coins already in the wallet (matched by burn address index) are moved
+    /// to `Minted` when their nullifier is spent; missing ones are rebuilt with
+    /// their deterministic salt in the `Burned` or `Minted` state. The wallet is saved once at the end of the scan.
+    ///
+    /// # Arguments
+    /// * `provider` - Provider used for balance queries and contract calls
+    /// * `contract` - Address of the DarkMint contract
+    /// * `gap_limit` - Consecutive unfunded addresses after which the scan stops
+    /// * `encrypted` - Whether rebuilt coins were minted in encrypted mode
+    ///
+    /// # Returns
+    /// * Result containing the recovery report or an error
+    pub async fn recover_coins<P: Provider>(
+        &mut self,
+        provider: &P,
+        contract: Address,
+        gap_limit: u64,
+        encrypted: bool,
+    ) -> Result<RecoveryReport, Box<dyn std::error::Error>> {
+        let report = self
+            .scan_coins(provider, contract, gap_limit, encrypted)
+            .await?;
+        if !report.coins.is_empty() {
+            self.save()?;
+        }
+        Ok(report)
+    }
+
+    /// Scan burn addresses and update the in-memory coins without saving
+    async fn scan_coins<P: Provider>(
+        &mut self,
+        provider: &P,
+        contract: Address,
+        gap_limit: u64,
+        encrypted: bool,
+    ) -> Result<RecoveryReport, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+        if gap_limit == 0 {
+            return Err("Gap limit must be at least 1".into());
+        }
+
+        let darkmint = IDarkMint::new(contract, provider);
+        let mut report = RecoveryReport::default();
+        let mut gap = 0;
+        let mut index = 0;
+
+        while gap < gap_limit {
+            let burn_addr = self.derive_burn_address(index)?;
+            let amount = provider.get_balance(burn_addr.address).await?;
+            report.scanned += 1;
+
+            if amount.is_zero() {
+                gap += 1;
+                index += 1;
+                continue;
+            }
+            gap = 0;
+            self.data.mark_burn_index_used(index);
+
+            let nullifier = burn_addr.nullifier();
+            let minted = darkmint.nullifiers(nullifier).call().await?;
+            let added = match self
+                .data
+                .coins
+                .iter_mut()
+                .find(|coin| coin.burn_index == Some(index))
+            {
+                Some(coin) => {
+                    // The contract is authoritative, whatever the recorded state
+                    if minted {
+                        coin.state = CoinState::Minted;
+                        coin.error = None;
+                    }
+                    false
+                }
+                None => {
+                    let mut coin = self.create_coin_for_burn(index, amount, encrypted)?;
+                    coin.burn_address = Some(burn_addr.address);
+                    coin.nullifier = Some(nullifier);
+                    if minted {
+                        coin.state = CoinState::Minted;
+                    }
+                    self.data.coins.push(coin);
+                    true
+                }
+            };
+
+            report.coins.push(RecoveredCoin {
+                index,
+                address: burn_addr.address,
+                amount,
+                minted,
+                added,
+            });
+            index += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derivation::DerivationScheme;
+    use crate::NOTE_SIZE;
+    use alloy::{
+        primitives::Bytes, providers::ProviderBuilder, sol_types::SolValue,
+        transports::mock::Asserter,
+    };
+
+    #[tokio::test]
+    async fn test_scan_stops_after_gap_limit() {
+        let mut wallet = Wallet::from_entropy(&[3u8; NOTE_SIZE], DerivationScheme::Legacy);
+        let asserter = Asserter::new();
+        // index 0: funded and minted, 1: empty, 2: funded, 3 and 4: empty
+        asserter.push_success(&U256::from(100u64));
+        asserter.push_success(&Bytes::from(true.abi_encode()));
+        asserter.push_success(&U256::ZERO);
+        asserter.push_success(&U256::from(7u64));
+        asserter.push_success(&Bytes::from(false.abi_encode()));
+        asserter.push_success(&U256::ZERO);
+        asserter.push_success(&U256::ZERO);
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter);
+
+        let report = wallet
+            .scan_coins(&provider, Address::ZERO, 2, false)
+            .await
+            .unwrap();
+
+        assert_eq!(report.scanned, 5);
+        assert_eq!(report.coins.len(), 2);
+        assert_eq!(report.coins[0].index, 0);
+        assert!(report.coins[0].minted);
+        assert_eq!(report.coins[1].index, 2);
+        assert!(!report.coins[1].minted);
+
+        let coins = wallet.get_coins();
+        assert_eq!(coins.len(), 2);
+        assert_eq!(coins[0].state, CoinState::Minted);
+        assert_eq!(coins[1].state, CoinState::Burned);
+        assert_eq!(coins[1].amount, U256::from(7u64));
+        assert_eq!(coins[1].salt, wallet.derive_coin_salt(2).unwrap());
+        assert_eq!(wallet.next_burn_index(), 3);
+        assert_eq!(wallet.burn_addresses().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_scan_does_not_duplicate_known_coins() {
+        let mut wallet = Wallet::from_entropy(&[4u8; NOTE_SIZE], DerivationScheme::Hierarchical);
+        wallet.set_chain_id(1);
+        let known = wallet
+            .create_coin_for_burn(0, U256::from(5u64), true)
+            .unwrap();
+        wallet.data.coins.push(known);
+
+        let asserter = Asserter::new();
+        asserter.push_success(&U256::from(5u64));
+        asserter.push_success(&Bytes::from(true.abi_encode()));
+        asserter.push_success(&U256::ZERO);
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter);
+
+        let report = wallet
+            .scan_coins(&provider, Address::ZERO, 1, false)
+            .await
+            .unwrap();
+
+        assert!(!report.coins[0].added);
+        assert_eq!(wallet.coin_count(), 1);
+        assert!(wallet.get_coins()[0].is_minted());
+        assert!(wallet.get_coins()[0].encrypted);
+    }
+}
@@ -0,0 +1,512 @@
+//! Shamir secret-sharing backup of wallet entropy
+//!
+//! The 32 bytes of wallet entropy are split into `n` shares so that any `t` of
+//! them rebuild it, while fewer reveal nothing about it. Each share is a
+//! bech32m string with the `dmshare` human-readable part:
+//!
+//! ```text
+//! version (1) | threshold (1) | index (1) | derivation (1) | backup id (4) | value (36)
+//! ```
+//!
+//! Sharing works byte by byte over GF(256): every byte of the secret is the
+//! constant term of a polynomial of degree `t - 1` whose other coefficients
+//! are drawn uniformly at random, and share `i` holds the polynomials
+//! evaluated at `i`.
+//!
+//! The shared secret is the entropy followed by a 4-byte checksum of it, so
+//! rebuilt entropy is checked without any share carrying a hash of the
+//! entropy. The backup id is random and only tells the shares of different
+//! backups apart, which are then refused. The bech32m checksum catches typos
+//! in a single share. Unlike a mnemonic, shares record the derivation scheme,
+//! so legacy wallets restore without extra flags.
+
+use bech32::{primitives::decode::CheckedHrpstring, Bech32m, Hrp};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use zeroize::Zeroizing;
+
+use crate::derivation::DerivationScheme;
+use crate::store::{MemoryStore, WalletStore};
+use crate::{Wallet, NOTE_SIZE};
+
+/// Human-readable part of encoded shares
+pub const SHARE_HRP: &str = "dmshare";
+
+/// Current version of the share payload
+pub const SHARE_FORMAT_VERSION: u8 = 2;
+
+/// Number of burn addresses compared when verifying shares against a wallet
+pub const SHARE_CHECK_ADDRESSES: u64 = 5;
+
+/// Domain separation tag of the entropy checksum
+const CHECKSUM_TAG: &[u8] = b"darkmint/share-checksum/v1";
+
+const CHECKSUM_SIZE: usize = 4;
+
+const BACKUP_ID_SIZE: usize = 4;
+
+/// Size of the shared secret: entropy and its checksum
+const SECRET_SIZE: usize = NOTE_SIZE + CHECKSUM_SIZE;
+
+const SHARE_PAYLOAD_SIZE: usize = 4 + BACKUP_ID_SIZE + SECRET_SIZE;
+
+/// Entropy rebuilt from shares, with the derivation scheme of its wallet
+pub type RebuiltEntropy = (Zeroizing<[u8; NOTE_SIZE]>, DerivationScheme);
+
+/// One share of the wallet entropy
+#[derive(Clone, PartialEq, Eq)]
+pub struct EntropyShare {
+    threshold: u8,
+    index: u8,
+    derivation: DerivationScheme,
+    backup_id: [u8; BACKUP_ID_SIZE],
+    value: Zeroizing<[u8; SECRET_SIZE]>,
+}
+
+impl fmt::Debug for EntropyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntropyShare")
+            .field("threshold", &self.threshold)
+            .field("index", &self.index)
+            .field("derivation", &self.derivation)
+            .field("backup_id", &hex::encode(self.backup_id))
+            .finish_non_exhaustive()
+    }
+}
+
+impl EntropyShare {
+    /// Number of shares needed to rebuild the entropy
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Position of the share, from 1
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Burn key derivation scheme of the wallet
+    pub fn derivation(&self) -> DerivationScheme {
+        self.derivation
+    }
+
+    /// Hex id of the backup, random and the same for all its shares
+    pub fn backup_id(&self) -> String {
+        hex::encode(self.backup_id)
+    }
+
+    /// Encode the share as a `dmshare` string
+    ///
+    /// # Returns
+    /// * Result containing the lowercase bech32m share or an error
+    pub fn encode(&self) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+        let derivation = match self.derivation {
+            DerivationScheme::Legacy => 0,
+            DerivationScheme::Hierarchical => 1,
+        };
+        let mut payload = Zeroizing::new(Vec::with_capacity(SHARE_PAYLOAD_SIZE));
+        payload.extend_from_slice(&[SHARE_FORMAT_VERSION, self.threshold, self.index, derivation]);
+        payload.extend_from_slice(&self.backup_id);
+        payload.extend_from_slice(self.value.as_ref());
+
+        Ok(Zeroizing::new(bech32::encode::<Bech32m>(
+            Hrp::parse(SHARE_HRP)?,
+            &payload,
+        )?))
+    }
+
+    /// Decode a `dmshare` string, lowercase or uppercase
+    ///
+    /// # Arguments
+    /// * `text` - Encoded share
+    ///
+    /// # Returns
+    /// * Result containing the share or an error
+    pub fn decode(text: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let checked = CheckedHrpstring::new::<Bech32m>(text.trim())
+            .map_err(|e| format!("Invalid share: {}", e))?;
+        if checked.hrp() != Hrp::parse(SHARE_HRP)? {
+            return Err(format!("Not a share, expected the '{}' prefix", SHARE_HRP).into());
+        }
+        let payload = Zeroizing::new(checked.byte_iter().collect::<Vec<u8>>());
+
+        if payload.first() != Some(&SHARE_FORMAT_VERSION) {
+            return Err("Unsupported share version".into());
+        }
+        if payload.len() != SHARE_PAYLOAD_SIZE {
+            return Err("Malformed share".into());
+        }
+        let (threshold, index) = (payload[1], payload[2]);
+        if threshold < 2 || index == 0 {
+            return Err("Malformed share".into());
+        }
+        let derivation = match payload[3] {
+            0 => DerivationScheme::Legacy,
+            1 => DerivationScheme::Hierarchical,
+            _ => return Err("Share has an unknown derivation scheme".into()),
+        };
+
+        let mut value = Zeroizing::new([0u8; SECRET_SIZE]);
+        value.copy_from_slice(&payload[4 + BACKUP_ID_SIZE..]);
+        Ok(Self {
+            threshold,
+            index,
+            derivation,
+            backup_id: payload[4..4 + BACKUP_ID_SIZE].try_into()?,
+            value,
+        })
+    }
+}
+
+/// Compute the checksum of wallet entropy that is shared along with it
+fn checksum(entropy: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let digest = Sha256::new()
+        .chain_update(CHECKSUM_TAG)
+        .chain_update(entropy)
+        .finalize();
+    let mut checksum = [0u8; CHECKSUM_SIZE];
+    checksum.copy_from_slice(&digest[..CHECKSUM_SIZE]);
+    checksum
+}
+
+/// Multiply in GF(256) with the AES polynomial, without secret-dependent branches
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Invert a non-zero element of GF(256), as `a^254`
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut power = a;
+    // 254 = 0b11111110
+    for _ in 1..8 {
+        power = gf_mul(power, power);
+        result = gf_mul(result, power);
+    }
+    result
+}
+
+/// Evaluate the sharing polynomials of every secret byte at `x`
+fn evaluate(coefficients: &[Zeroizing<[u8; SECRET_SIZE]>], x: u8) -> Zeroizing<[u8; SECRET_SIZE]> {
+    let mut value = Zeroizing::new([0u8; SECRET_SIZE]);
+    // Horner's rule, from the highest degree down to the secret
+    for coefficient in coefficients.iter().rev() {
+        for (byte, c) in value.iter_mut().zip(coefficient.iter()) {
+            *byte = gf_mul(*byte, x) ^ c;
+        }
+    }
+    value
+}
+
+/// Split wallet entropy into `count` shares, any `threshold` of which rebuild it
+///
+/// # Arguments
+/// * `entropy` - Wallet entropy
+/// * `derivation` - Burn key derivation scheme of the wallet
+/// * `threshold` - Number of shares needed, at least 2
+/// * `count` - Number of shares to create, at least `threshold`
+///
+/// # Returns
+/// * Result containing the shares, indexed from 1, or an error
+pub fn split_entropy(
+    entropy: &[u8; NOTE_SIZE],
+    derivation: DerivationScheme,
+    threshold: u8,
+    count: u8,
+) -> Result<Vec<EntropyShare>, Box<dyn std::error::Error>> {
+    if threshold < 2 {
+        return Err(
+            "The threshold must be at least 2, use the mnemonic for a single backup".into(),
+        );
+    }
+    if count < threshold {
+        return Err(format!("Cannot require {} shares out of only {}", threshold, count).into());
+    }
+
+    let mut secret = Zeroizing::new([0u8; SECRET_SIZE]);
+    secret[..NOTE_SIZE].copy_from_slice(entropy);
+    secret[NOTE_SIZE..].copy_from_slice(&checksum(entropy));
+
+    // The secret is the constant term, the other coefficients are uniform
+    let mut coefficients = vec![secret];
+    for _ in 1..threshold {
+        let mut coefficient = Zeroizing::new([0u8; SECRET_SIZE]);
+        OsRng.fill_bytes(coefficient.as_mut());
+        coefficients.push(coefficient);
+    }
+
+    let mut backup_id = [0u8; BACKUP_ID_SIZE];
+    OsRng.fill_bytes(&mut backup_id);
+
+    let shares = (1..=count)
+        .map(|index| EntropyShare {
+            threshold,
+            index,
+            derivation,
+            backup_id,
+            value: evaluate(&coefficients, index),
+        })
+        .collect();
+    Ok(shares)
+}
+
+/// Rebuild wallet entropy from shares
+///
+/// # Arguments
+/// * `shares` - At least `threshold` distinct shares of the same backup
+///
+/// # Returns
+/// * Result containing the entropy and derivation scheme, or an error
+pub fn combine_shares(
+    shares: &[EntropyShare],
+) -> Result<RebuiltEntropy, Box<dyn std::error::Error>> {
+    let first = shares.first().ok_or("No shares given")?;
+    let mut distinct = BTreeMap::new();
+    for share in shares {
+        if share.threshold != first.threshold
+            || share.derivation != first.derivation
+            || share.backup_id != first.backup_id
+        {
+            return Err("Shares belong to different backups".into());
+        }
+        if let Some(known) = distinct.insert(share.index, share) {
+            if known.value != share.value {
+                return Err(format!("Two different shares have index {}", share.index).into());
+            }
+        }
+    }
+    if distinct.len() < first.threshold as usize {
+        return Err(format!(
+            "{} shares are needed, got {}",
+            first.threshold,
+            distinct.len()
+        )
+        .into());
+    }
+
+    // Lagrange interpolation at 0 over `threshold` of the shares
+    let used: Vec<&EntropyShare> = distinct
+        .values()
+        .take(first.threshold as usize)
+        .copied()
+        .collect();
+    let mut secret = Zeroizing::new([0u8; SECRET_SIZE]);
+    for share in &used {
+        let mut basis = 1;
+        for other in &used {
+            if other.index != share.index {
+                basis = gf_mul(
+                    basis,
+                    gf_mul(other.index, gf_inv(other.index ^ share.index)),
+                );
+            }
+        }
+        for (byte, y) in secret.iter_mut().zip(share.value.iter()) {
+            *byte ^= gf_mul(basis, *y);
+        }
+    }
+
+    let mut entropy = Zeroizing::new([0u8; NOTE_SIZE]);
+    entropy.copy_from_slice(&secret[..NOTE_SIZE]);
+    if checksum(entropy.as_ref()) != secret[NOTE_SIZE..] {
+        return Err("Shares do not rebuild the backed up entropy".into());
+    }
+    Ok((entropy, first.derivation))
+}
+
+impl<S: WalletStore> Wallet<S> {
+    /// Split the wallet entropy into Shamir shares
+    ///
+    /// The shares are checked before they are returned: the first and the
+    /// last `threshold` of them must each rebuild a wallet deriving the same
+    /// burn addresses as this one. Hierarchical wallets need the chain id.
+    ///
+    /// # Arguments
+    /// * `threshold` - Number of shares needed to rebuild the wallet
+    /// * `count` - Number of shares to create
+    ///
+    /// # Returns
+    /// * Result containing the shares or an error
+    pub fn split_entropy(
+        &self,
+        threshold: u8,
+        count: u8,
+    ) -> Result<Vec<EntropyShare>, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+
+        let mut entropy = Zeroizing::new([0u8; NOTE_SIZE]);
+        hex::decode_to_slice(&self.data.entropy, entropy.as_mut())?;
+        let shares = split_entropy(&entropy, self.data.derivation, threshold, count)?;
+
+        let threshold = threshold as usize;
+        self.verify_shares(&shares[..threshold])?;
+        self.verify_shares(&shares[shares.len() - threshold..])?;
+        Ok(shares)
+    }
+
+    /// Check that shares rebuild a wallet deriving the same burn addresses
+    ///
+    /// Compares the first `SHARE_CHECK_ADDRESSES` burn addresses. Hierarchical
+    /// wallets need the chain id.
+    ///
+    /// # Arguments
+    /// * `shares` - Shares to check
+    ///
+    /// # Returns
+    /// * Result indicating success, or an error describing the mismatch
+    pub fn verify_shares(&self, shares: &[EntropyShare]) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+
+        let (entropy, derivation) = combine_shares(shares)?;
+        if derivation != self.data.derivation {
+            return Err(format!(
+                "Shares are for a {} wallet, this wallet uses {} derivation",
+                derivation, self.data.derivation
+            )
+            .into());
+        }
+        let mut rebuilt = Wallet::<MemoryStore>::from_entropy(&entropy, derivation);
+        if let Some(chain_id) = self.chain_id {
+            rebuilt.set_chain_id(chain_id);
+        }
+
+        for index in 0..SHARE_CHECK_ADDRESSES {
+            if rebuilt.derive_burn_address(index)?.address
+                != self.derive_burn_address(index)?.address
+            {
+                return Err(format!(
+                    "Shares rebuild a wallet with a different burn address #{}",
+                    index
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore a wallet from Shamir shares and save it to `store`
+    ///
+    /// Refuses to overwrite an existing wallet. The restored wallet has no
+    /// coins; they can be rediscovered from the chain.
+    ///
+    /// # Arguments
+    /// * `store` - Empty store for the restored wallet
+    /// * `shares` - At least `threshold` shares of the same backup
+    /// * `password` - Optional password used to encrypt the restored wallet
+    ///
+    /// # Returns
+    /// * Result containing the restored wallet or an error
+    pub fn restore_from_shares(
+        store: S,
+        shares: &[EntropyShare],
+        password: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if store.exists()? {
+            return Err(format!(
+                "Wallet '{}' already exists, move it away before restoring",
+                store.location()
+            )
+            .into());
+        }
+
+        let (entropy, derivation) = combine_shares(shares)?;
+        Self::create_from_entropy(store, &entropy, derivation, password)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_wallet(seed: u8) -> Wallet<MemoryStore> {
+        let mut wallet = Wallet::from_entropy(&[seed; NOTE_SIZE], DerivationScheme::Hierarchical);
+        wallet.set_chain_id(1);
+        wallet
+    }
+
+    #[test]
+    fn test_any_threshold_shares_restore_the_wallet() {
+        let original = test_wallet(5);
+        let shares = original.split_entropy(3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert_eq!(
+            shares.iter().map(EntropyShare::index).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+
+        let picked = [shares[4].clone(), shares[1].clone(), shares[2].clone()];
+        original.verify_shares(&picked).unwrap();
+        let mut restored = Wallet::restore_from_shares(MemoryStore::new(), &picked, None).unwrap();
+        restored.set_chain_id(1);
+        assert_eq!(
+            restored.derive_burn_address(7).unwrap().address,
+            original.derive_burn_address(7).unwrap().address
+        );
+    }
+
+    #[test]
+    fn test_too_few_shares_are_refused() {
+        let shares = test_wallet(5).split_entropy(3, 5).unwrap();
+        assert!(combine_shares(&shares[..2]).is_err());
+        // A repeated share does not count twice
+        let repeated = [shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert!(combine_shares(&repeated).is_err());
+    }
+
+    #[test]
+    fn test_shares_encode_with_checksum() {
+        let shares = test_wallet(5).split_entropy(2, 3).unwrap();
+        let text = shares[1].encode().unwrap();
+        assert!(text.starts_with("dmshare1"));
+
+        let decoded = EntropyShare::decode(&text.to_uppercase()).unwrap();
+        assert_eq!(decoded, shares[1]);
+
+        let mut typo = text.to_string();
+        let last = typo.pop().unwrap();
+        typo.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(EntropyShare::decode(&typo).is_err());
+    }
+
+    #[test]
+    fn test_shares_of_different_wallets_do_not_mix() {
+        let first = test_wallet(5).split_entropy(2, 2).unwrap();
+        let second = test_wallet(6).split_entropy(2, 2).unwrap();
+
+        assert!(combine_shares(&[first[0].clone(), second[1].clone()]).is_err());
+        assert!(test_wallet(6).verify_shares(&first).is_err());
+    }
+
+    #[test]
+    fn test_gf256_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+        // 0x53 * 0xca = 1 with the AES polynomial
+        assert_eq!(gf_mul(0x53, 0xca), 1);
+    }
+
+    #[test]
+    fn test_altered_share_is_detected() {
+        let shares = test_wallet(5).split_entropy(2, 3).unwrap();
+        let mut altered = shares[0].clone();
+        altered.value[0] ^= 1;
+        assert!(combine_shares(&[altered, shares[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_threshold_must_be_reachable() {
+        let wallet = test_wallet(5);
+        assert!(wallet.split_entropy(1, 3).is_err());
+        assert!(wallet.split_entropy(4, 3).is_err());
+    }
+}
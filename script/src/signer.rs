@@ -0,0 +1,103 @@
+//! Fee payer signers
+//!
+//! Burns and mint transactions are paid by a regular Ethereum account. Its key
+//! is read from a V3 JSON keystore (as written by geth, clef or
+//! `cast wallet import`) and decrypted with a password. Raw hex private keys
+//! are only accepted when the caller explicitly allows them.
+//!
+//! `burn_cmd` and `mint_cmd` take any alloy transaction signer, so services
+//! can plug in hardware or remote signers instead.
+
+use alloy::signers::local::PrivateKeySigner;
+use std::path::Path;
+
+/// Environment variable holding the keystore password for the worker process
+pub const KEYSTORE_PASSWORD_ENV: &str = "DARKMINT_KEYSTORE_PASSWORD";
+
+/// Environment variable holding a raw fee payer key, used only when raw keys are allowed
+pub const FEE_PAYER_KEY_ENV: &str = "DARKMINT_FEE_PAYER_KEY";
+
+/// Decrypt a V3 JSON keystore
+///
+/// # Arguments
+/// * `path` - Path to the keystore file
+/// * `password` - Keystore password
+///
+/// # Returns
+/// * Result containing the signer, or an error for a wrong password or a malformed file
+pub fn decrypt_keystore(
+    path: &Path,
+    password: &str,
+) -> Result<PrivateKeySigner, Box<dyn std::error::Error>> {
+    if !path.is_file() {
+        return Err(format!("Keystore {} not found", path.display()).into());
+    }
+    PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| {
+        format!(
+            "Cannot decrypt keystore {} (wrong password?): {}",
+            path.display(),
+            e
+        )
+        .into()
+    })
+}
+
+/// Parse a raw hex private key
+///
+/// The key is never echoed back in errors.
+///
+/// # Arguments
+/// * `key` - 32-byte private key as a `0x`-prefixed hex string
+///
+/// # Returns
+/// * Result containing the signer, or an error for malformed keys
+pub fn parse_raw_key(key: &str) -> Result<PrivateKeySigner, Box<dyn std::error::Error>> {
+    let hex_key = key
+        .trim()
+        .strip_prefix("0x")
+        .ok_or("Private key must be a hex string starting with 0x")?;
+    if hex_key.len() != 64 || !hex_key.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("Private key must be 32 bytes of hex".into());
+    }
+    hex_key
+        .parse()
+        .map_err(|_| "Private key is not a valid secp256k1 key".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = hex::decode(KEY.trim_start_matches("0x")).unwrap();
+        let (signer, name) = PrivateKeySigner::encrypt_keystore(
+            dir.path(),
+            &mut rand::thread_rng(),
+            key,
+            "hunter2",
+            Some("fee-payer.json"),
+        )
+        .unwrap();
+        let path = dir.path().join(name);
+
+        let decrypted = decrypt_keystore(&path, "hunter2").unwrap();
+        assert_eq!(decrypted.address(), signer.address());
+        assert_eq!(decrypted.address(), parse_raw_key(KEY).unwrap().address());
+
+        assert!(decrypt_keystore(&path, "wrong").is_err());
+        assert!(decrypt_keystore(&dir.path().join("missing.json"), "hunter2").is_err());
+    }
+
+    #[test]
+    fn test_malformed_raw_keys_are_not_echoed() {
+        for key in [&KEY[2..], &KEY[..40], "0xzz"] {
+            let error = parse_raw_key(key).unwrap_err().to_string();
+            assert!(!error.contains(&KEY[10..20]));
+        }
+        assert!(parse_raw_key(&format!("0x{}", "0".repeat(64))).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Dry runs of burns and mints
+//!
+//! Simulates the transactions of a burn or a mint with `eth_call` and
+//! `eth_estimateGas` and reports what they would cost. Nothing is signed,
+//! broadcast or recorded in the wallet.
+
+use alloy::{
+    primitives::{keccak256, Address, Bytes, B256, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::{SolCall, SolType},
+};
+use fibonacci_lib::PublicValuesStruct;
+
+use crate::amount::Amount;
+use crate::burn::current_fees;
+use crate::mint::mint_calldata;
+use crate::pending_burn::BurnFees;
+
+sol! {
+    interface IDarkMintState {
+        function nullifiers(uint256 nullifier) external view returns (bool);
+        function publicInputHashesUsed(bytes32 publicInputHash) external view returns (bool);
+    }
+}
+
+/// Outcome of a simulated transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallOutcome {
+    /// The transaction goes through and uses about this much gas
+    Success { gas: u64 },
+    /// The transaction reverts, with the reason given by the node
+    Reverted(String),
+}
+
+impl CallOutcome {
+    /// Get the gas estimate of a transaction that goes through
+    pub fn gas(&self) -> Option<u64> {
+        match self {
+            CallOutcome::Success { gas } => Some(*gas),
+            CallOutcome::Reverted(_) => None,
+        }
+    }
+
+    /// Print the outcome of a simulated transaction
+    ///
+    /// # Arguments
+    /// * `name` - Name of the simulated call
+    pub fn print(&self, name: &str) {
+        match self {
+            CallOutcome::Success { gas } => println!("{}: succeeds, {} gas", name, gas),
+            CallOutcome::Reverted(reason) => println!("{}: reverts ({})", name, reason),
+        }
+    }
+}
+
+/// Simulate a transaction with `eth_call`, then estimate its gas
+///
+/// # Arguments
+/// * `provider` - Provider of the chain to simulate on
+/// * `tx` - Transaction to simulate, with its sender set
+///
+/// # Returns
+/// * Result containing the outcome, or an error if the node cannot be reached
+pub async fn simulate_call<P: Provider>(
+    provider: &P,
+    tx: TransactionRequest,
+) -> Result<CallOutcome, Box<dyn std::error::Error>> {
+    // Reverts come back as error responses, anything else is a transport problem
+    if let Err(e) = provider.call(tx.clone()).await {
+        return match e.as_error_resp() {
+            Some(payload) => Ok(CallOutcome::Reverted(payload.message.to_string())),
+            None => Err(e.into()),
+        };
+    }
+    match provider.estimate_gas(tx).await {
+        Ok(gas) => Ok(CallOutcome::Success { gas }),
+        Err(e) => match e.as_error_resp() {
+            Some(payload) => Ok(CallOutcome::Reverted(payload.message.to_string())),
+            None => Err(e.into()),
+        },
+    }
+}
+
+/// Compute the hash the DarkMint contract records for the public values of a mint
+///
+/// `mint` marks `keccak256(abi.encodePacked(publicInputHashes))` as used, where
+/// the public input hashes are the public values in zero-padded 32-byte words.
+///
+/// # Arguments
+/// * `public_values` - Public values committed by the proof
+///
+/// # Returns
+/// * Hash looked up in `publicInputHashesUsed`
+pub fn public_inputs_hash(public_values: &[u8]) -> B256 {
+    let mut packed = public_values.to_vec();
+    packed.resize(public_values.len().div_ceil(32) * 32, 0);
+    keccak256(packed)
+}
+
+/// Expected outcome of minting a proof
+pub struct MintSimulation {
+    /// Public values committed by the program
+    pub public_values: PublicValuesStruct,
+    /// Nullifier the mint records
+    pub nullifier: U256,
+    /// Whether the contract already recorded the nullifier
+    pub nullifier_used: bool,
+    /// Whether the contract already recorded these public values
+    pub public_values_used: bool,
+    /// Outcome of `checkHash`, if an aggregation receipt was given
+    pub check_hash: Option<CallOutcome>,
+    /// Outcome of `mint`
+    pub mint: CallOutcome,
+    /// Current network fees
+    pub fees: BurnFees,
+}
+
+impl MintSimulation {
+    /// Whether every simulated call goes through and the nullifier is unused
+    pub fn succeeds(&self) -> bool {
+        !self.nullifier_used
+            && !self.public_values_used
+            && self.mint.gas().is_some()
+            && self
+                .check_hash
+                .as_ref()
+                .is_none_or(|outcome| outcome.gas().is_some())
+    }
+
+    /// Get the most the simulated transactions cost at the current max fee
+    pub fn max_cost(&self) -> U256 {
+        let gas = self.mint.gas().unwrap_or_default()
+            + self
+                .check_hash
+                .as_ref()
+                .and_then(CallOutcome::gas)
+                .unwrap_or_default();
+        U256::from(gas) * U256::from(self.fees.max_fee_per_gas)
+    }
+
+    /// Print the public values, nullifier status and expected costs
+    pub fn print(&self) {
+        println!("Public values:");
+        println!(
+            "  burn_preimage: 0x{}",
+            hex::encode(&self.public_values.burn_preimage)
+        );
+        println!("  commit_upper: {}", self.public_values.commit_upper);
+        println!(
+            "  encrypted_balance: {}",
+            self.public_values.encrypted_balance
+        );
+        println!("  nullifier: {}", self.public_values.nullifier);
+        println!("  encrypted: {}", self.public_values.encrypted);
+
+        println!(
+            "Nullifier {}: {}",
+            self.nullifier,
+            if self.nullifier_used {
+                "already used"
+            } else {
+                "unused"
+            }
+        );
+        if self.public_values_used {
+            println!("Public values: already minted");
+        }
+        match &self.check_hash {
+            Some(outcome) => outcome.print("checkHash"),
+            None => println!("checkHash: skipped, needs the aggregation receipt of the proof"),
+        }
+        self.mint.print("mint");
+        println!(
+            "Expected cost: up to {} at a max fee of {} wei",
+            Amount::from_wei(self.max_cost()),
+            self.fees.max_fee_per_gas
+        );
+    }
+}
+
+/// Simulate minting the coin of a proof
+///
+/// # Arguments
+/// * `provider_url` - RPC provider of the DarkMint chain
+/// * `contract` - DarkMint contract address
+/// * `from` - Fee payer
+/// * `recipient` - Account receiving the minted tokens
+/// * `amount` - Amount to mint in wei
+/// * `public_values` - Public values committed by the proof
+/// * `check_hash_input` - Calldata of `checkHash`, once the proof is aggregated
+///
+/// # Returns
+/// * Result containing the expected outcome or an error
+pub async fn simulate_mint(
+    provider_url: &str,
+    contract: Address,
+    from: Address,
+    recipient: Address,
+    amount: U256,
+    public_values: &[u8],
+    check_hash_input: Option<Bytes>,
+) -> Result<MintSimulation, Box<dyn std::error::Error>> {
+    let provider = ProviderBuilder::new().connect(provider_url).await?;
+    let decoded = <PublicValuesStruct as SolType>::abi_decode(public_values)?;
+    let (mint_input, nullifier) = mint_calldata(recipient, amount, public_values)?;
+
+    let nullifier_used = view_call(
+        &provider,
+        contract,
+        IDarkMintState::nullifiersCall { nullifier }.abi_encode(),
+    )
+    .await?;
+    let public_values_used = view_call(
+        &provider,
+        contract,
+        IDarkMintState::publicInputHashesUsedCall {
+            publicInputHash: public_inputs_hash(public_values),
+        }
+        .abi_encode(),
+    )
+    .await?;
+
+    let check_hash = match check_hash_input {
+        Some(input) => Some(
+            simulate_call(
+                &provider,
+                TransactionRequest::default()
+                    .from(from)
+                    .to(contract)
+                    .input(input.into()),
+            )
+            .await?,
+        ),
+        None => None,
+    };
+    let mint = simulate_call(
+        &provider,
+        TransactionRequest::default()
+            .from(from)
+            .to(contract)
+            .input(mint_input.into()),
+    )
+    .await?;
+
+    Ok(MintSimulation {
+        public_values: decoded,
+        nullifier,
+        nullifier_used,
+        public_values_used,
+        check_hash,
+        mint,
+        fees: current_fees(&provider, false).await?,
+    })
+}
+
+/// Call a view function of the contract that returns a bool
+async fn view_call<P: Provider>(
+    provider: &P,
+    contract: Address,
+    input: Vec<u8>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let result = provider
+        .call(
+            TransactionRequest::default()
+                .to(contract)
+                .input(input.into()),
+        )
+        .await?;
+    Ok(result.len() >= 32 && result[31] == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mint::IDarkMintMinter;
+
+    #[test]
+    fn test_public_inputs_hash_matches_mint_calldata() {
+        let public_values = PublicValuesStruct::abi_encode(&PublicValuesStruct {
+            burn_preimage: vec![7u8; 32].into(),
+            commit_upper: 1,
+            encrypted_balance: 2,
+            nullifier: 3,
+            encrypted: false,
+        });
+        // Odd length, so the last word is padded
+        let mut public_values = public_values.to_vec();
+        public_values.extend_from_slice(&[1, 2, 3]);
+
+        let (input, nullifier) =
+            mint_calldata(Address::ZERO, U256::from(1), &public_values).unwrap();
+        let call = IDarkMintMinter::mintCall::abi_decode(&input).unwrap();
+        let packed: Vec<u8> = call
+            .publicInputHashes
+            .iter()
+            .flat_map(|hash| hash.0)
+            .collect();
+
+        assert_eq!(nullifier, U256::from(3));
+        assert_eq!(public_inputs_hash(&public_values), keccak256(packed));
+    }
+}
@@ -0,0 +1,449 @@
+//! SQLite wallet store
+//!
+//! Keeps a wallet in an embedded database, for services that need to query
+//! coins. The `wallet` table holds the wallet in the wallet file format,
+//! encrypted like a wallet file when the wallet has a password.
+//!
+//! Saves of plaintext wallets also refresh the `coins` index (burn index,
+//! state, burn address, transaction hashes and error) and append state changes
+//! to `coin_history`, so that the mint pipeline can be queried with SQL;
+//! entropy, salts and amounts are only kept in the wallet record. Coins
+//! without a burn index (created with a random salt or imported from notes)
+//! are not indexed.
+//!
+//! The index would link burn addresses to mint transactions in the clear, so
+//! it is not kept for encrypted wallets: their saves empty both tables.
+//! Deleted rows and replaced wallet records are overwritten on disk
+//! (`secure_delete`).
+
+use alloy::primitives::{Address, B256};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::store::{decode_wallet, encode_wallet, StoredWallet, WalletStore};
+use crate::wallet_file::{self, WalletKey, WalletLock};
+use crate::{CoinState, WalletData};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS wallet (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    content TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS coins (
+    burn_index INTEGER PRIMARY KEY,
+    state TEXT NOT NULL,
+    burn_address TEXT,
+    burn_tx_hash TEXT,
+    mint_tx_hash TEXT,
+    error TEXT,
+    updated_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS coins_state ON coins (state);
+CREATE INDEX IF NOT EXISTS coins_burn_address ON coins (burn_address);
+CREATE TABLE IF NOT EXISTS coin_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    burn_index INTEGER NOT NULL,
+    state TEXT NOT NULL,
+    error TEXT,
+    recorded_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS coin_history_burn_index ON coin_history (burn_index);
+";
+
+const COIN_COLUMNS: &str =
+    "burn_index, state, burn_address, burn_tx_hash, mint_tx_hash, error, updated_at";
+
+/// Coin as listed in the coin index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedCoin {
+    /// Index of the burn address the coin was minted from
+    pub burn_index: u64,
+    /// Position in the mint pipeline
+    pub state: CoinState,
+    /// Burn address
+    pub burn_address: Option<Address>,
+    /// Burn transaction hash
+    pub burn_tx_hash: Option<B256>,
+    /// Mint transaction hash
+    pub mint_tx_hash: Option<B256>,
+    /// Error of a failed coin
+    pub error: Option<String>,
+    /// Unix time of the last change
+    pub updated_at: u64,
+}
+
+/// State change recorded in the coin history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoinEvent {
+    /// Index of the burn address the coin was minted from
+    pub burn_index: u64,
+    /// State the coin moved to
+    pub state: CoinState,
+    /// Error of a failed coin
+    pub error: Option<String>,
+    /// Unix time the change was saved
+    pub recorded_at: u64,
+}
+
+/// Wallet stored in an SQLite database
+///
+/// The database file is locked for as long as the store is alive.
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Connection,
+    path: PathBuf,
+    _lock: WalletLock,
+}
+
+/// Current Unix time in seconds
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// Parse an optional text column
+fn parse_column<T>(row: &Row, index: usize) -> rusqlite::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    row.get::<_, Option<String>>(index)?
+        .map(|text| {
+            text.parse().map_err(|e: T::Err| {
+                rusqlite::Error::FromSqlConversionFailure(index, Type::Text, e.into())
+            })
+        })
+        .transpose()
+}
+
+/// Parse a non-null coin state column
+fn parse_state(row: &Row, index: usize) -> rusqlite::Result<CoinState> {
+    parse_column(row, index)?.ok_or(rusqlite::Error::InvalidColumnType(
+        index,
+        "state".to_string(),
+        Type::Null,
+    ))
+}
+
+fn indexed_coin(row: &Row) -> rusqlite::Result<IndexedCoin> {
+    Ok(IndexedCoin {
+        burn_index: row.get::<_, i64>(0)? as u64,
+        state: parse_state(row, 1)?,
+        burn_address: parse_column(row, 2)?,
+        burn_tx_hash: parse_column(row, 3)?,
+        mint_tx_hash: parse_column(row, 4)?,
+        error: row.get(5)?,
+        updated_at: row.get::<_, i64>(6)? as u64,
+    })
+}
+
+impl SqliteStore {
+    /// Open or create a wallet database, locking it
+    ///
+    /// # Arguments
+    /// * `path` - Location of the database file
+    ///
+    /// # Returns
+    /// * Result containing the store, or an error if the wallet is in use
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let lock = WalletLock::acquire(path)?;
+        if !path.exists() {
+            // Create the file first so that SQLite keeps its owner-only permissions
+            wallet_file::write_private(path, b"")?;
+        }
+
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "secure_delete", true)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn,
+            path: path.to_path_buf(),
+            _lock: lock,
+        })
+    }
+
+    /// Get the location of the database file
+    ///
+    /// # Returns
+    /// * Path of the database file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the stored wallet record
+    fn content(&self) -> Result<String, Box<dyn std::error::Error>> {
+        self.conn
+            .query_row("SELECT content FROM wallet WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?
+            .ok_or_else(|| format!("No wallet found at '{}'", self.path.display()).into())
+    }
+
+    /// List the indexed coins
+    ///
+    /// The index is empty for encrypted wallets.
+    ///
+    /// # Returns
+    /// * Result containing the coins ordered by burn index, or an error
+    pub fn coins(&self) -> Result<Vec<IndexedCoin>, Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {} FROM coins ORDER BY burn_index",
+            COIN_COLUMNS
+        ))?;
+        let coins = statement
+            .query_map([], indexed_coin)?
+            .collect::<Result<_, _>>()?;
+        Ok(coins)
+    }
+
+    /// List the indexed coins in a state
+    ///
+    /// # Arguments
+    /// * `state` - Pipeline state to look for
+    ///
+    /// # Returns
+    /// * Result containing the coins ordered by burn index, or an error
+    pub fn coins_in_state(
+        &self,
+        state: CoinState,
+    ) -> Result<Vec<IndexedCoin>, Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {} FROM coins WHERE state = ?1 ORDER BY burn_index",
+            COIN_COLUMNS
+        ))?;
+        let coins = statement
+            .query_map([state.to_string()], indexed_coin)?
+            .collect::<Result<_, _>>()?;
+        Ok(coins)
+    }
+
+    /// Get the state changes of a coin
+    ///
+    /// # Arguments
+    /// * `burn_index` - Index of the burn address the coin was minted from
+    ///
+    /// # Returns
+    /// * Result containing the changes, oldest first, or an error
+    pub fn history(&self, burn_index: u64) -> Result<Vec<CoinEvent>, Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT burn_index, state, error, recorded_at FROM coin_history
+             WHERE burn_index = ?1 ORDER BY id",
+        )?;
+        let events = statement
+            .query_map([burn_index as i64], |row| {
+                Ok(CoinEvent {
+                    burn_index: row.get::<_, i64>(0)? as u64,
+                    state: parse_state(row, 1)?,
+                    error: row.get(2)?,
+                    recorded_at: row.get::<_, i64>(3)? as u64,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(events)
+    }
+}
+
+impl WalletStore for SqliteStore {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn exists(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM wallet", [], |row| row.get(0))?;
+        Ok(count > 0)
+    }
+
+    fn is_encrypted(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(wallet_file::is_encrypted(&self.content()?))
+    }
+
+    fn load(&self, password: Option<&str>) -> Result<StoredWallet, Box<dyn std::error::Error>> {
+        decode_wallet(&self.content()?, password)
+    }
+
+    fn save(
+        &mut self,
+        data: &WalletData,
+        key: Option<&WalletKey>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = encode_wallet(data, key)?;
+        let now = now();
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO wallet (id, content, updated_at) VALUES (1, ?1, ?2)
+             ON CONFLICT (id) DO UPDATE SET content = excluded.content, updated_at = excluded.updated_at",
+            params![content.as_str(), now],
+        )?;
+
+        if key.is_some() {
+            tx.execute_batch("DELETE FROM coins; DELETE FROM coin_history;")?;
+            tx.commit()?;
+            return Ok(());
+        }
+
+        let mut indexed = HashSet::new();
+        for coin in data.coins() {
+            let Some(burn_index) = coin.burn_index else {
+                continue;
+            };
+            indexed.insert(burn_index);
+
+            let previous = tx
+                .query_row(
+                    &format!("SELECT {} FROM coins WHERE burn_index = ?1", COIN_COLUMNS),
+                    [burn_index as i64],
+                    indexed_coin,
+                )
+                .optional()?;
+            let unchanged = previous.as_ref().is_some_and(|previous| {
+                previous.state == coin.state
+                    && previous.burn_address == coin.burn_address
+                    && previous.burn_tx_hash == coin.burn_tx_hash
+                    && previous.mint_tx_hash == coin.mint_tx_hash
+                    && previous.error == coin.error
+            });
+            if unchanged {
+                continue;
+            }
+
+            let state = coin.state.to_string();
+            if previous.map(|previous| previous.state) != Some(coin.state) {
+                tx.execute(
+                    "INSERT INTO coin_history (burn_index, state, error, recorded_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![burn_index as i64, state, coin.error, now],
+                )?;
+            }
+            tx.execute(
+                "INSERT INTO coins (burn_index, state, burn_address, burn_tx_hash, mint_tx_hash, error, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT (burn_index) DO UPDATE SET
+                     state = excluded.state,
+                     burn_address = excluded.burn_address,
+                     burn_tx_hash = excluded.burn_tx_hash,
+                     mint_tx_hash = excluded.mint_tx_hash,
+                     error = excluded.error,
+                     updated_at = excluded.updated_at",
+                params![
+                    burn_index as i64,
+                    state,
+                    coin.burn_address.map(|address| address.to_string()),
+                    coin.burn_tx_hash.map(|hash| hash.to_string()),
+                    coin.mint_tx_hash.map(|hash| hash.to_string()),
+                    coin.error,
+                    now
+                ],
+            )?;
+        }
+
+        // Drop coins that were removed from the wallet
+        let stale = {
+            let mut statement = tx.prepare("SELECT burn_index FROM coins")?;
+            let rows = statement
+                .query_map([], |row| row.get::<_, i64>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.into_iter()
+                .filter(|index| !indexed.contains(&(*index as u64)))
+                .collect::<Vec<_>>()
+        };
+        for index in stale {
+            tx.execute("DELETE FROM coins WHERE burn_index = ?1", [index])?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derivation::DerivationScheme;
+    use crate::{Coin, Wallet, NOTE_SIZE};
+    use alloy::primitives::U256;
+
+    fn burned_coin(burn_index: u64) -> Coin {
+        let mut coin = Coin::new(U256::from(10), U256::from(burn_index + 1), false);
+        coin.burn_index = Some(burn_index);
+        coin.burn_address = Some(Address::repeat_byte(burn_index as u8 + 1));
+        coin
+    }
+
+    #[test]
+    fn test_sqlite_store_indexes_coins() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.db");
+
+        let mut wallet =
+            Wallet::create_with_store(SqliteStore::open(&path).unwrap(), None).unwrap();
+        wallet.add_coin(burned_coin(0)).unwrap();
+        wallet.add_coin(burned_coin(1)).unwrap();
+        wallet
+            .update_coin(1, |coin| coin.transition(CoinState::Proving))
+            .unwrap();
+
+        let store = wallet.store();
+        assert_eq!(store.coins().unwrap().len(), 2);
+        let proving = store.coins_in_state(CoinState::Proving).unwrap();
+        assert_eq!(proving.len(), 1);
+        assert_eq!(proving[0].burn_index, 1);
+        assert_eq!(proving[0].burn_address, Some(Address::repeat_byte(2)));
+
+        let states: Vec<_> = store
+            .history(1)
+            .unwrap()
+            .into_iter()
+            .map(|event| event.state)
+            .collect();
+        assert_eq!(states, vec![CoinState::Burned, CoinState::Proving]);
+
+        drop(wallet);
+        let reopened = Wallet::open_with_store(SqliteStore::open(&path).unwrap(), None).unwrap();
+        assert_eq!(reopened.get_coins().len(), 2);
+        assert_eq!(reopened.get_coins()[1].state, CoinState::Proving);
+    }
+
+    #[test]
+    fn test_sqlite_store_encrypts_wallet_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = SqliteStore::open(&dir.path().join("wallet.db")).unwrap();
+        assert!(!store.exists().unwrap());
+
+        let mut wallet = Wallet::from_entropy(&[8u8; NOTE_SIZE], DerivationScheme::Legacy);
+        wallet.add_coin(burned_coin(4)).unwrap();
+        store.save(wallet.data(), None).unwrap();
+        assert_eq!(store.coins().unwrap()[0].burn_index, 4);
+
+        let key = wallet_file::KdfParams::with_costs(1024, 1, 1)
+            .derive_key("pw")
+            .unwrap();
+        store.save(wallet.data(), Some(&key)).unwrap();
+
+        assert!(store.is_encrypted().unwrap());
+        assert!(store.load(None).is_err());
+        assert!(store.load(Some("pw")).is_ok());
+        // Nothing about the coins is left in the clear
+        assert!(store.coins().unwrap().is_empty());
+        assert!(store.history(4).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sqlite_store_is_locked_while_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.db");
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert!(SqliteStore::open(&path).is_err());
+        drop(store);
+        assert!(SqliteStore::open(&path).is_ok());
+    }
+}
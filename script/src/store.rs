@@ -0,0 +1,337 @@
+//! Wallet storage backends
+//!
+//! A `Wallet` keeps its entropy and coins in memory and hands them to a
+//! `WalletStore` whenever they change. Stores decide where and how the data is
+//! kept:
+//!
+//! - `FileStore`: the encrypted wallet file, see `wallet_file`
+//! - `SqliteStore` (feature `sqlite`): an embedded database with a queryable
+//!   coin index and state history, see `sqlite_store`
+//! - `MemoryStore`: nothing leaves the process, for tests
+//!
+//! Stores exchange the wallet JSON, so schema migrations (see `migration`)
+//! and password handling work the same for every backend.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+use crate::wallet_file::{self, WalletKey, WalletLock};
+use crate::WalletData;
+
+/// Wallet content read from a store
+pub struct StoredWallet {
+    /// Wallet JSON, in the layout version it was saved with
+    pub json: Zeroizing<Vec<u8>>,
+    /// Key that decrypted the wallet, `None` for plaintext wallets
+    pub key: Option<WalletKey>,
+}
+
+/// Backend persisting the content of a wallet
+pub trait WalletStore: fmt::Debug {
+    /// Describe where the wallet is stored, for messages
+    fn location(&self) -> String;
+
+    /// Check whether the store holds a wallet
+    fn exists(&self) -> Result<bool, Box<dyn std::error::Error>>;
+
+    /// Check whether the stored wallet is password protected
+    fn is_encrypted(&self) -> Result<bool, Box<dyn std::error::Error>>;
+
+    /// Read the wallet, decrypting it with the password if it is encrypted
+    ///
+    /// # Arguments
+    /// * `password` - Wallet password, required for encrypted wallets
+    ///
+    /// # Returns
+    /// * Result containing the wallet JSON and key, or an error
+    fn load(&self, password: Option<&str>) -> Result<StoredWallet, Box<dyn std::error::Error>>;
+
+    /// Persist the wallet, encrypted under `key` when one is given
+    ///
+    /// # Arguments
+    /// * `data` - Wallet content
+    /// * `key` - Key derived from the wallet password, `None` for plaintext
+    ///
+    /// # Returns
+    /// * Result indicating success or error
+    fn save(
+        &mut self,
+        data: &WalletData,
+        key: Option<&WalletKey>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Persist the wallet under a new key, leaving no copy under the old one
+    ///
+    /// Used when a password is set or changed: backups of earlier versions
+    /// would still open with the old password, or be plaintext.
+    ///
+    /// # Arguments
+    /// * `data` - Wallet content
+    /// * `key` - Key derived from the new wallet password
+    ///
+    /// # Returns
+    /// * Result indicating success or error
+    fn save_rekeyed(
+        &mut self,
+        data: &WalletData,
+        key: &WalletKey,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.save(data, Some(key))
+    }
+}
+
+/// Serialize wallet data in the wallet file format
+///
+/// # Arguments
+/// * `data` - Wallet content
+/// * `key` - Key sealing the content, `None` for plaintext JSON
+///
+/// # Returns
+/// * Result containing the file content or an error
+pub(crate) fn encode_wallet(
+    data: &WalletData,
+    key: Option<&WalletKey>,
+) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    let json = Zeroizing::new(serde_json::to_string_pretty(data)?);
+    match key {
+        Some(key) => Ok(Zeroizing::new(wallet_file::encrypt(json.as_bytes(), key)?)),
+        None => Ok(json),
+    }
+}
+
+/// Read content in the wallet file format
+///
+/// # Arguments
+/// * `content` - File content, encrypted container or plaintext JSON
+/// * `password` - Wallet password, required for encrypted content
+///
+/// # Returns
+/// * Result containing the wallet JSON and key, or an error
+pub(crate) fn decode_wallet(
+    content: &str,
+    password: Option<&str>,
+) -> Result<StoredWallet, Box<dyn std::error::Error>> {
+    if !wallet_file::is_encrypted(content) {
+        return Ok(StoredWallet {
+            json: Zeroizing::new(content.as_bytes().to_vec()),
+            key: None,
+        });
+    }
+
+    let password = password.ok_or("Wallet is encrypted, the wallet password is needed")?;
+    let (json, key) = wallet_file::decrypt(content, password)?;
+    Ok(StoredWallet {
+        json,
+        key: Some(key),
+    })
+}
+
+/// Wallet stored in a single file
+///
+/// The file is locked for as long as the store is alive and is saved
+/// atomically with rotating backups.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    _lock: WalletLock,
+}
+
+impl FileStore {
+    /// Open the store of a wallet file, locking it
+    ///
+    /// The file itself does not need to exist yet.
+    ///
+    /// # Arguments
+    /// * `path` - Location of the wallet file
+    ///
+    /// # Returns
+    /// * Result containing the store, or an error if the wallet is in use
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            _lock: WalletLock::acquire(path)?,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Get the location of the wallet file
+    ///
+    /// # Returns
+    /// * Path of the wallet file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl WalletStore for FileStore {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn exists(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(self.path.exists())
+    }
+
+    fn is_encrypted(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(wallet_file::is_encrypted(&fs::read_to_string(&self.path)?))
+    }
+
+    fn load(&self, password: Option<&str>) -> Result<StoredWallet, Box<dyn std::error::Error>> {
+        decode_wallet(&fs::read_to_string(&self.path)?, password)
+    }
+
+    fn save(
+        &mut self,
+        data: &WalletData,
+        key: Option<&WalletKey>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = encode_wallet(data, key)?;
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            wallet_file::create_private_dir(dir)?;
+        }
+        wallet_file::write_private_atomic(
+            &self.path,
+            content.as_bytes(),
+            wallet_file::WALLET_BACKUP_COUNT,
+        )
+    }
+
+    fn save_rekeyed(
+        &mut self,
+        data: &WalletData,
+        key: &WalletKey,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = encode_wallet(data, Some(key))?;
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            wallet_file::create_private_dir(dir)?;
+        }
+        wallet_file::write_private_atomic(&self.path, content.as_bytes(), 0)?;
+        wallet_file::remove_backups(&self.path)
+    }
+}
+
+/// Wallet kept in memory in the wallet file format
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    content: Option<Zeroizing<String>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    ///
+    /// # Returns
+    /// * Store without a wallet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a store holding existing wallet file content
+    ///
+    /// # Arguments
+    /// * `content` - Wallet file content (encrypted container or plaintext JSON)
+    ///
+    /// # Returns
+    /// * Store holding the wallet
+    pub fn with_content(content: &str) -> Self {
+        Self {
+            content: Some(Zeroizing::new(content.to_string())),
+        }
+    }
+
+    /// Get the stored content, as it would be written to a wallet file
+    ///
+    /// # Returns
+    /// * Wallet file content, if a wallet was saved
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_ref().map(|content| content.as_str())
+    }
+
+    fn stored(&self) -> Result<&str, Box<dyn std::error::Error>> {
+        self.content().ok_or_else(|| "No wallet in memory".into())
+    }
+}
+
+impl WalletStore for MemoryStore {
+    fn location(&self) -> String {
+        "memory".to_string()
+    }
+
+    fn exists(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(self.content.is_some())
+    }
+
+    fn is_encrypted(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(wallet_file::is_encrypted(self.stored()?))
+    }
+
+    fn load(&self, password: Option<&str>) -> Result<StoredWallet, Box<dyn std::error::Error>> {
+        decode_wallet(self.stored()?, password)
+    }
+
+    fn save(
+        &mut self,
+        data: &WalletData,
+        key: Option<&WalletKey>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.content = Some(encode_wallet(data, key)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derivation::DerivationScheme;
+    use crate::{Coin, Wallet, NOTE_SIZE};
+    use alloy::primitives::U256;
+
+    fn test_key() -> WalletKey {
+        wallet_file::KdfParams::with_costs(1024, 1, 1)
+            .derive_key("pw")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_memory_store_roundtrip() {
+        let mut wallet = Wallet::from_entropy(&[6u8; NOTE_SIZE], DerivationScheme::Legacy);
+        wallet
+            .add_coin(Coin::new(U256::from(5), U256::from(9), false))
+            .unwrap();
+
+        let content = wallet.store().content().unwrap().to_string();
+        let reopened = Wallet::open_with_store(MemoryStore::with_content(&content), None).unwrap();
+        assert_eq!(reopened.get_coins().len(), 1);
+        assert_eq!(reopened.get_coins()[0].amount, U256::from(5));
+    }
+
+    #[test]
+    fn test_encrypted_store_needs_password() {
+        let mut store = MemoryStore::new();
+        let data = Wallet::from_entropy(&[6u8; NOTE_SIZE], DerivationScheme::Legacy)
+            .data()
+            .clone();
+        store.save(&data, Some(&test_key())).unwrap();
+
+        assert!(store.is_encrypted().unwrap());
+        assert!(store.load(None).is_err());
+        assert!(store.load(Some("wrong")).is_err());
+        let stored = store.load(Some("pw")).unwrap();
+        assert!(stored.key.is_some());
+
+        let locked = Wallet::open_with_store(store, None).unwrap();
+        assert!(locked.is_locked());
+    }
+
+    #[test]
+    fn test_file_store_is_locked_while_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.priv");
+
+        let store = FileStore::open(&path).unwrap();
+        assert!(!store.exists().unwrap());
+        assert!(FileStore::open(&path).is_err());
+        drop(store);
+        assert!(FileStore::open(&path).is_ok());
+    }
+}
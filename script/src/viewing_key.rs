@@ -0,0 +1,553 @@
+//! Viewing keys and watch-only wallets
+//!
+//! A viewing key is an X25519 key derived from wallet entropy at
+//! `m/3'/0'/account'/0'`. Notes (see `note`) are sealed to its public key and
+//! only the viewing key can open them.
+//!
+//! A watch-only wallet holds the viewing key together with a list of burn
+//! addresses exported from the full wallet. It can follow deposits and receive
+//! sealed notes, but it never sees a burn address preimage: received notes are
+//! kept without it. The DarkMint program
+//! derives both the burn address and the nullifier from that preimage, so a
+//! watch-only wallet cannot compute nullifiers or generate mint proofs, and it
+//! cannot derive new burn addresses either: extending the list means exporting
+//! it again from the full wallet.
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::derivation::{self, DerivationPath, DerivationScheme, DEFAULT_ACCOUNT};
+use crate::note::Note;
+use crate::store::WalletStore;
+use crate::{wallet_file, Coin, CoinState, Wallet};
+
+/// Format identifier of watch-only wallet files
+pub const WATCH_ONLY_FORMAT: &str = "darkmint-watch-only";
+
+/// Default file name of an exported watch-only wallet
+pub const WATCH_ONLY_FILENAME: &str = "burnth.watch";
+
+const NONCE_SIZE: usize = 24;
+
+/// Secret key able to open sealed notes
+#[derive(Clone)]
+pub struct ViewingKey {
+    secret: Zeroizing<[u8; 32]>,
+}
+
+impl fmt::Debug for ViewingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ViewingKey")
+            .field("public_key", &hex::encode(self.public_key()))
+            .finish()
+    }
+}
+
+impl ViewingKey {
+    /// Build a viewing key from its secret bytes
+    ///
+    /// # Arguments
+    /// * `secret` - X25519 secret key
+    ///
+    /// # Returns
+    /// * Viewing key
+    pub fn from_bytes(secret: [u8; 32]) -> Self {
+        Self {
+            secret: Zeroizing::new(secret),
+        }
+    }
+
+    /// Parse a hex-encoded viewing key
+    ///
+    /// # Arguments
+    /// * `hex_secret` - Hex-encoded secret key
+    ///
+    /// # Returns
+    /// * Result containing the viewing key or an error
+    pub fn from_hex(hex_secret: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut secret = Zeroizing::new([0u8; 32]);
+        hex::decode_to_slice(hex_secret.trim_start_matches("0x"), secret.as_mut())?;
+        Ok(Self { secret })
+    }
+
+    /// Hex encoding of the secret key
+    pub fn to_hex(&self) -> Zeroizing<String> {
+        Zeroizing::new(hex::encode(self.secret.as_ref()))
+    }
+
+    /// Public key notes are encrypted to
+    ///
+    /// # Returns
+    /// * X25519 public key bytes
+    pub fn public_key(&self) -> [u8; 32] {
+        PublicKey::from(&StaticSecret::from(*self.secret)).to_bytes()
+    }
+
+    /// Decrypt a message sealed to this viewing key with `seal`
+    ///
+    /// # Arguments
+    /// * `note` - Sealed message
+    /// * `info` - HKDF info string the message was sealed with
+    ///
+    /// # Returns
+    /// * Result containing the plaintext or an error
+    pub(crate) fn open(
+        &self,
+        note: &EncryptedNote,
+        info: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, Box<dyn std::error::Error>> {
+        let secret = StaticSecret::from(*self.secret);
+        let ephemeral_public = PublicKey::from(note.ephemeral_public);
+        let recipient_public = PublicKey::from(&secret);
+        let key = note_key(
+            &secret.diffie_hellman(&ephemeral_public),
+            &ephemeral_public,
+            &recipient_public,
+            info,
+        )?;
+
+        let cipher = XChaCha20Poly1305::new_from_slice(key.as_ref())
+            .map_err(|e| format!("Invalid note key: {}", e))?;
+        let plaintext = cipher
+            .decrypt(
+                XNonce::from_slice(&note.nonce),
+                Payload {
+                    msg: &note.ciphertext,
+                    aad: &note.ephemeral_public,
+                },
+            )
+            .map_err(|_| "Note is not addressed to this viewing key or was tampered with")?;
+        Ok(Zeroizing::new(plaintext))
+    }
+}
+
+/// Message sealed to a viewing key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedNote {
+    /// Ephemeral X25519 public key of the sender
+    pub ephemeral_public: [u8; 32],
+    /// XChaCha20-Poly1305 nonce
+    pub nonce: [u8; NONCE_SIZE],
+    /// Encrypted message
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedNote {
+    /// Serialize the note as `ephemeral_public || nonce || ciphertext`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            &self.ephemeral_public[..],
+            &self.nonce[..],
+            &self.ciphertext[..],
+        ]
+        .concat()
+    }
+
+    /// Parse a note serialized with `to_bytes`
+    ///
+    /// # Arguments
+    /// * `bytes` - Serialized note
+    ///
+    /// # Returns
+    /// * Result containing the note or an error
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        if bytes.len() <= 32 + NONCE_SIZE {
+            return Err("Encrypted note is too short".into());
+        }
+        let (ephemeral_public, rest) = bytes.split_at(32);
+        let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+        Ok(Self {
+            ephemeral_public: ephemeral_public.try_into()?,
+            nonce: nonce.try_into()?,
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
+
+/// Derive the symmetric key of a note from the X25519 shared secret
+fn note_key(
+    shared: &x25519_dalek::SharedSecret,
+    ephemeral_public: &PublicKey,
+    recipient_public: &PublicKey,
+    info: &[u8],
+) -> Result<Zeroizing<[u8; 32]>, Box<dyn std::error::Error>> {
+    if !shared.was_contributory() {
+        return Err("Invalid note public key".into());
+    }
+    let salt = [
+        &ephemeral_public.as_bytes()[..],
+        &recipient_public.as_bytes()[..],
+    ]
+    .concat();
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+        .expand(info, key.as_mut())
+        .map_err(|e| format!("Note key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypt a message to a viewing key
+///
+/// The message is sealed with XChaCha20-Poly1305 under a key agreed between a
+/// fresh ephemeral X25519 key and the viewing key. `info` separates the keys
+/// of different message kinds.
+///
+/// # Arguments
+/// * `plaintext` - Message to encrypt
+/// * `recipient` - Public key of the viewing key
+/// * `info` - HKDF info string of the message kind
+///
+/// # Returns
+/// * Result containing the sealed message or an error
+pub(crate) fn seal(
+    plaintext: &[u8],
+    recipient: &[u8; 32],
+    info: &[u8],
+) -> Result<EncryptedNote, Box<dyn std::error::Error>> {
+    let mut ephemeral_bytes = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(ephemeral_bytes.as_mut());
+    let ephemeral = StaticSecret::from(*ephemeral_bytes);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let recipient_public = PublicKey::from(*recipient);
+    let key = note_key(
+        &ephemeral.diffie_hellman(&recipient_public),
+        &ephemeral_public,
+        &recipient_public,
+        info,
+    )?;
+
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    let cipher = XChaCha20Poly1305::new_from_slice(key.as_ref())
+        .map_err(|e| format!("Invalid note key: {}", e))?;
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: ephemeral_public.as_bytes(),
+            },
+        )
+        .map_err(|e| format!("Note encryption failed: {}", e))?;
+
+    Ok(EncryptedNote {
+        ephemeral_public: ephemeral_public.to_bytes(),
+        nonce,
+        ciphertext,
+    })
+}
+
+/// Deposit observed on a watched burn address
+#[derive(Debug, Clone)]
+pub struct Deposit {
+    /// Index of the burn address
+    pub index: u64,
+    /// Burn address
+    pub address: Address,
+    /// Balance of the burn address in wei
+    pub balance: U256,
+}
+
+/// Wallet holding only a viewing key and exported burn addresses
+#[derive(Serialize, Deserialize)]
+pub struct WatchOnlyWallet {
+    /// Always `WATCH_ONLY_FORMAT`
+    format: String,
+    /// Hex-encoded viewing key
+    viewing_key: String,
+    /// Derivation scheme of the wallet the addresses were exported from
+    derivation: DerivationScheme,
+    /// Chain the addresses were derived for (hierarchical wallets only)
+    chain_id: Option<u64>,
+    /// Burn addresses, in index order
+    addresses: Vec<Address>,
+    /// Coins received as notes
+    #[serde(default)]
+    coins: Vec<Coin>,
+}
+
+impl fmt::Debug for WatchOnlyWallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchOnlyWallet")
+            .field("derivation", &self.derivation)
+            .field("chain_id", &self.chain_id)
+            .field("addresses", &self.addresses)
+            .field("coins", &self.coins)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WatchOnlyWallet {
+    /// Load a watch-only wallet from file
+    ///
+    /// # Arguments
+    /// * `path` - Path of the watch-only wallet file
+    ///
+    /// # Returns
+    /// * Result containing the watch-only wallet or an error
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let wallet: WatchOnlyWallet = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if wallet.format != WATCH_ONLY_FORMAT {
+            return Err(format!("'{}' is not a watch-only wallet", path.display()).into());
+        }
+        Ok(wallet)
+    }
+
+    /// Save the watch-only wallet to file, readable by the owner only
+    ///
+    /// # Arguments
+    /// * `path` - Path of the watch-only wallet file
+    ///
+    /// # Returns
+    /// * Result indicating success or error
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let json_data = Zeroizing::new(serde_json::to_string_pretty(self)?);
+        wallet_file::write_private(path, json_data.as_bytes())
+    }
+
+    /// Get the viewing key
+    pub fn viewing_key(&self) -> Result<ViewingKey, Box<dyn std::error::Error>> {
+        ViewingKey::from_hex(&self.viewing_key)
+    }
+
+    /// Get the watched burn addresses in index order
+    pub fn addresses(&self) -> &[Address] {
+        &self.addresses
+    }
+
+    /// Get the chain the addresses were derived for, if bound to one
+    pub fn chain_id(&self) -> Option<u64> {
+        self.chain_id
+    }
+
+    /// Get the coins received as notes
+    pub fn coins(&self) -> &[Coin] {
+        &self.coins
+    }
+
+    /// Keep the coin of a note opened with this wallet's viewing key
+    ///
+    /// The burn preimage of the note is dropped, so the watch-only wallet
+    /// still cannot mint the coin. Notes of another chain than the watched
+    /// addresses are refused.
+    ///
+    /// # Arguments
+    /// * `note` - Decoded note
+    ///
+    /// # Returns
+    /// * Result containing the received coin and whether it was new to the
+    ///   wallet, or an error
+    pub fn receive_note(
+        &mut self,
+        note: &Note,
+    ) -> Result<(Coin, bool), Box<dyn std::error::Error>> {
+        if let Some(chain_id) = self.chain_id.filter(|&chain_id| chain_id != note.chain_id) {
+            return Err(format!(
+                "Note is for chain {}, the watch-only wallet watches chain {}",
+                note.chain_id, chain_id
+            )
+            .into());
+        }
+        if let Some(known) = self
+            .coins
+            .iter()
+            .find(|coin| coin.amount == note.amount && coin.salt == note.salt)
+        {
+            return Ok((known.clone(), false));
+        }
+
+        let mut coin = Coin::new(note.amount, note.salt, note.encrypted);
+        coin.chain_id = Some(note.chain_id);
+        coin.burn_address = note.burn_address;
+        if note.burn_preimage.is_none() {
+            coin.state = CoinState::Minted;
+        }
+        self.coins.push(coin.clone());
+        Ok((coin, true))
+    }
+
+    /// Query the balance of every watched burn address
+    ///
+    /// # Arguments
+    /// * `provider` - Provider connected to the chain the addresses belong to
+    ///
+    /// # Returns
+    /// * Result containing the funded addresses or an error
+    pub async fn scan_deposits<P: Provider>(
+        &self,
+        provider: &P,
+    ) -> Result<Vec<Deposit>, Box<dyn std::error::Error>> {
+        if let Some(chain_id) = self.chain_id {
+            let connected = provider.get_chain_id().await?;
+            if connected != chain_id {
+                return Err(format!(
+                    "Watch-only wallet was exported for chain {}, provider is on chain {}",
+                    chain_id, connected
+                )
+                .into());
+            }
+        }
+
+        let mut deposits = Vec::new();
+        for (index, address) in self.addresses.iter().enumerate() {
+            let balance = provider.get_balance(*address).await?;
+            if !balance.is_zero() {
+                deposits.push(Deposit {
+                    index: index as u64,
+                    address: *address,
+                    balance,
+                });
+            }
+        }
+        Ok(deposits)
+    }
+}
+
+impl<S: WalletStore> Wallet<S> {
+    /// Derive the viewing key of the wallet
+    ///
+    /// # Returns
+    /// * Result containing the viewing key or an error
+    pub fn viewing_key(&self) -> Result<ViewingKey, Box<dyn std::error::Error>> {
+        self.ensure_unlocked()?;
+
+        let entropy_bytes = Zeroizing::new(hex::decode(&self.data.entropy)?);
+        let node =
+            derivation::derive_node(&entropy_bytes, &DerivationPath::viewing(DEFAULT_ACCOUNT));
+        Ok(ViewingKey::from_bytes(*node))
+    }
+
+    /// Export a watch-only wallet with the first `count` burn addresses
+    ///
+    /// Hierarchical wallets need the chain id to be set.
+    ///
+    /// # Arguments
+    /// * `count` - Number of burn addresses to export
+    ///
+    /// # Returns
+    /// * Result containing the watch-only wallet or an error
+    pub fn export_watch_only(
+        &self,
+        count: u64,
+    ) -> Result<WatchOnlyWallet, Box<dyn std::error::Error>> {
+        let addresses = (0..count)
+            .map(|index| Ok(self.derive_burn_address(index)?.address))
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        let chain_id = match self.data.derivation {
+            DerivationScheme::Legacy => None,
+            DerivationScheme::Hierarchical => self.chain_id,
+        };
+
+        Ok(WatchOnlyWallet {
+            format: WATCH_ONLY_FORMAT.to_string(),
+            viewing_key: self.viewing_key()?.to_hex().to_string(),
+            derivation: self.data.derivation,
+            chain_id,
+            addresses,
+            coins: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use crate::NOTE_SIZE;
+    use alloy::{providers::ProviderBuilder, transports::mock::Asserter};
+
+    fn test_wallet() -> Wallet<MemoryStore> {
+        let mut wallet = Wallet::from_entropy(&[5u8; NOTE_SIZE], DerivationScheme::Hierarchical);
+        wallet.set_chain_id(1);
+        wallet
+    }
+
+    #[test]
+    fn test_seal_roundtrip() {
+        let viewing_key = test_wallet().viewing_key().unwrap();
+
+        let sealed = seal(b"opening", &viewing_key.public_key(), b"test").unwrap();
+        let parsed = EncryptedNote::from_bytes(&sealed.to_bytes()).unwrap();
+        assert_eq!(
+            viewing_key.open(&parsed, b"test").unwrap().as_slice(),
+            b"opening"
+        );
+
+        // Other viewing keys and other message kinds cannot open it
+        assert!(ViewingKey::from_bytes([8u8; 32])
+            .open(&parsed, b"test")
+            .is_err());
+        assert!(viewing_key.open(&parsed, b"other").is_err());
+    }
+
+    #[test]
+    fn test_watch_only_receives_notes_without_preimage() {
+        let mut sender = Wallet::from_entropy(&[6u8; NOTE_SIZE], DerivationScheme::Hierarchical);
+        sender.set_chain_id(1);
+        let mut coin = sender
+            .create_coin_for_burn(0, U256::from(1000u64), true)
+            .unwrap();
+        coin.burn_address = Some(sender.derive_burn_address(0).unwrap().address);
+        sender.add_coin(coin).unwrap();
+
+        let mut watch_only = test_wallet().export_watch_only(1).unwrap();
+        let text = sender
+            .export_note(0)
+            .unwrap()
+            .encode_sealed(&watch_only.viewing_key().unwrap().public_key())
+            .unwrap();
+        let note = Note::decode(&text, Some(&watch_only.viewing_key().unwrap())).unwrap();
+
+        let (received, added) = watch_only.receive_note(&note).unwrap();
+        assert!(added);
+        assert_eq!(received.state, CoinState::Burned);
+        assert_eq!(received.burn_address, note.burn_address);
+        assert!(received.burn_preimage.is_none());
+        assert!(!watch_only.receive_note(&note).unwrap().1);
+        assert_eq!(watch_only.coins().len(), 1);
+    }
+
+    #[test]
+    fn test_watch_only_has_addresses_but_no_secrets() {
+        let wallet = test_wallet();
+        let watch_only = wallet.export_watch_only(3).unwrap();
+
+        for index in 0..3 {
+            assert_eq!(
+                watch_only.addresses()[index as usize],
+                wallet.derive_burn_address(index).unwrap().address
+            );
+        }
+        assert_eq!(watch_only.chain_id(), Some(1));
+
+        let json = serde_json::to_string(&watch_only).unwrap();
+        assert!(!json.contains(&wallet.data.entropy));
+        let preimage = wallet.derive_burn_address(0).unwrap().preimage;
+        assert!(!json.contains(&preimage.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_scan_deposits_checks_chain() {
+        let watch_only = test_wallet().export_watch_only(2).unwrap();
+        let asserter = Asserter::new();
+        asserter.push_success(&U256::from(5u64));
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter);
+
+        assert!(watch_only.scan_deposits(&provider).await.is_err());
+    }
+}
@@ -0,0 +1,576 @@
+//! On-disk wallet file format
+//!
+//! Wallets are stored either as legacy plaintext JSON or inside a versioned,
+//! password-encrypted container. The container derives a key from the password
+//! with Argon2id and seals the wallet JSON with XChaCha20-Poly1305. The header
+//! (format, version and KDF parameters) is authenticated as associated data, so
+//! tampering with the parameters makes decryption fail.
+//!
+//! Wallet files are never rewritten in place: a save writes a temporary file,
+//! fsyncs it and renames it over the wallet, keeping the previous versions as
+//! numbered backups. An open wallet holds an exclusive advisory lock on a
+//! `.lock` file next to it, so two processes cannot overwrite each other.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use fs2::FileExt;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Format identifier stored in the header of encrypted wallet files
+pub const WALLET_FILE_FORMAT: &str = "darkmint-wallet";
+
+/// Current version of the encrypted wallet container
+pub const WALLET_FILE_VERSION: u32 = 1;
+
+/// Environment variable the CLI uses to pass the wallet password around
+pub const WALLET_PASSWORD_ENV: &str = "DARKMINT_WALLET_PASSWORD";
+
+/// Number of previous wallet file versions kept as backups
+pub const WALLET_BACKUP_COUNT: usize = 5;
+
+const KDF_ALGORITHM: &str = "argon2id";
+const CIPHER_ALGORITHM: &str = "xchacha20poly1305";
+
+/// Argon2id memory cost in KiB
+const DEFAULT_M_COST: u32 = 64 * 1024;
+/// Argon2id number of passes
+const DEFAULT_T_COST: u32 = 3;
+/// Argon2id degree of parallelism
+const DEFAULT_P_COST: u32 = 1;
+
+const KDF_SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 24;
+const KEY_SIZE: usize = 32;
+
+/// Key derivation parameters stored in the wallet file header
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KdfParams {
+    /// KDF algorithm name (always "argon2id")
+    pub algorithm: String,
+    /// Memory cost in KiB
+    pub m_cost: u32,
+    /// Number of passes
+    pub t_cost: u32,
+    /// Degree of parallelism
+    pub p_cost: u32,
+    /// Hex-encoded random salt
+    pub salt: String,
+}
+
+impl KdfParams {
+    /// Create default KDF parameters with a fresh random salt
+    ///
+    /// # Returns
+    /// * New KDF parameters
+    pub fn new_random() -> Self {
+        Self::with_costs(DEFAULT_M_COST, DEFAULT_T_COST, DEFAULT_P_COST)
+    }
+
+    /// Create KDF parameters with explicit costs and a fresh random salt
+    ///
+    /// # Arguments
+    /// * `m_cost` - Memory cost in KiB
+    /// * `t_cost` - Number of passes
+    /// * `p_cost` - Degree of parallelism
+    ///
+    /// # Returns
+    /// * New KDF parameters
+    pub fn with_costs(m_cost: u32, t_cost: u32, p_cost: u32) -> Self {
+        let mut salt = [0u8; KDF_SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+
+        Self {
+            algorithm: KDF_ALGORITHM.to_string(),
+            m_cost,
+            t_cost,
+            p_cost,
+            salt: hex::encode(salt),
+        }
+    }
+
+    /// Derive the wallet encryption key from a password
+    ///
+    /// # Arguments
+    /// * `password` - Wallet password
+    ///
+    /// # Returns
+    /// * Result containing the derived key or an error
+    pub fn derive_key(&self, password: &str) -> Result<WalletKey, Box<dyn std::error::Error>> {
+        if self.algorithm != KDF_ALGORITHM {
+            return Err(format!("Unsupported KDF algorithm '{}'", self.algorithm).into());
+        }
+
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(KEY_SIZE))
+            .map_err(|e| format!("Invalid KDF parameters: {}", e))?;
+        let salt = hex::decode(&self.salt)?;
+
+        let mut key = Zeroizing::new([0u8; KEY_SIZE]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| format!("Key derivation failed: {}", e))?;
+
+        Ok(WalletKey {
+            key,
+            kdf: self.clone(),
+        })
+    }
+}
+
+/// Symmetric key derived from the wallet password
+///
+/// Kept in memory while the wallet is unlocked so that every save can
+/// re-encrypt the wallet without asking for the password again.
+#[derive(Clone)]
+pub struct WalletKey {
+    key: Zeroizing<[u8; KEY_SIZE]>,
+    kdf: KdfParams,
+}
+
+impl std::fmt::Debug for WalletKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalletKey")
+            .field("key", &"<redacted>")
+            .field("kdf", &self.kdf)
+            .finish()
+    }
+}
+
+/// Authenticated header of an encrypted wallet file
+#[derive(Debug, Serialize, Deserialize)]
+struct FileHeader {
+    format: String,
+    version: u32,
+    kdf: KdfParams,
+}
+
+/// Cipher parameters of an encrypted wallet file
+#[derive(Debug, Serialize, Deserialize)]
+struct CipherParams {
+    algorithm: String,
+    nonce: String,
+}
+
+/// Encrypted wallet container as stored on disk
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedWalletFile {
+    #[serde(flatten)]
+    header: FileHeader,
+    cipher: CipherParams,
+    ciphertext: String,
+}
+
+/// Decrypted wallet JSON together with the key that opened it
+pub type DecryptedWallet = (Zeroizing<Vec<u8>>, WalletKey);
+
+/// Check whether wallet file content is an encrypted container
+///
+/// # Arguments
+/// * `content` - Raw wallet file content
+///
+/// # Returns
+/// * true if the content is an encrypted container, false for legacy plaintext
+pub fn is_encrypted(content: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(content)
+        .ok()
+        .and_then(|value| value.get("format").and_then(|f| f.as_str().map(String::from)))
+        .is_some_and(|format| format == WALLET_FILE_FORMAT)
+}
+
+/// Seal wallet JSON into an encrypted container
+///
+/// # Arguments
+/// * `plaintext` - Serialized wallet JSON
+/// * `key` - Key derived from the wallet password
+///
+/// # Returns
+/// * Result containing the container as pretty JSON or an error
+pub fn encrypt(plaintext: &[u8], key: &WalletKey) -> Result<String, Box<dyn std::error::Error>> {
+    let header = FileHeader {
+        format: WALLET_FILE_FORMAT.to_string(),
+        version: WALLET_FILE_VERSION,
+        kdf: key.kdf.clone(),
+    };
+    let aad = serde_json::to_vec(&header)?;
+
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = XChaCha20Poly1305::new(key.key.as_ref().into());
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| "Wallet encryption failed")?;
+
+    let file = EncryptedWalletFile {
+        header,
+        cipher: CipherParams {
+            algorithm: CIPHER_ALGORITHM.to_string(),
+            nonce: hex::encode(nonce),
+        },
+        ciphertext: hex::encode(ciphertext),
+    };
+
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+/// Open an encrypted container with a password
+///
+/// # Arguments
+/// * `content` - Raw wallet file content
+/// * `password` - Wallet password
+///
+/// # Returns
+/// * Result containing the wallet JSON and the derived key, or an error
+pub fn decrypt(
+    content: &str,
+    password: &str,
+) -> Result<DecryptedWallet, Box<dyn std::error::Error>> {
+    let file: EncryptedWalletFile = serde_json::from_str(content)?;
+
+    if file.header.format != WALLET_FILE_FORMAT {
+        return Err(format!("Unknown wallet file format '{}'", file.header.format).into());
+    }
+    if file.header.version != WALLET_FILE_VERSION {
+        return Err(format!(
+            "Unsupported wallet file version {} (expected {})",
+            file.header.version, WALLET_FILE_VERSION
+        )
+        .into());
+    }
+    if file.cipher.algorithm != CIPHER_ALGORITHM {
+        return Err(format!("Unsupported cipher '{}'", file.cipher.algorithm).into());
+    }
+
+    let nonce = hex::decode(&file.cipher.nonce)?;
+    if nonce.len() != NONCE_SIZE {
+        return Err("Invalid nonce length in wallet file".into());
+    }
+    let ciphertext = hex::decode(&file.ciphertext)?;
+    let aad = serde_json::to_vec(&file.header)?;
+
+    let key = file.header.kdf.derive_key(password)?;
+    let cipher = XChaCha20Poly1305::new(key.key.as_ref().into());
+    let plaintext = cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| "Wrong wallet password or corrupted wallet file")?;
+
+    Ok((Zeroizing::new(plaintext), key))
+}
+
+/// Write a file readable and writable only by the owner (0600 on Unix)
+///
+/// # Arguments
+/// * `path` - File path
+/// * `data` - File content
+///
+/// # Returns
+/// * Result indicating success or error
+pub fn write_private(path: &Path, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // `mode` only applies on creation, so tighten files that already exist
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+/// Append a suffix to the file name of a path (`a/w.priv` -> `a/w.priv.lock`)
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Path of the `n`-th backup of a wallet file, 1 being the most recent
+///
+/// # Arguments
+/// * `path` - Wallet file path
+/// * `n` - Backup number
+///
+/// # Returns
+/// * Path of the backup file
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    with_suffix(path, &format!(".bak.{}", n))
+}
+
+/// Shift the backups of a file by one and back up its current content
+///
+/// The oldest backup beyond `keep` is dropped.
+fn rotate_backups(path: &Path, keep: usize) -> Result<(), Box<dyn std::error::Error>> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    let oldest = backup_path(path, keep);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for n in (1..keep).rev() {
+        let backup = backup_path(path, n);
+        if backup.exists() {
+            fs::rename(&backup, backup_path(path, n + 1))?;
+        }
+    }
+    // Copying keeps the current file in place until the new version replaces it
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+/// Delete every backup of a file
+///
+/// # Arguments
+/// * `path` - File whose backups are removed
+///
+/// # Returns
+/// * Result indicating success or error
+pub fn remove_backups(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    for n in 1..=WALLET_BACKUP_COUNT {
+        let backup = backup_path(path, n);
+        if backup.exists() {
+            fs::remove_file(&backup)?;
+        }
+    }
+    sync_dir(path.parent().unwrap_or(Path::new("")))
+}
+
+/// Flush a directory entry so that a rename in it survives a crash
+fn sync_dir(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        fs::File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Atomically replace a private file, keeping backups of its previous versions
+///
+/// The data is written to a temporary file in the same directory, flushed to
+/// disk and renamed over `path`, so a crash leaves either the old or the new
+/// version in place, never a truncated file.
+///
+/// # Arguments
+/// * `path` - File path
+/// * `data` - New file content
+/// * `backups` - Number of previous versions to keep (0 for none)
+///
+/// # Returns
+/// * Result indicating success or error
+pub fn write_private_atomic(
+    path: &Path,
+    data: &[u8],
+    backups: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = with_suffix(path, &format!(".{}.tmp", std::process::id()));
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let written = options.open(&tmp).and_then(|mut file| {
+        file.write_all(data)?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+
+    rotate_backups(path, backups)?;
+    fs::rename(&tmp, path)?;
+    sync_dir(path.parent().unwrap_or(Path::new("")))
+}
+
+/// Exclusive advisory lock on a wallet file
+///
+/// The lock is taken on `<wallet>.lock` rather than on the wallet itself,
+/// because atomic saves replace the wallet file. It is released when the
+/// value is dropped or the process exits.
+#[derive(Debug)]
+pub struct WalletLock {
+    file: fs::File,
+}
+
+impl WalletLock {
+    /// Lock a wallet file, failing if another process holds it
+    ///
+    /// # Arguments
+    /// * `path` - Wallet file path
+    ///
+    /// # Returns
+    /// * Result containing the held lock or an error
+    pub fn acquire(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            create_private_dir(dir)?;
+        }
+
+        let lock_path = with_suffix(path, ".lock");
+        let mut options = fs::OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(false);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options.open(&lock_path)?;
+
+        file.try_lock_exclusive().map_err(|_| {
+            format!(
+                "Wallet '{}' is in use by another DarkMint process",
+                path.display()
+            )
+        })?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for WalletLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// Create a directory (and its parents) accessible only by the owner (0700 on Unix)
+///
+/// # Arguments
+/// * `dir` - Directory path
+///
+/// # Returns
+/// * Result indicating success or error
+pub fn create_private_dir(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if dir.exists() {
+        return Ok(());
+    }
+
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key(password: &str) -> WalletKey {
+        // Cheap parameters keep the tests fast; production uses `new_random`
+        KdfParams::with_costs(1024, 1, 1).derive_key(password).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let key = test_key("correct horse");
+        let container = encrypt(b"{\"entropy\":\"00\"}", &key).unwrap();
+
+        assert!(is_encrypted(&container));
+        let (plaintext, _) = decrypt(&container, "correct horse").unwrap();
+        assert_eq!(plaintext.as_slice(), b"{\"entropy\":\"00\"}");
+    }
+
+    #[test]
+    fn test_decrypt_wrong_password() {
+        let container = encrypt(b"secret", &test_key("correct horse")).unwrap();
+        assert!(decrypt(&container, "battery staple").is_err());
+    }
+
+    #[test]
+    fn test_header_is_authenticated() {
+        let container = encrypt(b"secret", &test_key("pw")).unwrap();
+        let mut value: serde_json::Value = serde_json::from_str(&container).unwrap();
+        value["kdf"]["t_cost"] = serde_json::json!(2);
+
+        assert!(decrypt(&value.to_string(), "pw").is_err());
+    }
+
+    #[test]
+    fn test_plaintext_is_not_encrypted() {
+        assert!(!is_encrypted("{\"entropy\":\"00\",\"coins\":[]}"));
+        assert!(!is_encrypted("not json"));
+    }
+
+    #[test]
+    fn test_write_atomic_rotates_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.priv");
+
+        for version in 0..4 {
+            write_private_atomic(&path, format!("v{}", version).as_bytes(), 2).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v3");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "v2");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "v1");
+        assert!(!backup_path(&path, 3).exists());
+        // No temporary file is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_wallet_lock_is_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.priv");
+
+        let lock = WalletLock::acquire(&path).unwrap();
+        assert!(WalletLock::acquire(&path).is_err());
+        drop(lock);
+        assert!(WalletLock::acquire(&path).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.priv");
+        write_private(&path, b"data").unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
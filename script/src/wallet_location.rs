@@ -0,0 +1,248 @@
+//! Wallet file locations
+//!
+//! Wallets live in `<data dir>/darkmint/wallets/<name>.priv`, where the data
+//! dir is the platform data directory (`$XDG_DATA_HOME` on Linux) or
+//! `$DARKMINT_DATA_DIR` when set. The wallet to use is picked in this order:
+//!
+//! 1. the name given with `--wallet` (passed around as `$DARKMINT_WALLET`)
+//! 2. the default wallet stored in `<data dir>/darkmint/settings.json`
+//! 3. a legacy `burnth.priv` in the current directory, with a warning
+//! 4. the wallet named `default`
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{wallet_file, WALLET_FILENAME};
+
+/// Environment variable overriding the DarkMint data directory
+pub const DATA_DIR_ENV: &str = "DARKMINT_DATA_DIR";
+
+/// Environment variable the CLI uses to pass the selected wallet name around
+pub const WALLET_NAME_ENV: &str = "DARKMINT_WALLET";
+
+/// Name of the wallet used when none is selected
+pub const DEFAULT_WALLET_NAME: &str = "default";
+
+/// File extension of wallet files
+pub const WALLET_EXTENSION: &str = "priv";
+
+const SETTINGS_FILENAME: &str = "settings.json";
+
+/// Whether the legacy wallet warning has been printed already
+static LEGACY_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Get the DarkMint data directory
+///
+/// # Returns
+/// * Result containing the data directory or an error if none can be determined
+pub fn data_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if let Ok(dir) = env::var(DATA_DIR_ENV) {
+        return Ok(PathBuf::from(dir));
+    }
+    let base = dirs::data_dir().ok_or(format!(
+        "Cannot determine the data directory, set {}",
+        DATA_DIR_ENV
+    ))?;
+    Ok(base.join("darkmint"))
+}
+
+/// Get the directory holding named wallets
+pub fn wallets_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(data_dir()?.join("wallets"))
+}
+
+/// Check that a wallet name is usable as a file name
+///
+/// # Arguments
+/// * `name` - Wallet name
+///
+/// # Returns
+/// * Result indicating whether the name is valid
+pub fn validate_wallet_name(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!(
+            "Invalid wallet name '{}': use up to 64 letters, digits, '-' or '_'",
+            name
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Get the path of a named wallet
+///
+/// # Arguments
+/// * `name` - Wallet name
+///
+/// # Returns
+/// * Result containing the wallet file path or an error
+pub fn wallet_path(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    validate_wallet_name(name)?;
+    Ok(wallets_dir()?.join(format!("{}.{}", name, WALLET_EXTENSION)))
+}
+
+/// Persistent CLI settings
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Settings {
+    /// Wallet used when `--wallet` is not given
+    #[serde(default)]
+    pub default_wallet: Option<String>,
+}
+
+impl Settings {
+    /// Load the settings, or defaults when no settings file exists
+    ///
+    /// # Returns
+    /// * Result containing the settings or an error
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = data_dir()?.join(SETTINGS_FILENAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Save the settings to the data directory
+    ///
+    /// # Returns
+    /// * Result indicating success or error
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let dir = data_dir()?;
+        fs::create_dir_all(&dir)?;
+        wallet_file::write_private_atomic(
+            &dir.join(SETTINGS_FILENAME),
+            serde_json::to_string_pretty(self)?.as_bytes(),
+            0,
+        )
+    }
+}
+
+/// Resolve the wallet file to use
+///
+/// # Arguments
+/// * `name` - Wallet name, falls back to `$DARKMINT_WALLET` and the settings
+///
+/// # Returns
+/// * Result containing the wallet file path or an error
+pub fn resolve_wallet_path(name: Option<&str>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let name = match name
+        .map(str::to_string)
+        .or_else(|| env::var(WALLET_NAME_ENV).ok())
+    {
+        Some(name) => Some(name),
+        None => Settings::load()?.default_wallet,
+    };
+    if let Some(name) = name {
+        return wallet_path(&name);
+    }
+
+    if Path::new(WALLET_FILENAME).exists() {
+        if !LEGACY_WARNED.swap(true, Ordering::Relaxed) {
+            eprintln!(
+            "Warning: using legacy wallet '{}' from the current directory. Move it to '{}' to use it from anywhere.",
+            WALLET_FILENAME,
+            wallet_path(DEFAULT_WALLET_NAME)?.display()
+            );
+        }
+        return Ok(PathBuf::from(WALLET_FILENAME));
+    }
+
+    wallet_path(DEFAULT_WALLET_NAME)
+}
+
+/// Resolve the selected wallet file, failing when it does not exist
+///
+/// # Returns
+/// * Result containing the wallet file path or an error naming the missing wallet
+pub fn resolve_existing_wallet_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = resolve_wallet_path(None)?;
+    if !path.exists() {
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        return Err(format!(
+            "No wallet named '{}' at '{}'. Create it with 'darkmint --wallet {} wallet create'",
+            name,
+            path.display(),
+            name
+        )
+        .into());
+    }
+    Ok(path)
+}
+
+/// List the names of wallets in the wallet directory
+///
+/// # Returns
+/// * Result containing the sorted wallet names or an error
+pub fn list_wallets() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let dir = wallets_dir()?;
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut names = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some(WALLET_EXTENSION) {
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_wallet_name() {
+        assert!(validate_wallet_name("treasury").is_ok());
+        assert!(validate_wallet_name("cold_storage-2").is_ok());
+        assert!(validate_wallet_name("").is_err());
+        assert!(validate_wallet_name("../escape").is_err());
+        assert!(validate_wallet_name("a/b").is_err());
+        assert!(validate_wallet_name(&"x".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_create_and_open_wallet_at_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallets").join("treasury.priv");
+
+        let mut created = crate::Wallet::create(&path, None).unwrap();
+        assert!(path.exists());
+        assert!(crate::Wallet::create(&path, None).is_err());
+        created.set_chain_id(1);
+        let address = created.derive_burn_address(0).unwrap().address;
+
+        // The wallet stays locked until the handle is dropped
+        assert!(crate::Wallet::open(&path, None).is_err());
+        drop(created);
+
+        let mut opened = crate::Wallet::open(&path, None).unwrap();
+        opened.set_chain_id(1);
+        assert_eq!(opened.path(), path.as_path());
+        assert_eq!(opened.derive_burn_address(0).unwrap().address, address);
+
+        assert!(crate::Wallet::open(&dir.path().join("missing.priv"), None).is_err());
+    }
+
+    #[test]
+    fn test_settings_default_when_missing_field() {
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert!(settings.default_wallet.is_none());
+    }
+}